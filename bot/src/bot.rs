@@ -0,0 +1,318 @@
+// External Dependencies ------------------------------------------------------
+use std::net::SocketAddr;
+use std::sync::mpsc::Sender;
+use rand::{self, Rng, XorShiftRng};
+use shared::Lithium::Cobalt::ConnectionID;
+use shared::Lithium::{
+    Client, ClientHandle as Handle, ClientHandler,
+    DecodeError, DefaultRenderer, Entity, Incompatibility, Level
+};
+
+
+// Internal Dependencies ------------------------------------------------------
+use shared::{GAME_VERSION, SharedEvent, SharedInput, SharedLevel, SharedRegistry, SharedState, INPUT_FIRE};
+
+
+// Type Aliases ---------------------------------------------------------------
+pub type BotHandle<'a> = Handle<'a, Bot, DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, SharedState, SharedInput>;
+pub type BotClient = Client<Bot, DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, SharedState, SharedInput>;
+pub type BotEntity = Entity<SharedState, SharedInput, SharedLevel, DefaultRenderer>;
+pub type BotLevel = Level<SharedState, SharedLevel>;
+
+
+// Input Behaviour ------------------------------------------------------------
+#[derive(Debug, Copy, Clone)]
+pub enum Behaviour {
+
+    // Randomly change steering, thrust and firing every couple of ticks
+    Random,
+
+    // Constantly fly in circles while firing
+    Circle
+
+}
+
+impl Behaviour {
+    pub fn from_name(name: &str) -> Option<Behaviour> {
+        match name {
+            "random" => Some(Behaviour::Random),
+            "circle" => Some(Behaviour::Circle),
+            _ => None
+        }
+    }
+}
+
+
+// Statistics -----------------------------------------------------------------
+#[derive(Debug)]
+pub struct BotStats {
+    pub id: usize,
+    pub connected: bool,
+    pub joined: bool,
+    pub rtt: u32,
+    pub packet_loss: f32,
+    pub bytes_sent: u64,
+    pub bytes_received: u64
+}
+
+
+// Headless Bot Client --------------------------------------------------------
+pub struct Bot {
+    id: usize,
+    server_addr: SocketAddr,
+    behaviour: Behaviour,
+    stats: Sender<BotStats>,
+    rng: XorShiftRng,
+    connected: bool,
+    joined: bool,
+    input: SharedInput,
+    ticks: u32
+}
+
+impl Bot {
+
+    pub fn new(
+        id: usize,
+        server_addr: SocketAddr,
+        behaviour: Behaviour,
+        stats: Sender<BotStats>
+
+    ) -> Bot {
+        Bot {
+            id: id,
+            server_addr: server_addr,
+            behaviour: behaviour,
+            stats: stats,
+            rng: rand::weak_rng(),
+            connected: false,
+            joined: false,
+            input: SharedInput::default(),
+            ticks: 0
+        }
+    }
+
+    pub fn client(
+        id: usize,
+        server_addr: SocketAddr,
+        behaviour: Behaviour,
+        stats: Sender<BotStats>
+
+    ) -> BotClient {
+        let mut client = Client::new(
+            30,
+            SharedLevel::create(384, 384, 16),
+            SharedRegistry,
+            Bot::new(id, server_addr, behaviour, stats)
+        );
+        client.set_game_version(GAME_VERSION);
+        client
+    }
+
+    fn input(&mut self, tick: u8) -> SharedInput {
+        match self.behaviour {
+            Behaviour::Random => {
+                if self.ticks % 15 == 0 {
+                    self.input.steer = self.rng.gen_range(-1.0, 1.0);
+                    self.input.thrust = self.rng.gen_range(0.0, 1.0);
+                    self.input.buttons = if self.rng.gen() {
+                        INPUT_FIRE
+
+                    } else {
+                        0
+                    };
+                }
+                SharedInput {
+                    tick: tick,
+                    .. self.input.clone()
+                }
+            },
+            Behaviour::Circle => SharedInput {
+                tick: tick,
+                buttons: INPUT_FIRE,
+                steer: 1.0,
+                thrust: 1.0,
+                aim: 0.0
+            }
+        }
+    }
+
+    fn report(&self, handle: &BotHandle) {
+        self.stats.send(BotStats {
+            id: self.id,
+            connected: self.connected,
+            joined: self.joined,
+            rtt: handle.client.rtt() as u32,
+            packet_loss: handle.client.packet_loss() as f32,
+            bytes_sent: handle.client.bytes_sent() as u64,
+            bytes_received: handle.client.bytes_received() as u64
+
+        }).ok();
+    }
+
+}
+
+
+// Handler Implementation -----------------------------------------------------
+impl ClientHandler<DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, SharedState, SharedInput> for Bot {
+
+    fn init(&mut self, handle: BotHandle) {
+        handle.client.connect(self.server_addr).expect("Already connected!");
+    }
+
+    fn connect(&mut self, handle: BotHandle) {
+        println!("[Bot {}] Connected", self.id);
+        self.connected = true;
+        handle.events.send(SharedEvent::JoinGame {
+            name: format!("Bot {}", self.id),
+            color: None
+        });
+    }
+
+    fn disconnect(&mut self, handle: BotHandle, was_connected: bool, _: bool) {
+
+        if was_connected {
+            println!("[Bot {}] Connection lost, reconnecting...", self.id);
+        }
+
+        self.connected = false;
+        self.joined = false;
+        self.report(&handle);
+        handle.client.reset().ok();
+
+    }
+
+    fn config(&mut self, handle: BotHandle, level_data: &[u8]) {
+        match SharedLevel::create_from_serialized(level_data) {
+            Ok(level) => handle.level.set(level),
+            Err(err) => {
+                println!("[Bot {}] Received invalid level data: {:?}", self.id, err);
+                handle.client.close().ok();
+            }
+        }
+    }
+
+    fn incompatible(&mut self, _: BotHandle, reason: Incompatibility) {
+        println!("[Bot {}] Server is incompatible: {:?}", self.id, reason);
+    }
+
+    fn invalid_message(&mut self, _: BotHandle, err: DecodeError) {
+        println!("[Bot {}] Dropped invalid message: {:?}", self.id, err);
+    }
+
+    fn event(&mut self, _: BotHandle, _: ConnectionID, event: SharedEvent) {
+        match event {
+            SharedEvent::GameJoined { color, .. } => {
+                println!("[Bot {}] Joined the game as {:?}", self.id, color);
+                self.joined = true;
+            },
+            SharedEvent::JoinRejected(err) => {
+                println!("[Bot {}] Failed to join the game: {:?}", self.id, err);
+            },
+            _ => {}
+        }
+    }
+
+    fn tick_before(&mut self, _: BotHandle) {
+    }
+
+    fn tick_entity_before(
+        &mut self,
+        _: &mut DefaultRenderer,
+        _: &BotLevel,
+        entity: &mut BotEntity,
+        tick: u32, _: f32
+    ) {
+        if entity.local() && entity.type_id() == 0 {
+            let input = self.input(tick as u8);
+            entity.local_input(input);
+        }
+    }
+
+    fn tick_entity_after(
+        &mut self,
+        _: &mut DefaultRenderer,
+        _: &BotLevel,
+        _: &mut BotEntity,
+        _: u32, _: f32
+    ) {
+    }
+
+    fn tick_after(&mut self, handle: BotHandle) {
+
+        self.ticks = self.ticks.wrapping_add(1);
+
+        // Report roughly once per second
+        if self.ticks % 30 == 0 {
+            self.report(&handle);
+        }
+
+    }
+
+    fn draw(&mut self, _: BotHandle) {
+    }
+
+    fn destroy(&mut self, _: BotHandle) {
+    }
+
+}
+
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::sync::mpsc;
+    use shared::INPUT_FIRE;
+    use super::{Bot, Behaviour};
+
+    fn bot(behaviour: Behaviour) -> Bot {
+        let (stats, _) = mpsc::channel();
+        Bot::new(0, "127.0.0.1:7156".parse().unwrap(), behaviour, stats)
+    }
+
+    #[test]
+    fn test_behaviour_from_name() {
+        assert!(Behaviour::from_name("random").is_some());
+        assert!(Behaviour::from_name("circle").is_some());
+        assert!(Behaviour::from_name("idle").is_none());
+    }
+
+    #[test]
+    fn test_circle_input() {
+        let mut bot = bot(Behaviour::Circle);
+        let input = bot.input(42);
+        assert_eq!(input.tick, 42);
+        assert_eq!(input.buttons, INPUT_FIRE);
+        assert_eq!((input.steer, input.thrust), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_random_input() {
+
+        let mut bot = bot(Behaviour::Random);
+        let first = bot.input(0);
+
+        // Inputs are held for 15 ticks before they change again
+        for tick in 1..15 {
+            bot.ticks = tick;
+            let input = bot.input(tick as u8);
+            assert_eq!(input.tick, tick as u8);
+            assert_eq!(input.buttons, first.buttons);
+            assert_eq!((input.steer, input.thrust), (first.steer, first.thrust));
+        }
+
+        let changed = (0..20).any(|i| {
+            bot.ticks = 15 * (i + 1);
+            let input = bot.input(0);
+            (input.steer, input.thrust) != (first.steer, first.thrust)
+        });
+        assert!(changed);
+
+        let input = bot.input(0);
+        assert!(input.steer >= -1.0 && input.steer < 1.0);
+        assert!(input.thrust >= 0.0 && input.thrust < 1.0);
+
+    }
+
+}
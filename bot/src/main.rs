@@ -0,0 +1,144 @@
+#[macro_use]
+extern crate clap;
+extern crate rand;
+extern crate shared;
+
+
+// External Dependencies ------------------------------------------------------
+use std::thread;
+use std::str::FromStr;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::sync::mpsc::{self, Sender};
+use std::collections::HashMap;
+use shared::Lithium::DefaultRenderer;
+
+
+// Internal Dependencies ------------------------------------------------------
+mod bot;
+use bot::{Bot, Behaviour, BotStats};
+
+
+// Main -----------------------------------------------------------------------
+fn main() {
+
+    let args = clap::App::new("shooter-bot")
+        .version(&crate_version!())
+        .author("Ivo Wetzel <ivo.wetzel@googlemail.com>")
+        .about("Shooter Headless Bot Client")
+        .arg(clap::Arg::with_name("address:port")
+            .help("Remote server address to connect to.")
+            .index(1)
+
+        ).arg(clap::Arg::with_name("bots")
+            .help("Number of simulated players.")
+            .long("bots")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("behaviour")
+            .help("Input behaviour of the bots (random, circle).")
+            .long("behaviour")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("duration")
+            .help("Number of seconds to run before exiting.")
+            .long("duration")
+            .takes_value(true)
+
+        ).get_matches();
+
+
+    // Arguments --------------------------------------------------------------
+    let server_addr = value_t!(
+        args.value_of("address:port"), SocketAddr
+
+    ).unwrap_or(SocketAddr::from_str("127.0.0.1:31475").unwrap());
+
+    let bot_count = value_t!(args.value_of("bots"), usize).unwrap_or(8);
+    let duration = value_t!(args.value_of("duration"), u32).ok();
+    let behaviour = args.value_of("behaviour").and_then(
+        Behaviour::from_name
+
+    ).unwrap_or(Behaviour::Random);
+
+
+    // Bot Setup --------------------------------------------------------------
+    println!(
+        "[Bots] Connecting {} bots ({:?}) to {}...",
+        bot_count, behaviour, server_addr
+    );
+
+    let (sender, receiver) = mpsc::channel();
+    for id in 0..bot_count {
+
+        let sender = sender.clone();
+        thread::spawn(move || {
+            run_bot(id, server_addr, behaviour, sender);
+        });
+
+        // Spread out connection attempts
+        thread::sleep(Duration::from_millis(50));
+
+    }
+
+
+    // Statistics -------------------------------------------------------------
+    let mut stats: HashMap<usize, BotStats> = HashMap::new();
+    let mut last_totals: (u64, u64) = (0, 0);
+    let mut seconds = 0;
+    while duration.map_or(true, |d| seconds < d) {
+
+        thread::sleep(Duration::from_millis(1000));
+        seconds += 1;
+
+        while let Ok(s) = receiver.try_recv() {
+            stats.insert(s.id, s);
+        }
+
+        let connected = stats.values().filter(|s| s.connected).count();
+        let joined = stats.values().filter(|s| s.joined).count();
+        let (rtt, loss) = stats.values().filter(|s| s.connected).fold((0, 0.0), |t, s| {
+            (t.0 + s.rtt, t.1 + s.packet_loss)
+        });
+
+        let (rtt, loss) = if connected > 0 {
+            (rtt / connected as u32, loss / connected as f32)
+
+        } else {
+            (0, 0.0)
+        };
+
+        let totals = stats.values().fold((0, 0), |t, s| {
+            (t.0 + s.bytes_sent, t.1 + s.bytes_received)
+        });
+
+        println!(
+            "[Bots] {}s - connected: {}/{} - joined: {} - rtt: {}ms - loss: {:.2}% - sent/recv: {}/{} bytes ({}/{} bytes/s)",
+            seconds, connected, bot_count, joined, rtt, loss,
+            totals.0, totals.1,
+            totals.0.saturating_sub(last_totals.0),
+            totals.1.saturating_sub(last_totals.1)
+        );
+
+        last_totals = totals;
+
+    }
+
+}
+
+fn run_bot(id: usize, server_addr: SocketAddr, behaviour: Behaviour, stats: Sender<BotStats>) {
+
+    let mut renderer = DefaultRenderer;
+    let mut client = Bot::client(id, server_addr, behaviour, stats);
+    client.init(&mut renderer);
+
+    // The client stream generates ticks at the configured rate, so it only
+    // needs to be polled frequently enough
+    loop {
+        client.tick(&mut renderer);
+        client.draw(&mut renderer);
+        thread::sleep(Duration::from_millis(5));
+    }
+
+}
+
@@ -0,0 +1,63 @@
+// Internal Dependencies ------------------------------------------------------
+use shared::entities::Bullet;
+use shared::{SharedInput, SharedState, SharedLevel, Color, ColorName};
+use shared::Lithium::{Entity, EntityEvent, DrawableEntity, Level};
+use renderer::Renderer;
+
+
+// Bullet Drawable Implementation ---------------------------------------------
+pub struct RenderedBullet {
+    color: Color
+}
+
+impl RenderedBullet {
+
+    pub fn create_entity() -> Entity<SharedState, SharedInput, SharedLevel, Renderer> {
+        Entity::new(
+            Box::new(Bullet),
+            Box::new(RenderedBullet::new())
+        )
+    }
+
+    pub fn new() -> RenderedBullet {
+        RenderedBullet {
+            color: Color::from_name(ColorName::Grey)
+        }
+    }
+
+}
+
+impl DrawableEntity<SharedState, SharedLevel, Renderer> for RenderedBullet {
+
+    fn event(&mut self, event: &EntityEvent, _: &SharedState) {
+        if let &EntityEvent::Flags(flags) = event {
+            self.color = Color::from_flags(flags);
+        }
+    }
+
+    fn draw(
+        &mut self,
+        renderer: &mut Renderer,
+        _: &Level<SharedState, SharedLevel>,
+        state: SharedState
+    ) {
+
+        // Bullets are drawn as a short lived trail of particles
+        if let Some(p) = renderer.particle() {
+            p.color.set_to(&self.color);
+            p.x = state.x + 0.5;
+            p.y = state.y + 0.5;
+            p.s = 2.0;
+            p.sms = -4.0;
+            p.v = 0.0;
+            p.vms = 0.0;
+            p.r = state.r;
+            p.rms = 0.0;
+            p.fadeout = 0.1;
+            p.lifetime = 0.15;
+            p.remaining = p.lifetime;
+        }
+
+    }
+
+}
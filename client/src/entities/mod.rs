@@ -1,19 +1,22 @@
 // Internal Dependencies ------------------------------------------------------
 use shared::Lithium::{Entity, EntityRegistry};
-use shared::{SharedLevel, SharedState};
+use shared::{SharedInput, SharedLevel, SharedState};
 use renderer::Renderer;
 
+mod bullet;
 mod ship;
+pub use entities::bullet::RenderedBullet;
 pub use entities::ship::RenderedShip;
 
 
 // Entity Registry ------------------------------------------------------------
 pub struct Registry;
-impl EntityRegistry<SharedState, SharedLevel, Renderer> for Registry {
-    fn entity_from_type_id(&self, type_id: u8) -> Entity<SharedState, SharedLevel, Renderer> {
+impl EntityRegistry<SharedState, SharedInput, SharedLevel, Renderer> for Registry {
+    fn entity_from_type_id(&self, type_id: u8) -> Option<Entity<SharedState, SharedInput, SharedLevel, Renderer>> {
         match type_id {
-            0 => RenderedShip::create_entity(1.0),
-            _ => unreachable!()
+            0 => Some(RenderedShip::create_entity(1.0)),
+            1 => Some(RenderedBullet::create_entity()),
+            _ => None
         }
     }
 }
@@ -5,7 +5,7 @@ use std::{cmp, f32};
 
 // Internal Dependencies ------------------------------------------------------
 use shared::entities::Ship;
-use shared::{SharedState, SharedLevel, Color, ColorName};
+use shared::{SharedInput, SharedState, SharedLevel, Color, ColorName};
 use shared::Lithium::{Entity, EntityState, EntityEvent, DrawableEntity, Level};
 use renderer::Renderer;
 
@@ -15,12 +15,13 @@ pub struct RenderedShip {
     color_light: Color,
     color_mid: Color,
     scale: f32,
-    particle_count: u32
+    particle_count: u32,
+    frame: u32
 }
 
 impl RenderedShip {
 
-    pub fn create_entity(scale: f32) -> Entity<SharedState, SharedLevel, Renderer> {
+    pub fn create_entity(scale: f32) -> Entity<SharedState, SharedInput, SharedLevel, Renderer> {
         Entity::new(
             Box::new(Ship::new(scale)),
             Box::new(RenderedShip::new(scale))
@@ -32,7 +33,8 @@ impl RenderedShip {
             color_light: Color::from_name(ColorName::Grey),
             color_mid: Color::from_name(ColorName::Grey).darken(0.5),
             scale: scale,
-            particle_count: 5
+            particle_count: 5,
+            frame: 0
         }
     }
 
@@ -96,7 +98,11 @@ impl DrawableEntity<SharedState, SharedLevel, Renderer> for RenderedShip {
         state: SharedState
     ) {
 
-        self.draw_body(renderer, &state);
+        // Blink while invulnerable after respawning
+        self.frame = self.frame.wrapping_add(1);
+        if state.flags & 0x04 == 0 || (self.frame / 4) % 2 == 0 {
+            self.draw_body(renderer, &state);
+        }
 
         if state.flags & 0x02 == 0x02 {
 
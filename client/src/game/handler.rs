@@ -1,9 +1,9 @@
 // Internal Dependencies ------------------------------------------------------
-use shared::Lithium::ClientHandler;
+use shared::Lithium::{ClientHandler, DecodeError, Incompatibility};
 use shared::Lithium::Cobalt::ConnectionID;
 use entities::Registry;
 use renderer::Renderer;
-use shared::{SharedEvent, SharedLevel, SharedState};
+use shared::{SharedEvent, SharedInput, SharedLevel, SharedState};
 use game::{Game, ClientHandle, ClientEntity, ClientLevel};
 
 
@@ -40,7 +40,7 @@ macro_rules! with_view {
 
 
 // Handler Implementation -----------------------------------------------------
-impl ClientHandler<Renderer, Registry, SharedLevel, SharedEvent, SharedState> for Game {
+impl ClientHandler<Renderer, Registry, SharedLevel, SharedEvent, SharedState, SharedInput> for Game {
 
     fn init(&mut self, mut handle: ClientHandle) {
         with_view_state!(self, view, handle, {
@@ -54,6 +54,16 @@ impl ClientHandler<Renderer, Registry, SharedLevel, SharedEvent, SharedState> fo
         });
     }
 
+    fn incompatible(&mut self, mut handle: ClientHandle, reason: Incompatibility) {
+        with_view_state!(self, view, handle, {
+            view.incompatible(self, &mut handle, reason);
+        });
+    }
+
+    fn invalid_message(&mut self, _: ClientHandle, err: DecodeError) {
+        println!("[Client] Dropped invalid message: {:?}", err);
+    }
+
     fn connect(&mut self, mut handle: ClientHandle) {
         with_view_state!(self, view, handle, {
             view.connect(self, &mut handle);
@@ -83,7 +93,7 @@ impl ClientHandler<Renderer, Registry, SharedLevel, SharedEvent, SharedState> fo
         renderer: &mut Renderer,
         level: &ClientLevel,
         entity: &mut ClientEntity,
-        tick: u8, dt: f32
+        tick: u32, dt: f32
     ) {
         with_view!(self, view, {
             view.tick_entity_before(self, renderer, level, entity, tick, dt);
@@ -95,7 +105,7 @@ impl ClientHandler<Renderer, Registry, SharedLevel, SharedEvent, SharedState> fo
         renderer: &mut Renderer,
         level: &ClientLevel,
         entity: &mut ClientEntity,
-        tick: u8, dt: f32
+        tick: u32, dt: f32
     ) {
         with_view!(self, view, {
             view.tick_entity_after(self, renderer, level, entity, tick, dt);
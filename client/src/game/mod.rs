@@ -6,45 +6,92 @@ use std::net::SocketAddr;
 mod handler;
 mod views;
 use entities::Registry;
-use shared::Lithium::{Client, ClientHandle as Handle, Entity, Level, Renderer as LithiumRenderer};
-use shared::{SharedEvent, SharedState, SharedLevel};
+use shared::Lithium::{
+    Client, ClientHandle as Handle, Entity, Level, Replay, ReplayPlayer,
+    Renderer as LithiumRenderer
+};
+use shared::{GAME_VERSION, ColorName, SharedEvent, SharedInput, SharedState, SharedLevel};
 use renderer::Renderer;
 use self::views::View;
 
 
 // Type Aliases ---------------------------------------------------------------
-pub type ClientHandle<'a> = Handle<'a, Game, Renderer, Registry, SharedLevel, SharedEvent, SharedState>;
-pub type ClientEntity = Entity<SharedState, SharedLevel, Renderer>;
+pub type ClientHandle<'a> = Handle<'a, Game, Renderer, Registry, SharedLevel, SharedEvent, SharedState, SharedInput>;
+pub type ClientEntity = Entity<SharedState, SharedInput, SharedLevel, Renderer>;
 pub type ClientLevel = Level<SharedState, SharedLevel>;
 
 
 // Game -----------------------------------------------------------------------
 pub struct Game {
     server_addr: Option<SocketAddr>,
+    player_name: String,
+    player_color: Option<ColorName>,
+    spectate: bool,
     view: Option<Box<View>>,
     next_view: Option<Box<View>>
 }
 
 impl Game {
 
-    pub fn new(server_addr: Option<SocketAddr>) -> Game {
+    pub fn new(
+        server_addr: Option<SocketAddr>,
+        player_name: String,
+        player_color: Option<ColorName>,
+        spectate: bool
+
+    ) -> Game {
         Game {
             server_addr: server_addr,
+            player_name: player_name,
+            player_color: player_color,
+            spectate: spectate,
             view: Some(Box::new(views::InitView)),
             next_view: None
         }
     }
 
-    pub fn client(server_addr: Option<SocketAddr>) -> Client<
+    pub fn client(
+        server_addr: Option<SocketAddr>,
+        player_name: String,
+        player_color: Option<ColorName>,
+        spectate: bool
+
+    ) -> Client<
         Game, Renderer,
-        Registry, SharedLevel, SharedEvent, SharedState
+        Registry, SharedLevel, SharedEvent, SharedState, SharedInput
     > {
-        Client::new(
+        let mut client = Client::new(
             30,
             Game::default_level(),
             Registry,
-            Game::new(server_addr)
-        )
+            Game::new(server_addr, player_name, player_color, spectate)
+        );
+        client.set_game_version(GAME_VERSION);
+        client
+    }
+
+    // Creates a client which plays back a replay instead of connecting to
+    // a server
+    pub fn replay(replay: Replay) -> Client<
+        Game, Renderer,
+        Registry, SharedLevel, SharedEvent, SharedState, SharedInput
+    > {
+
+        let player = ReplayPlayer::new(replay);
+
+        let mut game = Game::new(None, "Spectator".to_string(), None, false);
+        game.view = Some(Box::new(views::ReplayView::new(player.clone())));
+
+        let mut client = Client::new(
+            player.tick_rate(),
+            Game::default_level(),
+            Registry,
+            game
+        );
+        client.set_game_version(GAME_VERSION);
+        client.set_transport(Box::new(player.transport()));
+        client
+
     }
 
     pub fn default_level() -> Level<SharedState, SharedLevel> {
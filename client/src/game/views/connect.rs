@@ -2,7 +2,7 @@
 use std::thread;
 use std::time::Duration;
 use std::net::{UdpSocket, SocketAddr};
-use shooter_server::game::Game as ServerGame;
+use shooter_server::game::{Game as ServerGame, MatchRules};
 
 
 // Internal Dependencies ------------------------------------------------------
@@ -10,7 +10,7 @@ use game::{Game, ClientHandle};
 use renderer::KeyCode;
 use shared::{Color, ColorName};
 use shared::Lithium::{Server, Renderer};
-use self::super::{View, MenuView, GameView};
+use self::super::{View, MenuView, GameView, SpectatorView};
 
 
 // View Implementation --------------------------------------------------------
@@ -62,7 +62,13 @@ impl View for ConnectView {
     }
 
     fn connect(&mut self, game: &mut Game, _: &mut ClientHandle) {
-        game.set_view(Box::new(GameView::new(self.server_addr.unwrap())));
+        let addr = self.server_addr.unwrap();
+        if game.spectate {
+            game.set_view(Box::new(SpectatorView::new(addr)));
+
+        } else {
+            game.set_view(Box::new(GameView::new(addr)));
+        }
     }
 
     fn disconnect(&mut self, _: &mut Game, handle: &mut ClientHandle, _: bool, _: bool) {
@@ -98,7 +104,7 @@ impl View for ConnectView {
 fn run_server(server_addr: SocketAddr, tick_rate: u32) {
     if let Err(err) = Server::run(
         server_addr,
-        ServerGame::server(tick_rate, true)
+        ServerGame::server(tick_rate, true, MatchRules::default())
     ) {
         println!("[Server] [Fatal] {:?}", err);
     }
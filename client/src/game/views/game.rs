@@ -4,24 +4,33 @@ use std::net::SocketAddr;
 
 // Internal Dependencies ------------------------------------------------------
 use shared::Lithium::Cobalt::ConnectionID;
-use renderer::{Renderer, KeyCode};
+use renderer::{Renderer, KeyCode, MouseButton};
 use level::RenderedLevel;
 use game::{Game, ClientHandle, ClientEntity, ClientLevel};
-use shared::Lithium::{EntityInput, EntityState, ClientHandler};
-use shared::{Color, ColorName, SharedEvent, SharedCommand};
-use self::super::{View, MenuView};
+use shared::Lithium::{EntityState, ClientHandler, Incompatibility};
+use shared::{Color, ColorName, SharedEvent, SharedCommand, SharedInput, SharedState, INPUT_AIM, INPUT_FIRE};
+use shared::entities::{Bullet, Ship};
+use self::super::{View, MenuView, Scoreboard};
 
 
 // View Implementation --------------------------------------------------------
 #[derive(Debug)]
 pub struct GameView {
-    server_addr: SocketAddr
+    server_addr: SocketAddr,
+    fired_bullets: Vec<(u8, SharedState)>,
+    last_fire_tick: Option<u8>,
+    mouse_aim: bool,
+    scoreboard: Scoreboard
 }
 
 impl GameView {
     pub fn new(server_addr: SocketAddr) -> GameView {
         GameView {
-            server_addr: server_addr
+            server_addr: server_addr,
+            fired_bullets: Vec::new(),
+            last_fire_tick: None,
+            mouse_aim: false,
+            scoreboard: Scoreboard::new()
         }
     }
 }
@@ -32,13 +41,30 @@ impl View for GameView {
         "Init"
     }
 
-    fn push(&mut self, _: &mut Game, handle: &mut ClientHandle) {
-        handle.events.send(SharedEvent::JoinGame);
+    fn push(&mut self, game: &mut Game, handle: &mut ClientHandle) {
+        handle.events.send(SharedEvent::JoinGame {
+            name: game.player_name.clone(),
+            color: game.player_color
+        });
     }
 
     fn config(&mut self, game: &mut Game, mut handle: &mut ClientHandle, level_data: &[u8]) {
-        handle.level.set(RenderedLevel::from_serialized(level_data));
-        game.reset(&mut handle);
+        match RenderedLevel::from_serialized(level_data) {
+            Ok(level) => {
+                handle.level.set(level);
+                game.reset(&mut handle);
+            },
+            Err(err) => {
+                println!("[Client] Received invalid level data: {:?}", err);
+                handle.client.close().ok();
+                game.set_view(Box::new(MenuView));
+            }
+        }
+    }
+
+    fn incompatible(&mut self, game: &mut Game, _: &mut ClientHandle, reason: Incompatibility) {
+        println!("[Client] Server is incompatible: {:?}", reason);
+        game.set_view(Box::new(MenuView));
     }
 
     fn disconnect(&mut self, game: &mut Game, _: &mut ClientHandle, was_connected: bool, by_remote: bool) {
@@ -50,17 +76,55 @@ impl View for GameView {
         game.set_view(Box::new(MenuView));
     }
 
-    fn event(&mut self, _: &mut Game, _: &mut ClientHandle, owner: ConnectionID, event: SharedEvent) {
-        println!("[Client] Event: {:?} {:?}", owner, event);
+    fn event(&mut self, game: &mut Game, handle: &mut ClientHandle, owner: ConnectionID, event: SharedEvent) {
+        match event {
+            SharedEvent::BulletConfirmed { local_id, entity } => {
+                handle.entities.confirm_predicted(local_id as u16, entity);
+            },
+            SharedEvent::GameJoined { id, color } => {
+                println!("[Client] Joined the game as {:?}", color);
+                self.scoreboard.set_player(id);
+            },
+            SharedEvent::JoinRejected(err) => {
+                println!("[Client] Failed to join the game: {:?}", err);
+                handle.client.close().ok();
+                game.set_view(Box::new(MenuView));
+            },
+            SharedEvent::PlayerJoined { id, name, color } => {
+                println!("[Client] {} joined the game ({:?})", name, color);
+                self.scoreboard.add_player(id, name, color);
+            },
+            SharedEvent::PlayerLeft { id, name } => {
+                println!("[Client] {} left the game", name);
+                self.scoreboard.remove_player(id);
+            },
+            SharedEvent::PlayerKilled { killer, victim } => {
+                println!(
+                    "[Client] {} was killed by {}",
+                    self.scoreboard.player_name(victim),
+                    self.scoreboard.player_name(killer)
+                );
+            },
+            SharedEvent::ScoreUpdate(scores) => {
+                self.scoreboard.set_scores(scores);
+            },
+            SharedEvent::RoundEnded { winner } => {
+                self.scoreboard.end_round(winner);
+            },
+            SharedEvent::RoundStarted => {
+                self.scoreboard.start_round();
+            },
+            _ => println!("[Client] Event: {:?} {:?}", owner, event)
+        }
     }
 
     fn tick_before(&mut self, _: &mut Game, handle: &mut ClientHandle) {
         let tick = handle.entities.tick();
         handle.renderer.reseed_rng([
-            ((tick as u32 + 7) * 941) as u32,
-            ((tick as u32 + 659) * 461) as u32,
-            ((tick as u32 + 13) * 227) as u32,
-            ((tick as u32 + 97) * 37) as u32
+            tick.wrapping_add(7).wrapping_mul(941),
+            tick.wrapping_add(659).wrapping_mul(461),
+            tick.wrapping_add(13).wrapping_mul(227),
+            tick.wrapping_add(97).wrapping_mul(37)
         ]);
     }
 
@@ -70,33 +134,73 @@ impl View for GameView {
         renderer: &mut Renderer,
         _: &ClientLevel,
         entity: &mut ClientEntity,
-        tick: u8, _: f32
+        tick: u32, dt: f32
     ) {
 
-        if entity.local() {
+        // Only ships are controlled by the player
+        if entity.local() && entity.type_id() == 0 {
+
+            let mut input = SharedInput {
+                tick: tick as u8,
+                .. Default::default()
+            };
 
-            let mut buttons = 0;
             if renderer.key_down(KeyCode::A) || renderer.key_down(KeyCode::Left) {
-                buttons |= 0x01;
+                input.steer -= 1.0;
             }
 
             if renderer.key_down(KeyCode::D) || renderer.key_down(KeyCode::Right) {
-                buttons |= 0x02;
+                input.steer += 1.0;
+            }
+
+            // Mouse aiming takes over once the mouse is moved and is given up
+            // again as soon as the ship is steered with the keyboard
+            if input.steer != 0.0 {
+                self.mouse_aim = false;
+
+            } else if renderer.mouse_moved() {
+                self.mouse_aim = true;
+            }
+
+            if self.mouse_aim {
+                let (x, y) = renderer.mouse_position();
+                input.buttons |= INPUT_AIM;
+                input.aim = (y - entity.state().y).atan2(x - entity.state().x);
+            }
+
+            if renderer.key_down(KeyCode::W) || renderer.key_down(KeyCode::Up)
+                || renderer.mouse_down(MouseButton::Right) {
+                input.thrust = 1.0;
             }
 
-            if renderer.key_down(KeyCode::W) || renderer.key_down(KeyCode::Up) {
-                buttons |= 0x04;
+            if renderer.key_down(KeyCode::Space) || renderer.mouse_down(MouseButton::Left) {
+
+                let can_fire = self.last_fire_tick.map_or(true, |last_tick| {
+                    input.tick.wrapping_sub(last_tick) >= Bullet::fire_interval()
+                });
+
+                // Pre-create a local bullet which will later be replaced by
+                // the one created by the server
+                if can_fire {
+                    input.buttons |= INPUT_FIRE;
+                    self.last_fire_tick = Some(input.tick);
+                    self.fired_bullets.push((input.tick, Bullet::fire(entity.state(), dt)));
+                }
+
             }
 
-            entity.local_input(EntityInput {
-                tick: tick,
-                fields: buttons
-            });
+            entity.local_input(input);
 
         }
 
     }
 
+    fn tick_after(&mut self, _: &mut Game, handle: &mut ClientHandle) {
+        for (tick, state) in self.fired_bullets.drain(..) {
+            handle.entities.create_predicted(tick as u16, 1, state);
+        }
+    }
+
     fn draw(&mut self, _: &mut Game, handle: &mut ClientHandle) {
 
         handle.renderer.clear(&Color::from_name(ColorName::Black));
@@ -105,12 +209,16 @@ impl View for GameView {
         handle.renderer.draw_particles();
 
         let network_state = format!(
-            "Press ESC to return to Menu - {}\nPing: {}ms - sent/recv: {}/{} - loss: {:.2}%",
+            "Press ESC to return to Menu - {}\nPing: {}ms - sent/recv: {}/{} - loss: {:.2}% - lead: {:.1} ticks - interp: {:.1} ticks - error: {:.1}/{:.1}",
             self.server_addr,
             handle.client.rtt() / 2,
             handle.client.bytes_sent(),
             handle.client.bytes_received(),
-            handle.client.packet_loss()
+            handle.client.packet_loss(),
+            handle.clock.lead(),
+            handle.entities.interpolation_delay(),
+            handle.entities.average_misprediction(),
+            handle.entities.misprediction()
         );
 
         handle.renderer.text(
@@ -119,6 +227,29 @@ impl View for GameView {
             &network_state[..]
         );
 
+        // Health display for the local ship
+        let ship_state = handle.entities.entities().find(|e| {
+            e.local() && e.type_id() == 0
+
+        }).map(|e| format!(
+            "Hull: {}/{} - Shield: {}/{}",
+            e.state().hp, Ship::max_hp(),
+            e.state().shield, Ship::max_shield()
+        ));
+
+        if let Some(ship_state) = ship_state {
+            handle.renderer.text(
+                &Color::from_name(ColorName::White),
+                0.0, 24.0,
+                &ship_state[..]
+            );
+        }
+
+        // Show the scoreboard while Tab is held or the round is over
+        if self.scoreboard.round_over() || handle.renderer.key_down(KeyCode::Tab) {
+            self.scoreboard.draw(handle.renderer, 48.0);
+        }
+
         if handle.renderer.key_released(KeyCode::Escape) {
             handle.events.send(SharedEvent::LeaveGame);
             handle.events.send(SharedEvent::Command(SharedCommand::Shutdown));
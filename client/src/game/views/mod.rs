@@ -2,6 +2,7 @@
 use renderer::Renderer;
 use shared::SharedEvent;
 use shared::Lithium::Cobalt::ConnectionID;
+use shared::Lithium::Incompatibility;
 use super::{Game, ClientHandle, ClientLevel, ClientEntity};
 
 
@@ -10,10 +11,16 @@ mod connect;
 mod init;
 mod menu;
 mod game;
+mod replay;
+mod scoreboard;
+mod spectator;
 pub use self::connect::ConnectView;
 pub use self::init::InitView;
 pub use self::menu::MenuView;
 pub use self::game::GameView;
+pub use self::replay::ReplayView;
+pub use self::scoreboard::Scoreboard;
+pub use self::spectator::SpectatorView;
 
 
 // View Trait -----------------------------------------------------------------
@@ -31,19 +38,21 @@ pub trait View {
 
     fn config(&mut self, _: &mut Game, _: &mut ClientHandle, _: &[u8]) {}
 
+    fn incompatible(&mut self, _: &mut Game, _: &mut ClientHandle, _: Incompatibility) {}
+
     fn event(&mut self, _: &mut Game, _: &mut ClientHandle, _: ConnectionID, _: SharedEvent) {}
 
     fn tick_before(&mut self, _: &mut Game, _: &mut ClientHandle) {}
 
     fn tick_entity_before(
         &mut self, _: &mut Game, _: &mut Renderer, _: &ClientLevel,
-        _: &mut ClientEntity, _: u8, _: f32
+        _: &mut ClientEntity, _: u32, _: f32
     ) {
     }
 
     fn tick_entity_after(
         &mut self, _: &mut Game, _: &mut Renderer, _: &ClientLevel,
-        _: &mut ClientEntity, _: u8, _: f32
+        _: &mut ClientEntity, _: u32, _: f32
     ) {
     }
 
@@ -0,0 +1,194 @@
+// External Dependencies ------------------------------------------------------
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+
+// Internal Dependencies ------------------------------------------------------
+use shared::Lithium::Cobalt::ConnectionID;
+use renderer::KeyCode;
+use level::RenderedLevel;
+use game::{Game, ClientHandle};
+use shared::Lithium::{ReplayPlayer, Incompatibility};
+use shared::{Color, ColorName, SharedEvent};
+use self::super::{View, Scoreboard};
+
+
+// View Implementation --------------------------------------------------------
+pub struct ReplayView {
+    player: ReplayPlayer,
+    scoreboard: Scoreboard,
+    error: Option<String>
+}
+
+impl ReplayView {
+
+    pub fn new(player: ReplayPlayer) -> ReplayView {
+        ReplayView {
+            player: player,
+            scoreboard: Scoreboard::new(),
+            error: None
+        }
+    }
+
+    pub fn seek_interval() -> usize {
+        5
+    }
+
+    fn seek(&mut self, frame: usize) {
+        // Events are replayed from the start when seeking backwards
+        if frame < self.player.frame() {
+            self.scoreboard.clear();
+        }
+        self.player.seek(frame);
+    }
+
+    fn format_time(&self, frame: usize) -> String {
+        let seconds = frame / self.player.tick_rate() as usize;
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+
+}
+
+impl View for ReplayView {
+
+    fn name(&self) -> &str {
+        "Replay"
+    }
+
+    fn init(&mut self, game: &mut Game, handle: &mut ClientHandle) {
+        println!("[Client] Starting replay...");
+
+        // The replay transport does not use the address
+        let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+        handle.client.connect(addr).expect("Replay already started!");
+        game.reset(handle);
+    }
+
+    fn config(&mut self, game: &mut Game, mut handle: &mut ClientHandle, level_data: &[u8]) {
+        match RenderedLevel::from_serialized(level_data) {
+            Ok(level) => {
+                handle.level.set(level);
+                game.reset(&mut handle);
+            },
+            Err(err) => {
+                println!("[Client] Replay contains invalid level data: {:?}", err);
+                self.error = Some(format!("Invalid level data: {:?}", err));
+                handle.client.close().ok();
+            }
+        }
+    }
+
+    fn incompatible(&mut self, _: &mut Game, _: &mut ClientHandle, reason: Incompatibility) {
+        println!("[Client] Replay is incompatible: {:?}", reason);
+        self.error = Some(format!("Incompatible replay: {:?}", reason));
+    }
+
+    fn event(&mut self, _: &mut Game, _: &mut ClientHandle, _: ConnectionID, event: SharedEvent) {
+        match event {
+            SharedEvent::PlayerJoined { id, name, color } => {
+                self.scoreboard.add_player(id, name, color);
+            },
+            SharedEvent::PlayerLeft { id, .. } => {
+                self.scoreboard.remove_player(id);
+            },
+            SharedEvent::ScoreUpdate(scores) => {
+                self.scoreboard.set_scores(scores);
+            },
+            SharedEvent::RoundEnded { winner } => {
+                self.scoreboard.end_round(winner);
+            },
+            SharedEvent::RoundStarted => {
+                self.scoreboard.start_round();
+            },
+            _ => {}
+        }
+    }
+
+    fn tick_before(&mut self, _: &mut Game, handle: &mut ClientHandle) {
+        let tick = handle.entities.tick();
+        handle.renderer.reseed_rng([
+            tick.wrapping_add(7).wrapping_mul(941),
+            tick.wrapping_add(659).wrapping_mul(461),
+            tick.wrapping_add(13).wrapping_mul(227),
+            tick.wrapping_add(97).wrapping_mul(37)
+        ]);
+    }
+
+    fn draw(&mut self, _: &mut Game, handle: &mut ClientHandle) {
+
+        if let Some(ref error) = self.error {
+            handle.renderer.clear(&Color::from_name(ColorName::Black));
+            handle.renderer.text(
+                &Color::from_name(ColorName::White),
+                0.0, 0.0,
+                &error[..]
+            );
+            return;
+        }
+
+        // Playback Controls
+        let seek_frames = ReplayView::seek_interval() * self.player.tick_rate() as usize;
+        if handle.renderer.key_released(KeyCode::Space) {
+            let paused = self.player.paused();
+            self.player.set_paused(!paused);
+        }
+
+        if handle.renderer.key_released(KeyCode::Left) {
+            let frame = self.player.frame().saturating_sub(seek_frames);
+            self.seek(frame);
+
+        } else if handle.renderer.key_released(KeyCode::Right) {
+            let frame = self.player.frame() + seek_frames;
+            self.seek(frame);
+
+        } else if handle.renderer.key_released(KeyCode::Enter) {
+            self.seek(0);
+        }
+
+        if handle.renderer.key_released(KeyCode::Up) {
+            let speed = self.player.speed();
+            self.player.set_speed((speed * 2.0).min(8.0));
+
+        } else if handle.renderer.key_released(KeyCode::Down) {
+            let speed = self.player.speed();
+            self.player.set_speed((speed * 0.5).max(0.25));
+        }
+
+        self.player.advance(handle.renderer.delta_time() * 1000.0);
+
+        // Drawing
+        handle.renderer.clear(&Color::from_name(ColorName::Black));
+        handle.level.draw(handle.renderer);
+        handle.entities.draw(handle.renderer, handle.level);
+        handle.renderer.draw_particles();
+
+        let status = if self.player.finished() {
+            "End of replay".to_string()
+
+        } else if self.player.paused() {
+            "Paused".to_string()
+
+        } else {
+            format!("{}x", self.player.speed())
+        };
+
+        let playback_state = format!(
+            "Replay {} / {} - {}\nSpace: Pause - Left/Right: Seek - Up/Down: Speed - Enter: Restart",
+            self.format_time(self.player.frame()),
+            self.format_time(self.player.frames()),
+            status
+        );
+
+        handle.renderer.text(
+            &Color::from_name(ColorName::White),
+            0.0, 0.0,
+            &playback_state[..]
+        );
+
+        if self.scoreboard.round_over() || handle.renderer.key_down(KeyCode::Tab) {
+            self.scoreboard.draw(handle.renderer, 48.0);
+        }
+
+    }
+
+}
+
@@ -0,0 +1,109 @@
+// External Dependencies ------------------------------------------------------
+use std::collections::HashMap;
+
+
+// Internal Dependencies ------------------------------------------------------
+use renderer::Renderer;
+use shared::{Color, ColorName, PlayerScore};
+
+
+// Scoreboard -----------------------------------------------------------------
+#[derive(Debug)]
+pub struct Scoreboard {
+    scores: Vec<PlayerScore>,
+    round_over: bool,
+    round_winner: Option<u8>,
+    player_id: Option<u32>,
+    roster: HashMap<u32, (String, ColorName)>
+}
+
+impl Scoreboard {
+
+    pub fn new() -> Scoreboard {
+        Scoreboard {
+            scores: Vec::new(),
+            round_over: false,
+            round_winner: None,
+            player_id: None,
+            roster: HashMap::new()
+        }
+    }
+
+    pub fn set_player(&mut self, id: u32) {
+        self.player_id = Some(id);
+    }
+
+    pub fn add_player(&mut self, id: u32, name: String, color: ColorName) {
+        self.roster.insert(id, (name, color));
+    }
+
+    pub fn remove_player(&mut self, id: u32) {
+        self.roster.remove(&id);
+    }
+
+    pub fn player_name(&self, id: u32) -> String {
+        self.roster.get(&id).map_or_else(|| format!("#{}", id), |p| p.0.clone())
+    }
+
+    pub fn player_for_color(&self, color: u8) -> Option<String> {
+        self.scores.iter().find(|s| s.color == color).map(|s| self.player_name(s.id))
+    }
+
+    pub fn set_scores(&mut self, scores: Vec<PlayerScore>) {
+        self.scores = scores;
+    }
+
+    pub fn round_over(&self) -> bool {
+        self.round_over
+    }
+
+    pub fn start_round(&mut self) {
+        self.round_over = false;
+        self.round_winner = None;
+    }
+
+    pub fn end_round(&mut self, winner: Option<u8>) {
+        self.round_over = true;
+        self.round_winner = winner;
+    }
+
+    pub fn clear(&mut self) {
+        self.scores.clear();
+        self.roster.clear();
+        self.start_round();
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, mut y: f32) {
+
+        if self.round_over {
+            let result = match self.round_winner {
+                Some(color) => format!("Round over - {:?} wins!", ColorName::from_u8(color)),
+                None => "Round over - Draw!".to_string()
+            };
+            renderer.text(&Color::from_name(ColorName::White), 0.0, y, &result[..]);
+            y += 24.0;
+        }
+
+        renderer.text(
+            &Color::from_name(ColorName::White),
+            0.0, y,
+            " Player      Score  Kills  Deaths"
+        );
+
+        for score in &self.scores {
+            y += 12.0;
+            renderer.text(
+                &Color::from_u8(score.color),
+                0.0, y,
+                &format!(
+                    "{}{:<10}  {:>5}  {:>5}  {:>6}",
+                    if self.player_id == Some(score.id) { ">" } else { " " },
+                    self.player_name(score.id), score.score, score.kills, score.deaths
+                )[..]
+            );
+        }
+
+    }
+
+}
+
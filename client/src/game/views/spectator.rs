@@ -0,0 +1,308 @@
+// External Dependencies ------------------------------------------------------
+use std::net::SocketAddr;
+
+
+// Internal Dependencies ------------------------------------------------------
+use shared::Lithium::Cobalt::ConnectionID;
+use renderer::KeyCode;
+use level::RenderedLevel;
+use game::{Game, ClientHandle};
+use shared::Lithium::Incompatibility;
+use shared::{Color, ColorName, SharedEvent, SharedCommand};
+use self::super::{View, MenuView, Scoreboard};
+
+
+// Camera Modes ---------------------------------------------------------------
+#[derive(Debug, Eq, PartialEq)]
+enum CameraMode {
+    Free,
+    Follow
+}
+
+
+// View Implementation --------------------------------------------------------
+#[derive(Debug)]
+pub struct SpectatorView {
+    server_addr: SocketAddr,
+    scoreboard: Scoreboard,
+    mode: CameraMode,
+    camera: Option<(f32, f32)>,
+    target: Option<(f32, f32)>,
+    followed: Option<(u16, u8)>
+}
+
+impl SpectatorView {
+
+    pub fn new(server_addr: SocketAddr) -> SpectatorView {
+        SpectatorView {
+            server_addr: server_addr,
+            scoreboard: Scoreboard::new(),
+            mode: CameraMode::Follow,
+            camera: None,
+            target: None,
+            followed: None
+        }
+    }
+
+    pub fn zoom() -> f32 {
+        2.0
+    }
+
+    pub fn pan_speed() -> f32 {
+        240.0
+    }
+
+    // Switches to the next ship in order of its entity id, wrapping around
+    // at the end
+    fn follow_next(&mut self, handle: &ClientHandle) {
+
+        let mut ships: Vec<(u16, u8)> = handle.entities.entities().filter(|e| {
+            e.type_id() == 0
+
+        }).map(|e| (e.id(), e.state().flags & 0b1111_0000)).collect();
+
+        ships.sort();
+
+        let current = self.followed.map(|f| f.0);
+        self.followed = ships.iter().find(|s| {
+            current.map_or(true, |id| s.0 > id)
+
+        }).or_else(|| ships.first()).cloned();
+
+    }
+
+    // Returns the position of the followed ship, a ship which disappeared is
+    // replaced with the next ship of the same color so respawns are followed
+    fn follow_position(&mut self, handle: &ClientHandle) -> Option<(f32, f32)> {
+
+        let followed = match self.followed {
+            Some(followed) => followed,
+            None => return None
+        };
+
+        let ship = handle.entities.get_entity(followed.0).and_then(|e| {
+            if e.type_id() == 0 {
+                Some(e)
+
+            } else {
+                None
+            }
+
+        }).or_else(|| handle.entities.entities().find(|e| {
+            e.type_id() == 0 && e.state().flags & 0b1111_0000 == followed.1
+        }));
+
+        match ship {
+            Some(ship) => {
+                self.followed = Some((ship.id(), ship.state().flags & 0b1111_0000));
+                Some((ship.state().x, ship.state().y))
+            },
+            None => None
+        }
+
+    }
+
+    fn update_camera(&mut self, handle: &mut ClientHandle) {
+
+        let dt = handle.renderer.delta_time();
+        let center = (
+            handle.level.width() as f32 * 0.5,
+            handle.level.height() as f32 * 0.5
+        );
+
+        if handle.renderer.key_released(KeyCode::Enter) {
+            self.mode = if self.mode == CameraMode::Free {
+                CameraMode::Follow
+
+            } else {
+                CameraMode::Free
+            };
+        }
+
+        if self.mode == CameraMode::Follow {
+
+            if handle.renderer.key_released(KeyCode::Space) || self.followed.is_none() {
+                self.follow_next(handle);
+            }
+
+            if let Some(position) = self.follow_position(handle) {
+                self.target = Some(position);
+            }
+
+        } else {
+
+            let (mut x, mut y) = self.target.unwrap_or(center);
+            let speed = SpectatorView::pan_speed() * dt;
+            if handle.renderer.key_down(KeyCode::A) || handle.renderer.key_down(KeyCode::Left) {
+                x -= speed;
+            }
+
+            if handle.renderer.key_down(KeyCode::D) || handle.renderer.key_down(KeyCode::Right) {
+                x += speed;
+            }
+
+            if handle.renderer.key_down(KeyCode::W) || handle.renderer.key_down(KeyCode::Up) {
+                y -= speed;
+            }
+
+            if handle.renderer.key_down(KeyCode::Down) {
+                y += speed;
+            }
+
+            self.target = Some((
+                x.max(0.0).min(handle.level.width() as f32),
+                y.max(0.0).min(handle.level.height() as f32)
+            ));
+
+        }
+
+        // Smoothly move the camera towards its target
+        let target = self.target.unwrap_or(center);
+        let (x, y) = self.camera.unwrap_or(target);
+        let u = (dt * 8.0).min(1.0);
+        self.camera = Some((x + (target.0 - x) * u, y + (target.1 - y) * u));
+
+    }
+
+}
+
+impl View for SpectatorView {
+
+    fn name(&self) -> &str {
+        "Spectator"
+    }
+
+    fn push(&mut self, _: &mut Game, handle: &mut ClientHandle) {
+        handle.events.send(SharedEvent::Spectate);
+    }
+
+    fn config(&mut self, game: &mut Game, mut handle: &mut ClientHandle, level_data: &[u8]) {
+        match RenderedLevel::from_serialized(level_data) {
+            Ok(level) => {
+                handle.level.set(level);
+                game.reset(&mut handle);
+            },
+            Err(err) => {
+                println!("[Client] Received invalid level data: {:?}", err);
+                handle.client.close().ok();
+                game.set_view(Box::new(MenuView));
+            }
+        }
+    }
+
+    fn incompatible(&mut self, game: &mut Game, _: &mut ClientHandle, reason: Incompatibility) {
+        println!("[Client] Server is incompatible: {:?}", reason);
+        game.set_view(Box::new(MenuView));
+    }
+
+    fn disconnect(&mut self, game: &mut Game, _: &mut ClientHandle, was_connected: bool, by_remote: bool) {
+        match (was_connected, by_remote) {
+            (true, true) => println!("[Client] Connection closed."),
+            (true, false) => println!("[Client] Connection lost."),
+            (false, _) => println!("[Client] Connection failed."),
+        }
+        game.set_view(Box::new(MenuView));
+    }
+
+    fn event(&mut self, game: &mut Game, handle: &mut ClientHandle, owner: ConnectionID, event: SharedEvent) {
+        match event {
+            SharedEvent::SpectateAccepted => {
+                println!("[Client] Spectating the game");
+            },
+            SharedEvent::JoinRejected(err) => {
+                println!("[Client] Failed to spectate the game: {:?}", err);
+                handle.client.close().ok();
+                game.set_view(Box::new(MenuView));
+            },
+            SharedEvent::PlayerJoined { id, name, color } => {
+                println!("[Client] {} joined the game ({:?})", name, color);
+                self.scoreboard.add_player(id, name, color);
+            },
+            SharedEvent::PlayerLeft { id, name } => {
+                println!("[Client] {} left the game", name);
+                self.scoreboard.remove_player(id);
+            },
+            SharedEvent::PlayerKilled { killer, victim } => {
+                println!(
+                    "[Client] {} was killed by {}",
+                    self.scoreboard.player_name(victim),
+                    self.scoreboard.player_name(killer)
+                );
+            },
+            SharedEvent::ScoreUpdate(scores) => {
+                self.scoreboard.set_scores(scores);
+            },
+            SharedEvent::RoundEnded { winner } => {
+                self.scoreboard.end_round(winner);
+            },
+            SharedEvent::RoundStarted => {
+                self.scoreboard.start_round();
+            },
+            _ => println!("[Client] Event: {:?} {:?}", owner, event)
+        }
+    }
+
+    fn tick_before(&mut self, _: &mut Game, handle: &mut ClientHandle) {
+        let tick = handle.entities.tick();
+        handle.renderer.reseed_rng([
+            tick.wrapping_add(7).wrapping_mul(941),
+            tick.wrapping_add(659).wrapping_mul(461),
+            tick.wrapping_add(13).wrapping_mul(227),
+            tick.wrapping_add(97).wrapping_mul(37)
+        ]);
+    }
+
+    fn draw(&mut self, _: &mut Game, handle: &mut ClientHandle) {
+
+        self.update_camera(handle);
+
+        // World
+        let (x, y) = self.camera.unwrap_or((0.0, 0.0));
+        handle.renderer.clear(&Color::from_name(ColorName::Black));
+        handle.renderer.set_camera(x, y, SpectatorView::zoom());
+        handle.level.draw(handle.renderer);
+        handle.entities.draw(handle.renderer, handle.level);
+        handle.renderer.draw_particles();
+        handle.renderer.reset_camera();
+
+        // HUD
+        let camera_state = match self.mode {
+            CameraMode::Free => "Free camera - WASD/Arrows: Move".to_string(),
+            CameraMode::Follow => format!(
+                "Following {} - Space: Next",
+                self.followed.and_then(|f| {
+                    self.scoreboard.player_for_color(f.1 >> 4)
+
+                }).unwrap_or_else(|| "nobody".to_string())
+            )
+        };
+
+        let spectator_state = format!(
+            "Spectating {} - Press ESC to return to Menu\n{} - Enter: Switch camera",
+            self.server_addr,
+            camera_state
+        );
+
+        handle.renderer.text(
+            &Color::from_name(ColorName::White),
+            0.0, 0.0,
+            &spectator_state[..]
+        );
+
+        if self.scoreboard.round_over() || handle.renderer.key_down(KeyCode::Tab) {
+            self.scoreboard.draw(handle.renderer, 48.0);
+        }
+
+        if handle.renderer.key_released(KeyCode::Escape) {
+            handle.events.send(SharedEvent::LeaveGame);
+            handle.events.send(SharedEvent::Command(SharedCommand::Shutdown));
+        }
+
+    }
+
+    fn destroy(&mut self, _: &mut Game, handle: &mut ClientHandle) {
+        handle.events.send(SharedEvent::Command(SharedCommand::Shutdown));
+    }
+
+}
+
@@ -1,5 +1,5 @@
 // Internal Dependencies ------------------------------------------------------
-use shared::Lithium::{Level, BaseLevel, DrawableLevel, Renderer};
+use shared::Lithium::{Level, BaseLevel, DecodeError, DrawableLevel, Renderer};
 use shared::{SharedLevel, SharedState};
 
 
@@ -7,11 +7,10 @@ use shared::{SharedLevel, SharedState};
 pub struct RenderedLevel;
 impl RenderedLevel {
 
-    pub fn from_serialized(data: &[u8]) -> Level<SharedState, SharedLevel> {
-        Level::new(
-            SharedLevel::from_serialized(data),
-            Box::new(RenderedLevel)
-        )
+    pub fn from_serialized(data: &[u8]) -> Result<Level<SharedState, SharedLevel>, DecodeError> {
+        SharedLevel::from_serialized(data).map(|level| {
+            Level::new(level, Box::new(RenderedLevel))
+        })
     }
 
 }
@@ -26,8 +26,10 @@ extern crate clock_ticks;
 
 
 // External Dependencies ------------------------------------------------------
+use std::path::Path;
 use std::net::SocketAddr;
-use shared::Lithium::Renderer as LithiumRenderer;
+use shared::ColorName;
+use shared::Lithium::{Replay, Renderer as LithiumRenderer};
 
 
 // Internal Dependencies ------------------------------------------------------
@@ -59,12 +61,42 @@ fn run() {
             .help("Remote server address to connect to.")
             .index(1)
 
+        ).arg(clap::Arg::with_name("name")
+            .help("Player name to join the game with.")
+            .long("name")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("color")
+            .help("Preferred ship color.")
+            .long("color")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("spectate")
+            .help("Watches the game as a spectator instead of joining it.")
+            .long("spectate")
+
+        ).arg(clap::Arg::with_name("replay")
+            .help("Plays back the given replay file instead of connecting.")
+            .long("replay")
+            .takes_value(true)
+
         ).get_matches();
 
 
     // Arguments --------------------------------------------------------------
+    if let Some(path) = args.value_of("replay") {
+        match Replay::open(Path::new(path)) {
+            Ok(replay) => Renderer::run(game::Game::replay(replay)),
+            Err(err) => println!("[Client] Failed to load replay: {:?}", err)
+        }
+        return;
+    }
+
     Renderer::run(game::Game::client(
-        value_t!(args.value_of("address:port"), SocketAddr).ok()
+        value_t!(args.value_of("address:port"), SocketAddr).ok(),
+        args.value_of("name").unwrap_or("Player").to_string(),
+        args.value_of("color").and_then(ColorName::parse),
+        args.is_present("spectate")
     ));
 
 }
@@ -7,8 +7,12 @@ use allegro::{
     EventQueue,
     KeyUp,
     KeyDown,
+    MouseAxes,
+    MouseButtonDown,
+    MouseButtonUp,
     Timer,
-    TimerTick
+    TimerTick,
+    Transform
 };
 use rand::{SeedableRng, XorShiftRng};
 use allegro_font::{FontDrawing, FontAddon, Font, FontAlign};
@@ -20,7 +24,7 @@ mod traits;
 mod particle_system;
 use shared::Color;
 use shared::Lithium::Renderer;
-use renderer::Particle;
+use renderer::{MouseButton, Particle};
 use self::particle_system::AllegroParticleSystem;
 pub use allegro::KeyCode as KeyCode;
 
@@ -50,6 +54,9 @@ pub struct AllegroRenderer {
     // Input
     key_state: [bool; 256],
     key_state_old: [bool; 256],
+    mouse_state: [bool; 2],
+    mouse_position: (f32, f32),
+    mouse_position_old: (f32, f32),
 
     // Internal State
     rng: XorShiftRng,
@@ -90,6 +97,9 @@ impl AllegroRenderer {
             u: 0.0,
             key_state: [false; 256],
             key_state_old: [false; 256],
+            mouse_state: [false; 2],
+            mouse_position: (0.0, 0.0),
+            mouse_position_old: (0.0, 0.0),
             rng: XorShiftRng::new_unseeded(),
             interpolation_ticks: 0
         }
@@ -107,6 +117,25 @@ impl AllegroRenderer {
     }
 
 
+    // Camera -----------------------------------------------------------------
+    // Centers all following drawing on the given position with the given
+    // zoom factor until the camera is reset
+    pub fn set_camera(&mut self, x: f32, y: f32, zoom: f32) {
+        let mut transform = Transform::identity();
+        transform.translate(-x, -y);
+        transform.scale(zoom, zoom);
+        transform.translate(
+            self.display.get_width() as f32 * 0.5,
+            self.display.get_height() as f32 * 0.5
+        );
+        self.core.use_transform(&transform);
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.core.use_transform(&Transform::identity());
+    }
+
+
     // Input ------------------------------------------------------------------
     pub fn key_down(&mut self, key_code: KeyCode) -> bool {
         self.key_state[key_code as usize]
@@ -120,6 +149,19 @@ impl AllegroRenderer {
         !self.key_state[key_code as usize] && self.key_state_old[key_code as usize]
     }
 
+    pub fn mouse_down(&mut self, button: MouseButton) -> bool {
+        self.mouse_state[button as usize]
+    }
+
+    pub fn mouse_position(&self) -> (f32, f32) {
+        self.mouse_position
+    }
+
+    // Whether the mouse was moved since the last frame
+    pub fn mouse_moved(&self) -> bool {
+        self.mouse_position != self.mouse_position_old
+    }
+
 
     // Drawing Methods --------------------------------------------------------
     pub fn clear(&mut self, color: &Color) {
@@ -221,6 +263,18 @@ impl AllegroRenderer {
                 self.key_state[k as usize] = false;
             },
 
+            MouseAxes{x, y, ..} => {
+                self.mouse_position = (x as f32, y as f32);
+            },
+
+            MouseButtonDown{button: b, ..} if b >= 1 && b <= 2 => {
+                self.mouse_state[b as usize - 1] = true;
+            },
+
+            MouseButtonUp{button: b, ..} if b >= 1 && b <= 2 => {
+                self.mouse_state[b as usize - 1] = false;
+            },
+
             TimerTick{timestamp: t, ..} => {
                 self.set_time(t);
                 self.redraw = true;
@@ -238,6 +292,7 @@ impl AllegroRenderer {
     fn draw(&mut self) {
         self.core.flip_display();
         self.key_state_old = self.key_state;
+        self.mouse_position_old = self.mouse_position;
     }
 
 }
@@ -6,7 +6,7 @@ use allegro::{
 
 // Internal Dependencies ------------------------------------------------------
 use shared::Lithium::{
-    Client, ClientHandler, EntityState, EntityRegistry, Event, BaseLevel, Renderer
+    Client, ClientHandler, EntityState, EntityInput, EntityRegistry, Event, BaseLevel, Renderer
 };
 use super::AllegroRenderer;
 
@@ -16,13 +16,14 @@ impl Renderer for AllegroRenderer {
 
     // Statics ----------------------------------------------------------------
     fn run<
-        H: ClientHandler<Self, G, L, E, S>,
+        H: ClientHandler<Self, G, L, E, S, I>,
         E: Event,
         S: EntityState,
+        I: EntityInput,
         L: BaseLevel<S>,
-        G: EntityRegistry<S, L, Self>
+        G: EntityRegistry<S, I, L, Self>
 
-    >(mut client: Client<H, Self, G, L, E, S>) where Self: Sized {
+    >(mut client: Client<H, Self, G, L, E, S, I>) where Self: Sized {
 
         // Init Allegro
         let mut core = Core::init().unwrap();
@@ -32,6 +33,10 @@ impl Renderer for AllegroRenderer {
         core.install_keyboard().unwrap();
         q.register_event_source(core.get_keyboard_event_source());
 
+        // Mouse
+        core.install_mouse().unwrap();
+        q.register_event_source(core.get_mouse_event_source());
+
         // Create Display
         core.set_new_display_flags(OPENGL);
         core.set_new_display_option(
@@ -13,7 +13,7 @@ mod particle_system;
 mod font;
 use shared::Color;
 use shared::Lithium::Renderer;
-use renderer::Particle;
+use renderer::{MouseButton, Particle};
 use self::particle_system::GliumParticleSystem;
 use self::font::Font;
 
@@ -25,7 +25,10 @@ pub enum KeyCode {
     C = 54,
     D = 40,
     W = 25,
+    Space = 65,
+    Tab = 23,
     Up = 111,
+    Down = 116,
     Left = 113,
     Right = 114,
     LCtrl = 37,
@@ -49,12 +52,17 @@ pub struct GliumRenderer {
     display: GlutinFacade,
     font: Font,
     perspective: [[f32; 4]; 4],
+    width: f32,
+    height: f32,
     particle_system: GliumParticleSystem,
     interpolation_ticks: usize,
 
     // Input
     key_state: [bool; 256],
     key_state_old: [bool; 256],
+    mouse_state: [bool; 2],
+    mouse_position: (f32, f32),
+    mouse_position_old: (f32, f32),
 
     // Internal State
     rng: XorShiftRng,
@@ -83,6 +91,8 @@ impl GliumRenderer {
             display: display,
             font: font,
             perspective: GliumRenderer::perspective(width as f32, height as f32),
+            width: width as f32,
+            height: height as f32,
 
             particle_system: particle_system,
             interpolation_ticks: 0,
@@ -90,6 +100,9 @@ impl GliumRenderer {
             // Input
             key_state: [false; 256],
             key_state_old: [false; 256],
+            mouse_state: [false; 2],
+            mouse_position: (0.0, 0.0),
+            mouse_position_old: (0.0, 0.0),
 
             // Internal State
             rng: XorShiftRng::new_unseeded(),
@@ -105,11 +118,29 @@ impl GliumRenderer {
     }
 
     pub fn resize(&mut self, width: i32, height: i32) {
-        self.perspective = GliumRenderer::perspective(width as f32, height as f32);
+        self.width = width as f32;
+        self.height = height as f32;
+        self.perspective = GliumRenderer::perspective(self.width, self.height);
         self.display.get_window().unwrap().set_inner_size(width as u32, height as u32);
     }
 
 
+    // Camera -----------------------------------------------------------------
+    // Centers all following drawing on the given position with the given
+    // zoom factor until the camera is reset
+    pub fn set_camera(&mut self, x: f32, y: f32, zoom: f32) {
+        let (w, h) = (self.width / zoom, self.height / zoom);
+        self.perspective = GliumRenderer::projection(
+            x - w * 0.5, x + w * 0.5,
+            y - h * 0.5, y + h * 0.5
+        );
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.perspective = GliumRenderer::perspective(self.width, self.height);
+    }
+
+
     // Input ------------------------------------------------------------------
     pub fn key_down(&mut self, key_code: KeyCode) -> bool {
         self.key_state[key_code as usize]
@@ -123,6 +154,19 @@ impl GliumRenderer {
         !self.key_state[key_code as usize] && self.key_state_old[key_code as usize]
     }
 
+    pub fn mouse_down(&mut self, button: MouseButton) -> bool {
+        self.mouse_state[button as usize]
+    }
+
+    pub fn mouse_position(&self) -> (f32, f32) {
+        self.mouse_position
+    }
+
+    // Whether the mouse was moved since the last frame
+    pub fn mouse_moved(&self) -> bool {
+        self.mouse_position != self.mouse_position_old
+    }
+
 
     // Drawing Methods --------------------------------------------------------
     pub fn clear(&mut self, color: &Color) {
@@ -178,16 +222,18 @@ impl GliumRenderer {
 
     // Perspective Conversion -------------------------------------------------
     fn perspective(width: f32, height: f32) -> [[f32; 4]; 4] {
+        GliumRenderer::projection(0.0, width, 0.0, height)
+    }
 
-        let (r, l, t, b, f, n) = (
-            width as f32, 0.0, 0.0, height as f32, 100.0, 0.0
-        );
+    fn projection(l: f32, r: f32, t: f32, b: f32) -> [[f32; 4]; 4] {
+
+        let (f, n) = (100.0, 0.0);
 
         [
             [2.0 / (r - l), 0.0, 0.0, 0.0],
             [0.0, 2.0 / (t - b), 0.0, 0.0],
             [0.0, 0.0, -2.0 / (f - n), 0.0],
-            [-1.0, 1.0, -(f + n) / (f - n), 1.0f32]
+            [-(r + l) / (r - l), -(t + b) / (t - b), -(f + n) / (f - n), 1.0f32]
         ]
 
     }
@@ -215,6 +261,7 @@ impl GliumRenderer {
                     for i in 0..256 {
                         self.key_state[i] = false;
                     }
+                    self.mouse_state = [false; 2];
                 },
                 glutin::Event::KeyboardInput(glutin::ElementState::Pressed, code, _) => {
                     self.key_state[code as usize] = true;
@@ -226,6 +273,15 @@ impl GliumRenderer {
                 glutin::Event::KeyboardInput(glutin::ElementState::Released, code, _) => {
                     self.key_state[code as usize] = false;
                 },
+                glutin::Event::MouseMoved((x, y)) => {
+                    self.mouse_position = (x as f32, y as f32);
+                },
+                glutin::Event::MouseInput(state, glutin::MouseButton::Left) => {
+                    self.mouse_state[MouseButton::Left as usize] = state == glutin::ElementState::Pressed;
+                },
+                glutin::Event::MouseInput(state, glutin::MouseButton::Right) => {
+                    self.mouse_state[MouseButton::Right as usize] = state == glutin::ElementState::Pressed;
+                },
                 _ => {}
             }
         }
@@ -243,6 +299,7 @@ impl GliumRenderer {
         target.finish().unwrap();
         self.display.swap_buffers().unwrap(); // TODO make sure vsync doesn't screw with us here
         self.key_state_old = self.key_state;
+        self.mouse_position_old = self.mouse_position;
     }
 
 }
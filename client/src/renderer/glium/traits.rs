@@ -4,7 +4,7 @@ use glium::{glutin, DisplayBuild, Surface};
 
 // Internal Dependencies ------------------------------------------------------
 use shared::Lithium::{
-    Client, ClientHandler, EntityState, EntityRegistry, Event, BaseLevel, Renderer
+    Client, ClientHandler, EntityState, EntityInput, EntityRegistry, Event, BaseLevel, Renderer
 };
 use super::GliumRenderer;
 
@@ -14,13 +14,14 @@ impl Renderer for GliumRenderer {
 
     // Statics ----------------------------------------------------------------
     fn run<
-        H: ClientHandler<Self, G, L, E, S>,
+        H: ClientHandler<Self, G, L, E, S, I>,
         E: Event,
         S: EntityState,
+        I: EntityInput,
         L: BaseLevel<S>,
-        G: EntityRegistry<S, L, Self>
+        G: EntityRegistry<S, I, L, Self>
 
-    >(mut client: Client<H, Self, G, L, E, S>) where Self: Sized {
+    >(mut client: Client<H, Self, G, L, E, S, I>) where Self: Sized {
 
         let (width, height) = (256, 256);
         let display = glutin::WindowBuilder::new()
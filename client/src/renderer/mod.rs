@@ -19,6 +19,9 @@ pub use self::glium::GliumRenderer as Renderer;
 pub use self::glium::KeyCode as KeyCode;
 
 // Generic Components ---------------------------------------------------------
+mod mouse;
+pub use self::mouse::MouseButton as MouseButton;
+
 mod particle_system;
 pub use self::particle_system::ParticleSystem as ParticleSystem;
 pub use self::particle_system::Particle as Particle;
@@ -0,0 +1,7 @@
+// Mouse Button Mapping -------------------------------------------------------
+#[derive(Copy, Clone)]
+pub enum MouseButton {
+    Left = 0,
+    Right = 1
+}
+
@@ -6,15 +6,20 @@ use cobalt::{Config, ConnectionID, ClientStream, ClientEvent, MessageKind};
 
 // Internal Dependencies ------------------------------------------------------
 use network;
+use network::DecodeError;
 use entity::{
     Entity,
     EntityState,
+    EntityInput,
     EntityManager,
     EntityRegistry
 };
+use clock;
+use clock::{ClientClock, SyncHeader};
 use level::{Level, BaseLevel};
 use event::{Event, EventHandler};
 use renderer::Renderer;
+use transport::ClientTransport;
 
 
 // Macros ---------------------------------------------------------------------
@@ -26,7 +31,8 @@ macro_rules! handle {
             events: &mut $s.events,
             entities: &mut $s.manager,
             timer: &mut $s.timer,
-            client: &mut $s.client
+            clock: &$s.clock,
+            client: &mut *$s.client
        }
     }
 }
@@ -34,22 +40,24 @@ macro_rules! handle {
 
 // Client Abstraction ---------------------------------------------------------
 pub struct Client<
-    H: Handler<R, G, L, E, S>, R: Renderer,
-    G: EntityRegistry<S, L, R>, L: BaseLevel<S>, E: Event, S: EntityState
+    H: Handler<R, G, L, E, S, I>, R: Renderer,
+    G: EntityRegistry<S, I, L, R>, L: BaseLevel<S>, E: Event, S: EntityState, I: EntityInput
 > {
     handler: H,
-    client: ClientStream,
-    manager: EntityManager<S, L, R, G>,
+    client: Box<ClientTransport>,
+    manager: EntityManager<S, I, L, R, G>,
     events: EventHandler<E>,
     level: Level<S, L>,
-    timer: Timer<H, R, G, L, E, S>
+    timer: Timer<H, R, G, L, E, S, I>,
+    clock: ClientClock,
+    header: network::ConfigHeader
 }
 
 impl<
-    H: Handler<R, G, L, E, S>, R: Renderer,
-    G: EntityRegistry<S, L, R>, L: BaseLevel<S>, E: Event, S: EntityState
+    H: Handler<R, G, L, E, S, I>, R: Renderer,
+    G: EntityRegistry<S, I, L, R>, L: BaseLevel<S>, E: Event, S: EntityState, I: EntityInput
 
-> Client<H, R, G, L, E, S> {
+> Client<H, R, G, L, E, S, I> {
 
     // Statics ----------------------------------------------------------------
     pub fn new(
@@ -58,14 +66,14 @@ impl<
         registry: G,
         handler: H
 
-    ) -> Client<H, R, G, L, E, S> {
+    ) -> Client<H, R, G, L, E, S, I> {
         Client {
             handler: handler,
-            client: ClientStream::new(Config {
+            client: Box::new(ClientStream::new(Config {
                 send_rate: tick_rate as u32,
                 connection_init_threshold: 250,
                 .. Default::default()
-            }),
+            })),
             manager: EntityManager::new(
                 tick_rate, 1000, 75,
                 false,
@@ -73,12 +81,51 @@ impl<
             ),
             events: EventHandler::new(),
             level: level,
-            timer: Timer::new()
+            timer: Timer::new(),
+            clock: ClientClock::new(tick_rate),
+            header: network::ConfigHeader::new(0)
         }
     }
 
 
     // Public -----------------------------------------------------------------
+    pub fn set_game_version(&mut self, version: u16) {
+        self.header.game_version = version;
+    }
+
+    // Number of unconfirmed inputs which are re-sent with every tick
+    pub fn set_input_redundancy(&mut self, ticks: usize) {
+        self.manager.set_input_redundancy(ticks);
+    }
+
+    // Time inputs should arrive at the server ahead of the tick they are
+    // applied at, on top of the measured network jitter
+    pub fn set_input_buffer(&mut self, buffer_ms: u32) {
+        self.clock.set_input_buffer(buffer_ms);
+    }
+
+    // Time remote entities keep moving on their own after running out of
+    // received states
+    pub fn set_max_extrapolation(&mut self, extrapolation_ms: u32) {
+        self.manager.set_max_extrapolation(extrapolation_ms);
+    }
+
+    // Time over which corrections of locally predicted entities are smoothed
+    // out instead of being applied instantly
+    pub fn set_correction_smoothing(&mut self, smoothing_ms: u32) {
+        self.manager.set_correction_smoothing(smoothing_ms);
+    }
+
+    // Replaces the default UDP transport
+    pub fn set_transport(&mut self, mut transport: Box<ClientTransport>) {
+        let config = transport.config();
+        transport.set_config(Config {
+            send_rate: self.manager.config().tick_rate as u32,
+            .. config
+        });
+        self.client = transport;
+    }
+
     pub fn init(&mut self, renderer: &mut R) {
         self.update_tick_config(renderer);
         self.handler.init(handle!(self, renderer));
@@ -109,31 +156,14 @@ impl<
 
                 ClientEvent::Connection => {
                     self.manager.reset();
+                    self.clock.reset();
                     self.handler.connect(handle!(self, renderer));
                 },
 
                 ClientEvent::Message(data) =>  {
-                    match network::Message::from_u8(data[0]) {
-
-                        network::Message::ServerConfig => {
-                            let data = self.manager.receive_config(&data[1..]);
-                            self.update_tick_config(renderer);
-                            self.handler.config(handle!(self, renderer), data);
-                        },
-
-                        network::Message::ServerState => {
-                            self.manager.receive_state(&data[1..]);
-                        },
-
-                        network::Message::ServerEvents => {
-                            self.events.receive_events(
-                                ConnectionID(0),
-                                &data[1..]
-                            );
-                        },
-
-                        _=> println!("Unknown Server Message {:?}", data)
-
+                    // Malformed messages are dropped
+                    if let Err(err) = self.receive_message(renderer, &data) {
+                        self.handler.invalid_message(handle!(self, renderer), err);
                     }
                 },
 
@@ -145,25 +175,42 @@ impl<
                         }
                     }
 
-                    self.handler.tick_before(handle!(self, renderer));
+                    // Move the simulation along with the estimated server
+                    // tick, which might require to skip or repeat a tick
+                    let (reset_tick, ticks) = self.clock.tick(self.manager.tick());
+                    if let Some(tick) = reset_tick {
+                        self.manager.set_tick(tick);
+                    }
 
-                    self.tick_entities(renderer);
-                    self.send_events();
+                    // Remote entities are played back at the transport tick
+                    // rate which the server sends its states at
+                    self.manager.tick_interpolation();
 
-                    self.handler.tick_after(handle!(self, renderer));
+                    for _ in 0..ticks {
 
-                    ticked = true;
+                        self.handler.tick_before(handle!(self, renderer));
+
+                        self.tick_entities(renderer);
+                        self.send_events();
+
+                        self.handler.tick_after(handle!(self, renderer));
+
+                        ticked = true;
+
+                    }
 
                 },
 
                 ClientEvent::ConnectionLost => {
                     self.manager.reset();
+                    self.clock.reset();
                     self.handler.disconnect(handle!(self, renderer), true, false);
                     self.client.close().ok();
                 },
 
                 ClientEvent::ConnectionClosed(by_remote) => {
                     self.manager.reset();
+                    self.clock.reset();
                     self.handler.disconnect(handle!(self, renderer), true, by_remote);
                     self.client.close().ok();
                 },
@@ -203,8 +250,109 @@ impl<
 
 
     // Internal ---------------------------------------------------------------
+    fn receive_message(&mut self, renderer: &mut R, data: &[u8]) -> Result<(), DecodeError> {
+
+        let (message, data) = match network::Message::parse(data) {
+            Ok(message) => message,
+            Err(err) => return Err(err)
+        };
+
+        match message {
+
+            network::Message::ServerConfig => {
+                match self.receive_config(data) {
+                    Ok(data) => {
+                        self.update_tick_config(renderer);
+                        self.handler.config(handle!(self, renderer), data);
+                    },
+                    Err(reason) => {
+                        // Refuse to talk to incompatible servers
+                        self.manager.reset();
+                        self.handler.incompatible(handle!(self, renderer), reason);
+                        self.client.close().ok();
+                    }
+                }
+                Ok(())
+            },
+
+            network::Message::ServerState => {
+
+                let data = match SyncHeader::from_serialized(data) {
+                    Ok((Some(header), data)) => {
+                        self.clock.receive(header);
+                        data
+                    },
+                    Ok((None, data)) => data,
+                    Err(err) => return Err(err)
+                };
+
+                match self.manager.receive_state(data) {
+
+                    // Acknowledge the state so the server can use it
+                    // as the baseline for future delta updates, every once
+                    // in a while the acknowledgement doubles as a sync request
+                    Ok(tick) => {
+                        let mut ack = vec![tick as u8];
+                        if let Some(client_tick) = self.clock.request() {
+                            ack.extend(clock::serialize_u32(client_tick));
+                        }
+
+                        self.send_message(
+                            MessageKind::Instant,
+                            network::Message::ClientAck,
+                            &ack
+                        );
+                        Ok(())
+                    },
+
+                    // States encoded against an outdated baseline are
+                    // expected to occur on packet loss
+                    Err(DecodeError::MissingBaseline) => Ok(()),
+
+                    Err(err) => Err(err)
+
+                }
+            },
+
+            network::Message::ServerEvents => {
+                self.events.receive_events(ConnectionID(0), data)
+            },
+
+            network::Message::ServerEntities => {
+                self.manager.receive_lifecycle(data)
+            },
+
+            message => Err(DecodeError::UnknownMessage(message as u8))
+
+        }
+
+    }
+
+    fn receive_config<'a>(
+        &mut self, data: &'a [u8]
+
+    ) -> Result<&'a [u8], network::Incompatibility> {
+
+        let header = match network::ConfigHeader::from_serialized(data) {
+            Ok(header) => header,
+            Err(err) => return Err(network::Incompatibility::InvalidConfig(err))
+        };
+
+        if let Err(reason) = self.header.compatible(&header) {
+            return Err(reason);
+        }
+
+        self.manager.receive_config(
+            &data[network::ConfigHeader::encoded_size()..]
+
+        ).map_err(network::Incompatibility::InvalidConfig)
+
+    }
+
     fn update_tick_config(&mut self, renderer: &mut R) {
         let tick_rate = self.manager.config().tick_rate as u32;
+        self.clock.set_tick_rate(tick_rate as u8);
+
         let config = self.client.config();
         self.client.set_config(Config {
             send_rate: tick_rate,
@@ -234,7 +382,7 @@ impl<
 
     fn send_events(&mut self) {
 
-        if let Some(events) = self.events.serialize_events(None) {
+        if let Some(events) = self.events.serialize_events() {
             self.send_message(
                 MessageKind::Ordered,
                 network::Message::ClientEvents,
@@ -258,55 +406,64 @@ impl<
 // Client Handle for Access from Handler --------------------------------------
 pub struct Handle<
     'a,
-    H: Handler<R, G, L, E, S> + 'a,
+    H: Handler<R, G, L, E, S, I> + 'a,
     R: Renderer + 'a,
-    G: EntityRegistry<S, L, R> + 'a,
+    G: EntityRegistry<S, I, L, R> + 'a,
     L: BaseLevel<S> + 'a,
     E: Event + 'a,
-    S: EntityState + 'a
+    S: EntityState + 'a,
+    I: EntityInput + 'a
 > {
     pub renderer: &'a mut R,
     pub level: &'a mut Level<S, L>,
     pub events: &'a mut EventHandler<E>,
-    pub entities: &'a mut EntityManager<S, L, R, G>,
-    pub timer: &'a mut Timer<H, R, G, L, E, S>,
-    pub client: &'a mut ClientStream
+    pub entities: &'a mut EntityManager<S, I, L, R, G>,
+    pub timer: &'a mut Timer<H, R, G, L, E, S, I>,
+    pub clock: &'a ClientClock,
+    pub client: &'a mut ClientTransport
 }
 
 
 // Client Handler -------------------------------------------------------------
 pub trait Handler<
     R: Renderer,
-    G: EntityRegistry<S, L, R>, L: BaseLevel<S>, E: Event, S: EntityState,
+    G: EntityRegistry<S, I, L, R>, L: BaseLevel<S>, E: Event, S: EntityState, I: EntityInput,
 > {
 
-    fn init(&mut self, Handle<Self, R, G, L, E, S>) where Self: Sized;
-    fn connect(&mut self, Handle<Self, R, G, L, E, S>) where Self: Sized;
-    fn disconnect(&mut self, Handle<Self, R, G, L, E, S>, bool, bool) where Self: Sized;
+    fn init(&mut self, Handle<Self, R, G, L, E, S, I>) where Self: Sized;
+    fn connect(&mut self, Handle<Self, R, G, L, E, S, I>) where Self: Sized;
+    fn disconnect(&mut self, Handle<Self, R, G, L, E, S, I>, bool, bool) where Self: Sized;
+
+    fn config(&mut self, Handle<Self, R, G, L, E, S, I>, &[u8]) where Self: Sized;
+
+    fn incompatible(
+        &mut self, Handle<Self, R, G, L, E, S, I>, network::Incompatibility
+
+    ) where Self: Sized;
 
-    fn config(&mut self, Handle<Self, R, G, L, E, S>, &[u8]) where Self: Sized;
+    fn invalid_message(&mut self, Handle<Self, R, G, L, E, S, I>, DecodeError) where Self: Sized;
 
-    fn event(&mut self, Handle<Self, R, G, L, E, S>, ConnectionID, E) where Self: Sized;
+    fn event(&mut self, Handle<Self, R, G, L, E, S, I>, ConnectionID, E) where Self: Sized;
 
-    fn tick_before(&mut self, Handle<Self, R, G, L, E, S>) where Self: Sized;
+    fn tick_before(&mut self, Handle<Self, R, G, L, E, S, I>) where Self: Sized;
 
     fn tick_entity_before(
-        &mut self, &mut R, &Level<S, L>, &mut Entity<S, L, R>, u8, f32
+        &mut self, &mut R, &Level<S, L>, &mut Entity<S, I, L, R>, u32, f32
     );
 
     fn tick_entity_after(
-        &mut self, &mut R, &Level<S, L>, &mut Entity<S, L, R>, u8, f32
+        &mut self, &mut R, &Level<S, L>, &mut Entity<S, I, L, R>, u32, f32
     );
 
-    fn tick_after(&mut self, Handle<Self, R, G, L, E, S>) where Self: Sized;
+    fn tick_after(&mut self, Handle<Self, R, G, L, E, S, I>) where Self: Sized;
 
-    fn draw(&mut self, Handle<Self, R, G, L, E, S>) where Self: Sized;
+    fn draw(&mut self, Handle<Self, R, G, L, E, S, I>) where Self: Sized;
 
-    fn destroy(&mut self, Handle<Self, R, G, L, E, S>) where Self: Sized;
+    fn destroy(&mut self, Handle<Self, R, G, L, E, S, I>) where Self: Sized;
 
 }
 
 
 // Timer Implementation -------------------------------------------------------
-impl_timer!(Handler, Renderer, EntityRegistry, BaseLevel, Event, EntityState);
+impl_timer!(Handler, Renderer, EntityRegistry, BaseLevel, Event, EntityState, EntityInput);
 
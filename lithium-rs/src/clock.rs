@@ -0,0 +1,361 @@
+// External Dependencies ------------------------------------------------------
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use cobalt::ConnectionID;
+
+
+// Internal Dependencies ------------------------------------------------------
+use network::DecodeError;
+
+
+// Number of round trip samples the clock estimates are based on
+const SYNC_SAMPLES: usize = 8;
+
+// Maximum relative deviation of the client tick rate from the server's
+const MAX_TICK_SCALE: f32 = 0.05;
+
+// Fraction of the tick error which is turned into a tick rate change
+const TICK_CORRECTION: f32 = 0.1;
+
+// Fraction of the input depth error which is fed back into the lead
+const DEPTH_CORRECTION: f32 = 0.25;
+
+// Weight of each new input depth sample on the server
+const DEPTH_SMOOTHING: f32 = 0.1;
+
+// Default time inputs should be buffered on the server before being applied
+const DEFAULT_INPUT_BUFFER_MS: u32 = 30;
+
+
+// Clock Sync Header ----------------------------------------------------------
+pub struct SyncHeader {
+
+    // Local clock tick at which the client sent its request
+    pub client_tick: u32,
+
+    // Server tick at which the request was answered
+    pub server_tick: u32,
+
+    // Average number of ticks the client's inputs arrive ahead of the server
+    pub input_depth: i8
+
+}
+
+impl SyncHeader {
+
+    pub fn serialize(header: Option<&SyncHeader>) -> Vec<u8> {
+        if let Some(header) = header {
+            let mut data = vec![1];
+            data.extend(serialize_u32(header.client_tick));
+            data.extend(serialize_u32(header.server_tick));
+            data.push(header.input_depth as u8);
+            data
+
+        } else {
+            vec![0]
+        }
+    }
+
+    // Returns the header, if any, and the remaining data
+    pub fn from_serialized(data: &[u8]) -> Result<(Option<SyncHeader>, &[u8]), DecodeError> {
+
+        if data.is_empty() {
+            return Err(DecodeError::Truncated);
+        }
+
+        match data[0] {
+            0 => Ok((None, &data[1..])),
+            1 => if data.len() >= 10 {
+                Ok((Some(SyncHeader {
+                    client_tick: deserialize_u32(&data[1..5]),
+                    server_tick: deserialize_u32(&data[5..9]),
+                    input_depth: data[9] as i8
+
+                }), &data[10..]))
+
+            } else {
+                Err(DecodeError::Truncated)
+            },
+            _ => Err(DecodeError::Invalid)
+        }
+
+    }
+
+}
+
+
+// Client Side Clock ----------------------------------------------------------
+// Estimates the server tick NTP style from the round trips of sync requests
+// and keeps the local simulation slightly ahead of it, so inputs arrive at the
+// server shortly before the tick they were generated for.
+pub struct ClientClock {
+
+    tick_rate: u8,
+
+    // Local clock in ticks, advanced with every transport tick and never
+    // adjusted
+    local_tick: u32,
+    last_request: Option<u32>,
+
+    // Round trip times in ticks and clock offsets of the most recent samples
+    samples: VecDeque<(u32, f64)>,
+
+    // Estimated number of ticks the server is ahead of the local clock
+    offset: Option<f64>,
+
+    // Additional lead derived from the input depth reported by the server
+    lead_correction: f32,
+    input_depth: Option<f32>,
+    input_buffer_ms: u32,
+
+    // Relative tick rate and fractional ticks carried over to the next tick
+    scale: f32,
+    remainder: f32,
+
+    // Number of samples received since the simulation was aligned
+    synced_samples: Option<usize>
+
+}
+
+impl ClientClock {
+
+    pub fn new(tick_rate: u8) -> ClientClock {
+        ClientClock {
+            tick_rate: tick_rate,
+            local_tick: 0,
+            last_request: None,
+            samples: VecDeque::with_capacity(SYNC_SAMPLES),
+            offset: None,
+            lead_correction: 0.0,
+            input_depth: None,
+            input_buffer_ms: DEFAULT_INPUT_BUFFER_MS,
+            scale: 1.0,
+            remainder: 0.0,
+            synced_samples: None
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.last_request = None;
+        self.samples.clear();
+        self.offset = None;
+        self.lead_correction = 0.0;
+        self.input_depth = None;
+        self.scale = 1.0;
+        self.remainder = 0.0;
+        self.synced_samples = None;
+    }
+
+    pub fn set_tick_rate(&mut self, tick_rate: u8) {
+        self.tick_rate = cmp::max(tick_rate, 1);
+    }
+
+    pub fn set_input_buffer(&mut self, buffer_ms: u32) {
+        self.input_buffer_ms = buffer_ms;
+    }
+
+    // Estimated current server tick
+    pub fn server_tick(&self) -> Option<f64> {
+        self.offset.map(|offset| self.local_tick as f64 + offset)
+    }
+
+    // Shortest recently measured round trip time in milliseconds
+    pub fn rtt(&self) -> Option<f32> {
+        self.round_trip().map(|ticks| ticks * self.tick_ms())
+    }
+
+    // Number of ticks the simulation runs ahead of the estimated server tick
+    pub fn lead(&self) -> f32 {
+        self.round_trip().unwrap_or(0.0) * 0.5 + self.target_depth() + self.lead_correction
+    }
+
+    // Average number of ticks the inputs arrived ahead of time on the server
+    pub fn input_depth(&self) -> Option<f32> {
+        self.input_depth
+    }
+
+    // Current tick rate relative to the server's
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    // Returns the local clock tick to include with the next state
+    // acknowledgement in case a new sync sample is due
+    pub fn request(&mut self) -> Option<u32> {
+
+        let interval = cmp::max(self.tick_rate as u32 / 4, 1);
+        let due = self.last_request.map_or(true, |tick| {
+            self.local_tick.wrapping_sub(tick) >= interval
+        });
+
+        if due {
+            self.last_request = Some(self.local_tick);
+            Some(self.local_tick)
+
+        } else {
+            None
+        }
+
+    }
+
+    pub fn receive(&mut self, header: SyncHeader) {
+
+        // Answers to requests which were never sent are ignored
+        if header.client_tick > self.local_tick {
+            return;
+        }
+
+        // The server answers within the tick it received the request in, so
+        // both of its timestamps are the same
+        let round_trip = self.local_tick - header.client_tick;
+        let offset = header.server_tick as f64
+                   - (header.client_tick as f64 + self.local_tick as f64) * 0.5;
+
+        self.samples.push_back((round_trip, offset));
+        if self.samples.len() > SYNC_SAMPLES {
+            self.samples.pop_front();
+        }
+
+        // Samples with the shortest round trip are the least affected by
+        // queuing delays
+        self.offset = self.samples.iter().min_by_key(|s| s.0).map(|s| s.1);
+
+        // The reported input depth only becomes meaningful once the server
+        // received a full window of inputs generated after the alignment
+        let depth = header.input_depth as f32;
+        self.input_depth = Some(depth);
+
+        let synced_samples = self.synced_samples;
+        match synced_samples {
+            Some(synced) if synced >= SYNC_SAMPLES => {
+                let max_correction = self.tick_rate as f32;
+                self.lead_correction = (
+                    self.lead_correction + (self.target_depth() - depth) * DEPTH_CORRECTION
+
+                ).max(-max_correction).min(max_correction);
+            },
+            Some(synced) => self.synced_samples = Some(synced + 1),
+            None => {}
+        }
+
+    }
+
+    // Advances the local clock by one transport tick and returns the tick the
+    // simulation needs to be moved to, if any, along with the number of ticks
+    // to simulate. While the simulation is behind or ahead of its target the
+    // tick rate is smoothly scaled up or down.
+    pub fn tick(&mut self, tick: u32) -> (Option<u32>, usize) {
+
+        self.local_tick = self.local_tick.wrapping_add(1);
+
+        let target = match self.server_tick() {
+            Some(server_tick) => server_tick + self.lead() as f64,
+            None => return (None, 1)
+        };
+
+        // Jump straight to the target when aligning for the first time or
+        // when being off by more than a second
+        let error = target - tick as f64;
+        if self.synced_samples.is_none() || error.abs() > self.tick_rate as f64 {
+            self.synced_samples = Some(0);
+            self.scale = 1.0;
+            self.remainder = 0.0;
+            return (Some(target.max(0.0).round() as u32), 1);
+        }
+
+        let scale = 1.0 + (error as f32 * TICK_CORRECTION).max(-MAX_TICK_SCALE).min(MAX_TICK_SCALE);
+        self.scale += (scale - self.scale) * TICK_CORRECTION;
+        self.remainder += self.scale;
+
+        let ticks = self.remainder.floor();
+        self.remainder -= ticks;
+        (None, ticks as usize)
+
+    }
+
+    fn round_trip(&self) -> Option<f32> {
+        self.samples.iter().map(|s| s.0).min().map(|ticks| ticks as f32)
+    }
+
+    // Inputs are buffered for the configured time plus the round trip jitter
+    fn target_depth(&self) -> f32 {
+        let min = self.samples.iter().map(|s| s.0).min().unwrap_or(0);
+        let max = self.samples.iter().map(|s| s.0).max().unwrap_or(0);
+        self.input_buffer_ms as f32 / self.tick_ms() + (max - min) as f32 * 0.5
+    }
+
+    fn tick_ms(&self) -> f32 {
+        1000.0 / self.tick_rate as f32
+    }
+
+}
+
+
+// Server Side Clock ----------------------------------------------------------
+pub struct ServerClock {
+
+    // Pending sync requests of each connection
+    requests: HashMap<ConnectionID, u32>,
+
+    // Smoothed number of ticks inputs arrive ahead of time for each connection
+    input_depths: HashMap<ConnectionID, f32>
+
+}
+
+impl ServerClock {
+
+    pub fn new() -> ServerClock {
+        ServerClock {
+            requests: HashMap::new(),
+            input_depths: HashMap::new()
+        }
+    }
+
+    pub fn request(&mut self, owner: &ConnectionID, client_tick: u32) {
+        self.requests.insert(*owner, client_tick);
+    }
+
+    pub fn input(&mut self, owner: &ConnectionID, input_tick: u8, server_tick: u32) {
+        let sample = input_tick.wrapping_sub(server_tick as u8) as i8 as f32;
+        let depth = self.input_depths.entry(*owner).or_insert(sample);
+        *depth += (sample - *depth) * DEPTH_SMOOTHING;
+    }
+
+    // Answers the pending sync request of the connection, if any
+    pub fn response(&mut self, owner: &ConnectionID, server_tick: u32) -> Vec<u8> {
+        if let Some(client_tick) = self.requests.remove(owner) {
+            let depth = self.input_depths.get(owner).map_or(0.0, |d| *d);
+            SyncHeader::serialize(Some(&SyncHeader {
+                client_tick: client_tick,
+                server_tick: server_tick,
+                input_depth: depth.max(-128.0).min(127.0).round() as i8
+            }))
+
+        } else {
+            SyncHeader::serialize(None)
+        }
+    }
+
+    pub fn reset(&mut self, owner: &ConnectionID) {
+        self.requests.remove(owner);
+        self.input_depths.remove(owner);
+    }
+
+}
+
+
+// Helpers --------------------------------------------------------------------
+pub fn serialize_u32(value: u32) -> Vec<u8> {
+    vec![
+        (value >> 24) as u8,
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8
+    ]
+}
+
+pub fn deserialize_u32(data: &[u8]) -> u32 {
+    (data[0] as u32) << 24 | (data[1] as u32) << 16
+        | (data[2] as u32) << 8 | data[3] as u32
+}
+
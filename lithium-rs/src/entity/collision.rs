@@ -0,0 +1,304 @@
+// External Dependencies ------------------------------------------------------
+use std::collections::{HashMap, HashSet};
+
+
+// Collision Shapes -----------------------------------------------------------
+#[derive(Debug, Clone)]
+pub enum CollisionShape {
+
+    // Radius
+    Circle(f32),
+
+    // Convex polygon, points are relative to the entity position
+    Polygon(Vec<(f32, f32)>)
+
+}
+
+impl CollisionShape {
+
+    pub fn radius(&self) -> f32 {
+        match *self {
+            CollisionShape::Circle(r) => r,
+            CollisionShape::Polygon(ref points) => {
+                points.iter().fold(0.0, |r, &(x, y)| {
+                    r.max((x * x + y * y).sqrt())
+                })
+            }
+        }
+    }
+
+    // Checks whether this shape overlaps the other shape which is located at
+    // the given offset
+    pub fn overlaps(&self, other: &CollisionShape, offset: (f32, f32)) -> bool {
+        match (self, other) {
+
+            (&CollisionShape::Circle(a), &CollisionShape::Circle(b)) => {
+                offset.0 * offset.0 + offset.1 * offset.1 <= (a + b) * (a + b)
+            },
+
+            (&CollisionShape::Circle(r), &CollisionShape::Polygon(ref points)) => {
+                circle_overlaps_polygon(r, (-offset.0, -offset.1), points)
+            },
+
+            (&CollisionShape::Polygon(ref points), &CollisionShape::Circle(r)) => {
+                circle_overlaps_polygon(r, offset, points)
+            },
+
+            (&CollisionShape::Polygon(ref a), &CollisionShape::Polygon(ref b)) => {
+                let b: Vec<(f32, f32)> = b.iter().map(|&(x, y)| {
+                    (x + offset.0, y + offset.1)
+
+                }).collect();
+
+                !has_separating_axis(a, &b) && !has_separating_axis(&b, a)
+            }
+
+        }
+    }
+
+}
+
+
+// Broadphase -----------------------------------------------------------------
+pub struct CollisionGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>
+}
+
+impl CollisionGrid {
+
+    pub fn new(cell_size: f32) -> CollisionGrid {
+        CollisionGrid {
+            cell_size: cell_size,
+            cells: HashMap::new()
+        }
+    }
+
+    pub fn set_cell_size(&mut self, cell_size: f32) {
+        self.cell_size = cell_size;
+    }
+
+    // Returns the ids of all pairs of overlapping colliders, the wrap bounds
+    // are used to treat colliders at opposing edges of the world as adjacent
+    pub fn collisions(
+        &mut self,
+        colliders: &[(u16, (f32, f32), CollisionShape)],
+        wrap_bounds: Option<(f32, f32)>
+
+    ) -> Vec<(u16, u16)> {
+
+        // Wrapping worlds are divided into a whole number of cells so that
+        // cells at opposing edges are actually adjacent, this makes the
+        // cells slightly larger than configured
+        let grid_size = wrap_bounds.map(|(w, h)| {
+            (
+                ((w / self.cell_size).floor() as i32).max(1),
+                ((h / self.cell_size).floor() as i32).max(1)
+            )
+        });
+
+        let (cell_w, cell_h) = match (wrap_bounds, grid_size) {
+            (Some((w, h)), Some((cols, rows))) => (w / cols as f32, h / rows as f32),
+            _ => (self.cell_size, self.cell_size)
+        };
+
+        // Insert all colliders into the cells covered by their bounding box
+        self.cells.clear();
+        for (index, &(_, (x, y), ref shape)) in colliders.iter().enumerate() {
+
+            let r = shape.radius();
+            let (x0, y0) = (((x - r) / cell_w).floor() as i32, ((y - r) / cell_h).floor() as i32);
+            let (x1, y1) = (((x + r) / cell_w).floor() as i32, ((y + r) / cell_h).floor() as i32);
+
+            let mut inserted = HashSet::new();
+            for cx in x0..x1 + 1 {
+                for cy in y0..y1 + 1 {
+
+                    let cell = match grid_size {
+                        Some((cols, rows)) => (wrap(cx, cols), wrap(cy, rows)),
+                        None => (cx, cy)
+                    };
+
+                    if inserted.insert(cell) {
+                        self.cells.entry(cell).or_insert_with(Vec::new).push(index);
+                    }
+
+                }
+            }
+
+        }
+
+        // Check all potential pairs in each of the cells
+        let mut checked = HashSet::new();
+        let mut collisions = Vec::new();
+        for (_, indices) in &self.cells {
+            for (i, a) in indices.iter().enumerate() {
+                for b in &indices[i + 1..] {
+
+                    let pair = if a < b { (*a, *b) } else { (*b, *a) };
+                    if !checked.insert(pair) {
+                        continue;
+                    }
+
+                    let (id_a, pos_a, ref shape_a) = colliders[pair.0];
+                    let (id_b, pos_b, ref shape_b) = colliders[pair.1];
+                    if shape_a.overlaps(shape_b, delta(pos_a, pos_b, wrap_bounds)) {
+                        collisions.push((id_a, id_b));
+                    }
+
+                }
+            }
+        }
+
+        collisions
+
+    }
+
+}
+
+
+// Helpers --------------------------------------------------------------------
+pub fn delta(
+    from: (f32, f32), to: (f32, f32), wrap_bounds: Option<(f32, f32)>
+
+) -> (f32, f32) {
+
+    let (mut dx, mut dy) = (to.0 - from.0, to.1 - from.1);
+
+    // Use the shortest distance across the world edges
+    if let Some((w, h)) = wrap_bounds {
+        if dx > w * 0.5 {
+            dx -= w;
+
+        } else if dx < -w * 0.5 {
+            dx += w;
+        }
+
+        if dy > h * 0.5 {
+            dy -= h;
+
+        } else if dy < -h * 0.5 {
+            dy += h;
+        }
+    }
+
+    (dx, dy)
+
+}
+
+fn wrap(value: i32, size: i32) -> i32 {
+    ((value % size) + size) % size
+}
+
+fn project(points: &[(f32, f32)], axis: (f32, f32)) -> (f32, f32) {
+    points.iter().fold((::std::f32::MAX, ::std::f32::MIN), |(min, max), &(x, y)| {
+        let p = x * axis.0 + y * axis.1;
+        (min.min(p), max.max(p))
+    })
+}
+
+fn has_separating_axis(a: &[(f32, f32)], b: &[(f32, f32)]) -> bool {
+    for i in 0..a.len() {
+        let (ax, ay) = a[i];
+        let (bx, by) = a[(i + 1) % a.len()];
+        let axis = (ay - by, bx - ax);
+        let (min_a, max_a) = project(a, axis);
+        let (min_b, max_b) = project(b, axis);
+        if max_a < min_b || max_b < min_a {
+            return true;
+        }
+    }
+    false
+}
+
+fn circle_overlaps_polygon(r: f32, center: (f32, f32), points: &[(f32, f32)]) -> bool {
+
+    // Axis from the closest polygon point towards the circle center
+    let closest = points.iter().fold((0.0, 0.0, ::std::f32::MAX), |c, &(x, y)| {
+        let d = (x - center.0) * (x - center.0) + (y - center.1) * (y - center.1);
+        if d < c.2 {
+            (x, y, d)
+
+        } else {
+            c
+        }
+    });
+
+    let mut axes = vec![(center.0 - closest.0, center.1 - closest.1)];
+    for i in 0..points.len() {
+        let (ax, ay) = points[i];
+        let (bx, by) = points[(i + 1) % points.len()];
+        axes.push((ay - by, bx - ax));
+    }
+
+    for axis in axes {
+
+        let l = (axis.0 * axis.0 + axis.1 * axis.1).sqrt();
+        if l == 0.0 {
+            continue;
+        }
+
+        let axis = (axis.0 / l, axis.1 / l);
+        let (min, max) = project(points, axis);
+        let c = center.0 * axis.0 + center.1 * axis.1;
+        if c + r < min || c - r > max {
+            return false;
+        }
+
+    }
+
+    true
+
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use super::{CollisionGrid, CollisionShape};
+
+    #[test]
+    fn test_collision_across_wrapped_edge() {
+
+        // The world size is not a multiple of the cell size
+        let colliders = vec![
+            (0, (2.0, 50.0), CollisionShape::Circle(8.0)),
+            (1, (91.0, 50.0), CollisionShape::Circle(4.0))
+        ];
+
+        let mut grid = CollisionGrid::new(32.0);
+        let collisions = grid.collisions(&colliders, Some((100.0, 100.0)));
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions[0] == (0, 1) || collisions[0] == (1, 0));
+
+    }
+
+    #[test]
+    fn test_collision_across_wrapped_corner() {
+
+        let colliders = vec![
+            (0, (1.0, 1.0), CollisionShape::Circle(4.0)),
+            (1, (98.0, 98.0), CollisionShape::Circle(4.0))
+        ];
+
+        let mut grid = CollisionGrid::new(32.0);
+        assert_eq!(grid.collisions(&colliders, Some((100.0, 100.0))).len(), 1);
+
+    }
+
+    #[test]
+    fn test_no_collision_without_wrapping() {
+
+        let colliders = vec![
+            (0, (2.0, 50.0), CollisionShape::Circle(8.0)),
+            (1, (91.0, 50.0), CollisionShape::Circle(4.0))
+        ];
+
+        let mut grid = CollisionGrid::new(32.0);
+        assert!(grid.collisions(&colliders, None).is_empty());
+
+    }
+
+}
+
@@ -1,51 +1,82 @@
 // External Dependencies ------------------------------------------------------
 use cobalt::ConnectionID;
-use std::collections::VecDeque;
+use std::cmp;
+use std::mem;
+use std::collections::{BTreeMap, VecDeque};
+use std::collections::vec_deque::Iter;
 
 
 // Internal Dependencies ------------------------------------------------------
 use renderer::Renderer;
+use network::DecodeError;
 use level::{Level, BaseLevel};
-use entity::{EntityEvent, EntityInput, EntityState};
+use entity::{CollisionShape, EntityEvent, EntityHandle, EntityInput, EntityState};
+use entity::input;
+use entity::input::InputStats;
 use entity::traits::{BaseEntity, DrawableEntity};
+use entity::manager::snapshot::SnapshotEntity;
+
+
+// Time in seconds over which remote entities are blended back onto their
+// received states once they ran out of them and were extrapolated
+const UNDERRUN_BLEND_TIME: f32 = 0.1;
+
+// Number of most recent corrections the misprediction statistics cover
+const MISPREDICTION_SAMPLES: usize = 30;
 
 
 // Entity Wrapper Structure ---------------------------------------------------
-pub struct Entity<S: EntityState, L: BaseLevel<S>, R: Renderer> {
+pub struct Entity<S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer> {
 
-    entity: Box<BaseEntity<S, L>>,
+    entity: Box<BaseEntity<S, I, L>>,
     drawable: Box<DrawableEntity<S, L, R>>,
     owner: Option<ConnectionID>,
     is_alive: bool,
     is_visible: bool,
     local_id: u16,
+    generation: u8,
 
     // State
     state: S,
     base_state: S,
     last_state: S,
-    confirmed_state: Option<(u8, S)>,
-    state_buffer: VecDeque<(u8, S)>,
+    confirmed_state: Option<(u32, S)>,
+    state_buffer: VecDeque<(u32, S)>,
+    rewound_states: Option<(S, S, S)>,
+    remote_states: VecDeque<(u32, S)>,
+    drawn_state: Option<S>,
+    extrapolating: bool,
+    blend_remaining: f32,
+    correction_offset: (f32, f32),
+    mispredictions: VecDeque<f32>,
 
     // Inputs
-    input_buffer: VecDeque<EntityInput>,
-    confirmed_input_tick: u8,
-    initial_input: bool,
+    input_buffer: VecDeque<I>,
+    input_queue: BTreeMap<u32, I>,
+    input_stats: InputStats,
+    confirmed_input_tick: u32,
+    confirmed_inputs: VecDeque<I>,
+    last_input: Option<(u32, I)>,
+    acknowledged_input: Option<I>,
+    last_confirmed_tick: Option<u32>,
     serialized_inputs: Option<Vec<u8>>,
 
     // Configuration
     input_buffer_size: usize,
-    state_buffer_size: usize
+    input_redundancy: usize,
+    state_buffer_size: usize,
+    max_extrapolation_ticks: f32,
+    correction_time: f32
 
 }
 
-impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
+impl<S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer> Entity<S, I, L, R> {
 
     pub fn new(
-        entity: Box<BaseEntity<S, L>>,
+        entity: Box<BaseEntity<S, I, L>>,
         drawable: Box<DrawableEntity<S, L, R>>
 
-    ) -> Entity<S, L, R> {
+    ) -> Entity<S, I, L, R> {
         Entity {
 
             // Entity Behavior
@@ -67,6 +98,9 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
             // Locally used Entity ID
             local_id: 0,
 
+            // Number of times the ID was re-used before
+            generation: 0,
+
             // Current - calculated - entity state
             state: S::default(),
 
@@ -83,19 +117,51 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
             // and server-side latency compensation
             state_buffer: VecDeque::new(),
 
+            // Actual states while the entity is rewound (server only)
+            rewound_states: None,
+
+            // Received states along with their snapshot tick, most recent
+            // first, and the state the entity was last drawn with. Remote
+            // entities which ran out of states are extrapolated and then
+            // blended back onto the received ones (client only)
+            remote_states: VecDeque::new(),
+            drawn_state: None,
+            extrapolating: false,
+            blend_remaining: 0.0,
+
+            // Render space offset between the previously predicted and the
+            // corrected state, which decays over time, and the distance of
+            // the most recent correction (client only)
+            correction_offset: (0.0, 0.0),
+            mispredictions: VecDeque::with_capacity(MISPREDICTION_SAMPLES),
+
             // Pending inputs (client only)
             input_buffer: VecDeque::new(),
 
             // Serialized inputs (client only)
             serialized_inputs: None,
 
-            // Last tick for which input was received (server only)
+            // Received inputs waiting for their tick (server only)
+            input_queue: BTreeMap::new(),
+            input_stats: InputStats::default(),
+
+            // Last tick for which input was applied, the most recently
+            // received inputs used as delta bases and the last applied input
+            // along with its full tick (server only)
             confirmed_input_tick: 0,
-            initial_input: true,
+            confirmed_inputs: VecDeque::new(),
+            last_input: None,
+
+            // Last input acknowledged by the server (client only)
+            acknowledged_input: None,
+            last_confirmed_tick: None,
 
             // Configuration
             input_buffer_size: 30,
-            state_buffer_size: 30
+            input_redundancy: 30,
+            state_buffer_size: 30,
+            max_extrapolation_ticks: 0.0,
+            correction_time: 0.0
 
         }
     }
@@ -106,10 +172,26 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
         self.local_id
     }
 
+    pub fn type_id(&self) -> u8 {
+        self.entity.type_id()
+    }
+
     pub fn set_id(&mut self, id: u16) {
         self.local_id = id;
     }
 
+    pub fn generation(&self) -> u8 {
+        self.generation
+    }
+
+    pub fn set_generation(&mut self, generation: u8) {
+        self.generation = generation;
+    }
+
+    pub fn handle(&self) -> EntityHandle {
+        EntityHandle::new(self.local_id, self.generation)
+    }
+
     pub fn local(&self) -> bool {
         self.state.flags() & 0x01 == 0x01
     }
@@ -127,6 +209,26 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
         self.state_buffer_size = ticks;
     }
 
+    // Number of most recent unconfirmed inputs which are re-sent every tick
+    pub fn set_input_redundancy(&mut self, ticks: usize) {
+        self.input_redundancy = ticks;
+    }
+
+    // Number of ticks remote entities are extrapolated beyond their most
+    // recently received state
+    pub fn set_max_extrapolation(&mut self, ticks: f32) {
+        self.max_extrapolation_ticks = ticks;
+    }
+
+    // Time in seconds over which corrections of locally predicted states are
+    // smoothed out, zero disables smoothing
+    pub fn set_correction_smoothing(&mut self, seconds: f32) {
+        self.correction_time = seconds;
+        if seconds <= 0.0 {
+            self.correction_offset = (0.0, 0.0);
+        }
+    }
+
 
     // Ownership --------------------------------------------------------------
     pub fn owner(&self) -> Option<&ConnectionID> {
@@ -145,22 +247,37 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
     }
 
 
+    // Collision --------------------------------------------------------------
+    pub fn collision_shape(&self) -> Option<CollisionShape> {
+        self.entity.collision_shape(&self.state)
+    }
+
+
     // Visibility -------------------------------------------------------------
     pub fn visible_to(&self, owner: &ConnectionID) -> bool {
         self.entity.visible_to(owner)
     }
 
+    pub fn priority(&self) -> f32 {
+        self.entity.priority(&self.state)
+    }
+
     pub fn is_visible(&self) -> bool {
         self.is_visible
     }
 
-    pub fn show(&mut self, tick: u8) {
+    // Pending corrections would otherwise offset the entity from wherever it
+    // gets shown again
+    pub fn show(&mut self, tick: u32) {
         self.is_visible = true;
+        self.correction_offset = (0.0, 0.0);
+        self.reset_remote_states();
         self.event(EntityEvent::Show(tick));
     }
 
-    pub fn hide(&mut self, tick: u8) {
+    pub fn hide(&mut self, tick: u32) {
         self.is_visible = false;
+        self.correction_offset = (0.0, 0.0);
         self.event(EntityEvent::Hide(tick));
     }
 
@@ -198,34 +315,81 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
         }
     }
 
-    /*
     pub fn rewind_state(&mut self, tick_offset: usize) {
-        // TODO clean up
+
+        // Keep the actual states around so they can be restored later on
+        if self.rewound_states.is_none() {
+            self.rewound_states = Some((
+                self.state.clone(),
+                self.base_state.clone(),
+                self.last_state.clone()
+            ));
+        }
+
         let (state, last_state) = {
             let states = self.buffered_states(tick_offset);
             (states.0.clone(), states.1.clone())
         };
+
         self.state.set_to(&state);
         self.base_state.set_to(&state);
         self.last_state.set_to(&last_state);
+
     }
 
     pub fn forward_state(&mut self) {
-        self.rewind_state(0);
+        if let Some((state, base_state, last_state)) = self.rewound_states.take() {
+            self.state.set_to(&state);
+            self.base_state.set_to(&base_state);
+            self.last_state.set_to(&last_state);
+        }
     }
-    */
+
     pub fn set_state(&mut self, state: S) {
+        self.correction_offset = (0.0, 0.0);
         self.set_entity_state(state, true);
     }
 
-    pub fn set_remote_state(&mut self, state: S) {
+    pub fn set_remote_state(&mut self, tick: u32, state: S) {
+
+        // Keep the states ordered by their tick in case snapshots arrive out
+        // of order
+        let index = self.remote_states.iter().position(|&(t, _)| t <= tick);
+        match index {
+            Some(i) if self.remote_states[i].0 == tick => {},
+            Some(i) => self.remote_states.insert(i, (tick, state.clone())),
+            None => self.remote_states.push_back((tick, state.clone()))
+        }
+
+        if self.remote_states.len() > self.state_buffer_size {
+            self.remote_states.pop_back();
+        }
+
         self.set_entity_state(state, false);
+
     }
 
-    pub fn set_confirmed_state(&mut self, tick: u8, state: S) {
+    pub fn set_confirmed_state(&mut self, tick: u32, state: S) {
         self.confirmed_state = Some((tick, state));
     }
 
+    // Largest distance between the predicted and the corrected state during
+    // the most recent corrections (client only)
+    pub fn misprediction(&self) -> f32 {
+        self.mispredictions.iter().fold(0.0, |max: f32, m| max.max(*m))
+    }
+
+    // Average distance between the predicted and the corrected state during
+    // the most recent corrections (client only)
+    pub fn average_misprediction(&self) -> f32 {
+        if self.mispredictions.is_empty() {
+            0.0
+
+        } else {
+            self.mispredictions.iter().fold(0.0, |sum, m| sum + m) / self.mispredictions.len() as f32
+        }
+    }
+
     fn set_entity_state(&mut self, new_state: S, override_last: bool) {
 
         let old_flags = self.state.flags();
@@ -247,17 +411,29 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
 
 
     // Input ------------------------------------------------------------------
-    pub fn local_input(&mut self, input: EntityInput) {
+    pub fn local_input(&mut self, input: I) {
 
         self.input(input);
 
-        let mut serialized_inputs = Vec::new();
-        for input in &self.input_buffer {
-            serialized_inputs.extend(input.serialize());
-        }
+        // Never re-send more inputs than fit into a single block
+        let count = cmp::min(self.input_redundancy, input::max_serialized_inputs::<I>());
+        let skip = self.input_buffer.len().saturating_sub(count);
+        let inputs: Vec<I> = self.input_buffer.iter().skip(skip).cloned().collect();
+
+        self.serialized_inputs = Some(
+            input::serialize_inputs(self.acknowledged_input.as_ref(), &inputs)
+        );
 
-        self.serialized_inputs = Some(serialized_inputs);
+    }
 
+    // Drops all unconfirmed inputs along with the delta base, used when the
+    // simulation jumps to another tick and the buffered inputs no longer
+    // line up with the ticks they were generated for (client only)
+    pub fn clear_inputs(&mut self) {
+        self.input_buffer.clear();
+        self.serialized_inputs = None;
+        self.acknowledged_input = None;
+        self.last_confirmed_tick = None;
     }
 
     pub fn serialized_inputs(&mut self) -> Option<Vec<u8>> {
@@ -269,20 +445,118 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
         }
     }
 
-    pub fn remote_input(&mut self, input: EntityInput) {
+    // Decodes and queues inputs serialized via local_input(), returns all
+    // decoded inputs including the ones which were already received before
+    pub fn remote_inputs(&mut self, data: &[u8], tick: u32) -> Result<Vec<I>, DecodeError> {
 
-        if self.initial_input ||  tick_is_more_recent(
-            input.tick,
-            self.confirmed_input_tick
+        let inputs = match input::from_serialized_inputs(
+            data, &self.confirmed_inputs, self.input_buffer_size
         ) {
-            self.initial_input = false;
-            self.confirmed_input_tick = input.tick;
-            self.input(input);
+            Ok(inputs) => inputs,
+            Err(err) => return Err(err)
+        };
+
+        for input in &inputs {
+            self.remote_input(input.clone(), tick);
+        }
+
+        Ok(inputs)
+
+    }
+
+    // Queues an input until its tick comes up, inputs which were already
+    // applied are ignored. Queued inputs are replaced by the most recently
+    // received input for their tick, in case the client moved its simulation
+    // back its new inputs take precedence (server only).
+    pub fn remote_input(&mut self, input: I, tick: u32) {
+
+        let input_tick = tick_from_u8(input.tick(), tick);
+        let applied = self.last_input.as_ref().map_or(false, |&(t, _)| input_tick <= t);
+        if applied {
+            return;
+        }
+
+        // Inputs from too far in the future can never be applied in time
+        if input_tick > tick + self.input_buffer_size as u32 {
+            self.input_stats.early += 1;
+            return;
         }
 
+        self.input_queue.insert(input_tick, input);
+
     }
 
-    fn input(&mut self, input: EntityInput) {
+    // Takes exactly one input from the queue for the given tick, always the
+    // oldest one which is due so late inputs are still applied in order.
+    // Only once more inputs are queued than could arrive in time the oldest
+    // ones are dropped. In case no input is due the last one is extrapolated
+    // and nothing is returned (server only).
+    pub fn consume_input(&mut self, tick: u32) -> Option<I> {
+
+        while self.input_queue.len() > self.input_buffer_size + 1 {
+            let oldest = *self.input_queue.keys().next().unwrap();
+            self.input_queue.remove(&oldest);
+            self.input_stats.overflowed += 1;
+        }
+
+        let due = self.input_queue.keys().next().cloned().and_then(|t| {
+            if t <= tick {
+                self.input_queue.remove(&t).map(|input| (t, input))
+
+            } else {
+                None
+            }
+        });
+
+        if let Some((input_tick, input)) = due {
+
+            // Only applied inputs are confirmed to the client and can
+            // serve as the base of its delta encoded inputs
+            self.confirmed_inputs.push_back(input.clone());
+            if self.confirmed_inputs.len() > self.input_buffer_size {
+                self.confirmed_inputs.pop_front();
+            }
+
+            if input_tick < tick {
+                self.input_stats.late += 1;
+            }
+
+            self.input_stats.consumed += 1;
+            self.confirmed_input_tick = input_tick;
+            self.last_input = Some((input_tick, input.clone()));
+            self.input(input.clone());
+            Some(input)
+
+        } else {
+            let extrapolated = self.last_input.as_ref().map(|&(_, ref input)| {
+                input.extrapolate()
+            });
+
+            if let Some(input) = extrapolated {
+                self.input_stats.starved += 1;
+                self.input(input);
+            }
+
+            None
+        }
+
+    }
+
+    // Returns the input statistics gathered since the last call (server only)
+    pub fn take_input_stats(&mut self) -> InputStats {
+        mem::replace(&mut self.input_stats, InputStats::default())
+    }
+
+    // Number of received inputs waiting for their tick (server only)
+    pub fn queued_inputs(&self) -> usize {
+        self.input_queue.len()
+    }
+
+    pub fn inputs(&self) -> Iter<I> {
+        self.input_buffer.iter()
+    }
+
+    fn input(&mut self, input: I) {
 
         self.input_buffer.push_back(input);
 
@@ -295,29 +569,58 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
 
 
     // Ticking ----------------------------------------------------------------
-    pub fn client_tick(&mut self, level: &Level<S, L>, tick: u8, dt: f32) {
+    pub fn client_tick(&mut self, level: &Level<S, L>, tick: u32, dt: f32) {
         self.event(EntityEvent::Tick(tick, dt)); // TODO useful?
         self.tick(level, tick, dt, false);
     }
 
-    pub fn server_tick(&mut self, level: &Level<S, L>, tick: u8, dt: f32) {
+    pub fn server_tick(&mut self, level: &Level<S, L>, tick: u32, dt: f32) {
         self.event(EntityEvent::Tick(tick, dt)); // TODO useful?
         self.tick(level, tick, dt, true);
     }
 
-    pub fn tick(&mut self, level: &Level<S, L>, tick: u8, dt: f32, server: bool) {
+    pub fn tick(&mut self, level: &Level<S, L>, tick: u32, dt: f32, server: bool) {
+
+        // State which would have been predicted for this tick without a
+        // correction from the remote
+        let mut predicted = None;
 
         // Check if we have a remote state
         if let Some((confirmed_tick, confirmed_state)) = self.confirmed_state.take() {
 
+            let mut state = self.base_state.clone();
+            for input in &self.input_buffer {
+                self.entity.apply_input(level, &mut state, input, dt);
+            }
+
+            predicted = Some(state);
+
             // Set the current state as the last state and tkae over the
             // confirmed state as new base state
             self.set_entity_state(confirmed_state, false);
 
+            // Keep the confirmed input around as the delta base for all
+            // further inputs. The confirmed tick is only trusted once it
+            // changed, before the remote received any input it refers to
+            // no input at all.
+            if self.last_confirmed_tick.map_or(false, |t| t != confirmed_tick) {
+                let acknowledged = self.input_buffer.iter().find(|input| {
+                    input.tick() == confirmed_tick as u8
+
+                }).cloned();
+
+                if acknowledged.is_some() {
+                    self.acknowledged_input = acknowledged;
+                }
+            }
+
+            self.last_confirmed_tick = Some(confirmed_tick);
+
             // Drop all inputs confirmed by the remote so the remaining ones
-            // get applied on top of the new base state
+            // get applied on top of the new base state, the buffered inputs
+            // are never further apart than the buffer size
             self.input_buffer.retain(|input| {
-                tick_is_more_recent(input.tick, confirmed_tick)
+                tick_from_u8(input.tick(), confirmed_tick) > confirmed_tick
             });
 
         // Otherwise reset the local state and re-apply the inputs on top of it
@@ -332,6 +635,35 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
             self.entity.apply_input(level, &mut new_state, input, dt);
         }
 
+        // Advance the simulation of authoritative entities
+        if server {
+            self.entity.update(level, &mut new_state, dt);
+        }
+
+        // Decay the visual offset of earlier corrections with the simulation
+        // so it also wears off while the entity is not drawn
+        if self.correction_time > 0.0 {
+            let decay = (1.0 - dt / self.correction_time).max(0.0);
+            self.correction_offset.0 *= decay;
+            self.correction_offset.1 *= decay;
+        }
+
+        // Instead of popping to the corrected state, keep drawing the entity
+        // at its predicted position and let the offset decay over time
+        if let Some(predicted) = predicted {
+
+            let (dx, dy) = level.entity_state_distance(&new_state, &predicted);
+            self.mispredictions.push_back((dx * dx + dy * dy).sqrt());
+            if self.mispredictions.len() > MISPREDICTION_SAMPLES {
+                self.mispredictions.pop_front();
+            }
+
+            if self.correction_time > 0.0 {
+                self.correction_offset.0 += dx;
+                self.correction_offset.1 += dy;
+            }
+        }
+
         // Assign calculated state
         self.state.set_to(&new_state);
 
@@ -351,40 +683,119 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
 
 
     // Drawing ----------------------------------------------------------------
-    pub fn draw(&mut self, renderer: &mut R, level: &Level<S, L>) {
+    // Remote entities are drawn at the given fractional snapshot tick, in case
+    // there is one, otherwise they trail the simulation by a fixed number of
+    // ticks (client only)
+    pub fn draw(&mut self, renderer: &mut R, level: &Level<S, L>, render_tick: Option<f64>) {
+        let state = self.draw_state(renderer, level, render_tick);
+        self.drawable.draw(renderer, level, state);
+    }
+
+    // Computes the render space state the entity is drawn with
+    fn draw_state(&mut self, renderer: &mut R, level: &Level<S, L>, render_tick: Option<f64>) -> S {
+
+        let remote_state = match render_tick {
+            Some(tick) if !self.local() => {
+                self.remote_state_at(level, tick + renderer.delta_u() as f64)
+            },
+            _ => None
+        };
+
+        if let Some((target, extrapolated)) = remote_state {
+            let state = self.blend_remote_state(renderer, level, target, extrapolated);
+            level.interpolate_entity_state(renderer, &state, &state)
 
-        let state = if self.local() {
-            level.interpolate_entity_state(renderer, &self.state, &self.last_state)
+        } else if self.local() {
+
+            // Corrections are applied in simulation space, so they wrap
+            // around the level before being converted into render space
+            let mut state = self.state.clone();
+            let mut last_state = self.last_state.clone();
+            if self.correction_offset != (0.0, 0.0) {
+                level.offset_entity_state(&mut state, self.correction_offset);
+                level.offset_entity_state(&mut last_state, self.correction_offset);
+            }
+            level.interpolate_entity_state(renderer, &state, &last_state)
 
         } else {
             let offset = self.buffered_states(renderer.interpolation_ticks());
             level.interpolate_entity_state(renderer, &offset.0, &offset.1)
+        }
+
+    }
+
+    // Blends between the received states surrounding the tick, beyond the
+    // most recent state the entity is extrapolated for a limited number of
+    // ticks. Returns whether the state was extrapolated.
+    fn remote_state_at(&self, level: &Level<S, L>, tick: f64) -> Option<(S, bool)> {
+
+        let &(latest_tick, ref latest) = match self.remote_states.front() {
+            Some(latest) => latest,
+            None => return None
         };
 
-        self.drawable.draw(renderer, level, state);
+        if tick >= latest_tick as f64 {
+            let ticks = (tick - latest_tick as f64).min(self.max_extrapolation_ticks as f64);
+            return Some((level.extrapolate_entity_state(latest, ticks as f32), ticks > 0.0));
+        }
+
+        for (i, &(state_tick, ref state)) in self.remote_states.iter().enumerate().skip(1) {
+            if state_tick as f64 <= tick {
+                let &(next_tick, ref next) = &self.remote_states[i - 1];
+                let u = (tick - state_tick as f64) / (next_tick - state_tick) as f64;
+                return Some((level.blend_entity_state(state, next, u as f32), false));
+            }
+        }
+
+        // The tick lies before all of the buffered states
+        self.remote_states.back().map(|&(_, ref state)| (state.clone(), false))
 
     }
 
+    // Once states arrive again after the entity was extrapolated, the drawn
+    // state is blended towards the received ones over a short time instead
+    // of snapping onto them
+    fn blend_remote_state(
+        &mut self,
+        renderer: &R, level: &Level<S, L>,
+        target: S, extrapolated: bool
+
+    ) -> S {
+
+        if self.extrapolating && !extrapolated {
+            self.blend_remaining = UNDERRUN_BLEND_TIME;
+        }
+
+        self.extrapolating = extrapolated;
+
+        let state = match self.drawn_state {
+            Some(ref drawn) if self.blend_remaining > 0.0 => {
+                let dt = renderer.delta_time();
+                let u = (dt / self.blend_remaining).min(1.0);
+                self.blend_remaining = (self.blend_remaining - dt).max(0.0);
+                level.blend_entity_state(drawn, &target, u)
+            },
+            _ => target
+        };
+
+        self.drawn_state = Some(state.clone());
+        state
 
-    // Serialization ----------------------------------------------------------
-    pub fn header_size() -> usize {
-        5
     }
 
-    pub fn serialize_state(&self, owner: &ConnectionID) -> Vec<u8> {
+    fn reset_remote_states(&mut self) {
+        self.remote_states.clear();
+        self.drawn_state = None;
+        self.extrapolating = false;
+        self.blend_remaining = 0.0;
+    }
 
-        // Entity Header
-        let is_visible = self.visible_to(owner);
-        let mut data = [
-            (self.local_id >> 8) as u8, self.local_id as u8,
-            self.entity.type_id(),
-            self.confirmed_input_tick,
-            is_visible as u8
 
-        ].to_vec();
+    // Serialization ----------------------------------------------------------
+    pub fn serialize_state(&self, owner: &ConnectionID) -> SnapshotEntity<S> {
 
         // Serialize state only for visible entities
-        if is_visible {
+        let state = if self.visible_to(owner) {
 
             // Create a copy of the current state
             let mut state: S = self.state.clone();
@@ -398,10 +809,18 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
             // Invoke type specific serialization handler
             self.entity.serialize_state(&mut state, owner);
 
-            data.extend(state.serialize());
-        }
+            Some(state)
+
+        } else {
+            None
+        };
 
-        data
+        SnapshotEntity {
+            type_id: self.entity.type_id(),
+            generation: self.generation,
+            confirmed_tick: self.confirmed_input_tick as u8,
+            state: state
+        }
 
     }
 
@@ -416,7 +835,137 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer> Entity<S, L, R> {
 
 
 // Helpers --------------------------------------------------------------------
-pub fn tick_is_more_recent(a: u8, b: u8) -> bool {
-    (a > b) && (a - b <= 128) || (b > a) && (b - a > 128)
+// Reconstructs a full tick from its lower 8 bits as sent over the network,
+// picking the tick which is closest to the given reference tick
+pub fn tick_from_u8(tick: u8, reference: u32) -> u32 {
+    let offset = tick.wrapping_sub(reference as u8) as i8;
+    if offset < 0 {
+        reference.saturating_sub(-(offset as i32) as u32)
+
+    } else {
+        reference + offset as u32
+    }
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use mock;
+    use mock::{MockState, MockInput, MockLevel, MockRegistry};
+    use renderer::DefaultRenderer;
+    use std::collections::VecDeque;
+    use entity::EntityRegistry;
+    use entity::input::{from_serialized_inputs, max_serialized_inputs};
+    use super::Entity;
+
+    fn local_entity(x: f32) -> Entity<MockState, MockInput, MockLevel, DefaultRenderer> {
+        let mut entity = MockRegistry.entity_from_type_id(0).unwrap();
+        entity.set_buffer_size(2);
+        entity.set_correction_smoothing(0.1);
+        entity.set_state(local_state(x));
+        entity
+    }
+
+    fn local_state(x: f32) -> MockState {
+        MockState {
+            x: x,
+            y: 0.0,
+            flags: 0x01
+        }
+    }
+
+    #[test]
+    fn test_correction_decays_with_ticks() {
+
+        let level = mock::level();
+        let mut entity = local_entity(100.0);
+        entity.set_confirmed_state(0, local_state(80.0));
+        entity.tick(&level, 0, 1.0 / 30.0, false);
+        assert_eq!(entity.correction_offset, (20.0, 0.0));
+
+        // The offset decays even though the entity is never drawn
+        let mut last = entity.correction_offset.0;
+        for tick in 1..10 {
+            entity.tick(&level, tick, 1.0 / 30.0, false);
+            assert!(entity.correction_offset.0 < last);
+            last = entity.correction_offset.0;
+        }
+
+        entity.hide(10);
+        assert_eq!(entity.correction_offset, (0.0, 0.0));
+
+    }
+
+    #[test]
+    fn test_correction_wraps() {
+        let mut state = local_state(990.0);
+        mock::level().offset_entity_state(&mut state, (20.0, 0.0));
+        assert_eq!(state.x, 10.0);
+    }
+
+    // Entities inside of the level border are drawn at negative positions
+    // and must not be wrapped around to the far side of the level
+    #[test]
+    fn test_correction_inside_border() {
+
+        let level = mock::bordered_level(16.0);
+        let mut entity = local_entity(8.0);
+        assert_eq!(entity.draw_state(&mut DefaultRenderer, &level, None).x, -8.0);
+
+        entity.set_confirmed_state(0, local_state(4.0));
+        entity.tick(&level, 0, 1.0 / 30.0, false);
+        assert_eq!(entity.correction_offset, (4.0, 0.0));
+
+        // Mock levels draw the previous state, which was at 8
+        assert_eq!(entity.draw_state(&mut DefaultRenderer, &level, None).x, -4.0);
+
+    }
+
+    #[test]
+    fn test_misprediction_window() {
+
+        let level = mock::level();
+        let mut entity = local_entity(0.0);
+        for tick in 0..super::MISPREDICTION_SAMPLES as u32 {
+            let x = entity.state().x;
+            let error = if tick == 0 { 30.0 } else { 0.0 };
+            entity.set_confirmed_state(tick, local_state(x + error));
+            entity.tick(&level, tick, 1.0 / 30.0, false);
+        }
+
+        assert_eq!(entity.misprediction(), 30.0);
+        assert_eq!(entity.average_misprediction(), 1.0);
+
+        // The large correction eventually leaves the window
+        entity.set_confirmed_state(100, local_state(30.0));
+        entity.tick(&level, 100, 1.0 / 30.0, false);
+        assert_eq!(entity.misprediction(), 0.0);
+
+    }
+
+    #[test]
+    fn test_input_redundancy_limit() {
+
+        let mut entity = local_entity(0.0);
+        entity.set_input_redundancy(1000);
+        entity.input_buffer_size = 1000;
+        for tick in 0..1000 {
+            entity.local_input(MockInput {
+                tick: tick as u8,
+                dx: tick as u8 % 2
+            });
+        }
+
+        // Only the most recent inputs which fit into a block are re-sent
+        let max = max_serialized_inputs::<MockInput>();
+        let data = entity.serialized_inputs().unwrap();
+        let inputs = from_serialized_inputs::<MockInput>(&data, &VecDeque::new(), 1000).unwrap();
+        assert_eq!(inputs.len(), max);
+        assert_eq!(inputs.last().unwrap().tick, 999u32 as u8);
+
+    }
+
 }
 
@@ -1,11 +1,11 @@
 // Server / Client Side Entity Events -----------------------------------------
 #[derive(Debug)]
 pub enum EntityEvent {
-    Tick(u8, f32),
-    Created(u8, u16),
-    Destroyed(u8, u16),
-    Hide(u8),
-    Show(u8),
+    Tick(u32, f32),
+    Created(u32, u16),
+    Destroyed(u32, u16),
+    Hide(u32),
+    Show(u32),
     Flags(u8)
 }
 
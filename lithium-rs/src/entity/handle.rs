@@ -0,0 +1,20 @@
+// Entity Handle --------------------------------------------------------------
+// Refers to one specific entity, ids are re-used once their entity has been
+// destroyed so the generation is used to tell apart stale references
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, RustcEncodable, RustcDecodable)]
+pub struct EntityHandle {
+    pub id: u16,
+    pub generation: u8
+}
+
+impl EntityHandle {
+
+    pub fn new(id: u16, generation: u8) -> EntityHandle {
+        EntityHandle {
+            id: id,
+            generation: generation
+        }
+    }
+
+}
+
@@ -1,40 +1,229 @@
 // External Dependencies ------------------------------------------------------
-use bincode::SizeLimit;
-use bincode::rustc_serialize::{encode, decode};
+use std::cmp;
+use std::collections::VecDeque;
 
 
-// Entity Input ---------------------------------------------------------------
-#[derive(Debug, RustcEncodable, RustcDecodable)]
-pub struct EntityInput {
-    pub tick: u8,
-    pub fields: u32
+// Internal Dependencies ------------------------------------------------------
+use network::DecodeError;
+use entity::EntityInput;
+
+
+// Input Encoding -------------------------------------------------------------
+// Largest block of serialized inputs which can be sent for a single entity
+pub const MAX_SERIALIZED_SIZE: usize = 65535;
+
+// Inputs are sent as runs of identical inputs on consecutive ticks. Each run
+// is delta encoded against the previous one, the first run against the last
+// input the server acknowledged, if any.
+//
+// Inputs: [has_base, base_tick, run_count, run...]
+// Run:    [tick, length, delta...]
+pub fn serialize_inputs<I: EntityInput>(base: Option<&I>, inputs: &[I]) -> Vec<u8> {
+
+    let mut data = vec![
+        base.is_some() as u8,
+        base.map_or(0, |b| b.tick()),
+        0
+    ];
+
+    let default = I::default();
+    let mut previous = base.unwrap_or(&default).clone();
+    let mut runs: Vec<(u8, u8, &I)> = Vec::new();
+
+    for input in inputs {
+
+        let extends_run = runs.last().map_or(false, |&(tick, length, run_input)| {
+            length < 255
+                && tick.wrapping_add(length) == input.tick()
+                && same_input(run_input, input)
+        });
+
+        if extends_run {
+            runs.last_mut().unwrap().1 += 1;
+
+        } else {
+            runs.push((input.tick(), 1, input));
+        }
+
+    }
+
+    let run_count = cmp::min(runs.len(), 255);
+    for &(tick, length, input) in runs.iter().take(run_count) {
+        data.push(tick);
+        data.push(length);
+        data.extend(input.serialize_delta(&previous));
+        previous = input.clone();
+    }
+
+    data[2] = run_count as u8;
+    data
+
 }
 
-impl EntityInput {
+// Number of inputs which always fit into a single block, even if every one
+// of them starts its own run and its delta takes one byte more than the full
+// input
+pub fn max_serialized_inputs<I: EntityInput>() -> usize {
+    cmp::min((MAX_SERIALIZED_SIZE - 3) / (3 + I::encoded_size()), 255)
+}
+
+// Decodes inputs using the history of inputs which were already received in
+// order to find the base, inputs whose base is no longer known are skipped.
+// Runs can expand to far more inputs than could ever be buffered, so data
+// which decodes to more than max_inputs is rejected as a whole.
+pub fn from_serialized_inputs<I: EntityInput>(
+    data: &[u8], history: &VecDeque<I>, max_inputs: usize
 
-    pub fn encoded_size() -> usize {
-        5
+) -> Result<Vec<I>, DecodeError> {
+
+    if data.len() < 3 {
+        return Err(DecodeError::Truncated);
     }
 
-    pub fn from_serialized(data: &[u8]) -> EntityInput {
-        match decode::<EntityInput>(data) {
+    let mut previous = if data[0] == 1 {
+        match history.iter().find(|i| i.tick() == data[1]) {
+            Some(input) => input.clone(),
+            None => return Ok(Vec::new())
+        }
+
+    } else {
+        I::default()
+    };
+
+    let mut inputs = Vec::new();
+    let mut offset = 3;
+    for _ in 0..data[2] {
+
+        if data.len() < offset + 2 {
+            return Err(DecodeError::Truncated);
+        }
+
+        let (tick, length) = (data[offset], data[offset + 1]);
+        if inputs.len() + length as usize > max_inputs {
+            return Err(DecodeError::Invalid);
+        }
+
+        let (input, len) = match I::from_serialized_delta(&previous, &data[offset + 2..]) {
             Ok(input) => input,
-            Err(_) => EntityInput::default()
+            Err(err) => return Err(err)
+        };
+
+        for i in 0..length {
+            let mut input = input.clone();
+            input.set_tick(tick.wrapping_add(i));
+            inputs.push(input);
         }
+
+        previous = input;
+        offset += 2 + len;
+
     }
 
-    pub fn serialize(&self) -> Vec<u8> {
-        encode(&self, SizeLimit::Infinite).unwrap()
+    Ok(inputs)
+
+}
+
+// Compares two inputs while ignoring the tick they were generated at
+fn same_input<I: EntityInput>(a: &I, b: &I) -> bool {
+    let mut b = b.clone();
+    b.set_tick(a.tick());
+    a.serialize() == b.serialize()
+}
+
+
+// Input Statistics -----------------------------------------------------------
+#[derive(Debug, Default, Copy, Clone)]
+pub struct InputStats {
+
+    // Inputs which were applied at or after their tick
+    pub consumed: u32,
+
+    // Consumed inputs which were applied after their tick
+    pub late: u32,
+
+    // Ticks without a due input for which the last input was extrapolated
+    pub starved: u32,
+
+    // Inputs which were dropped because they arrived too far ahead of the
+    // tick they were generated for
+    pub early: u32,
+
+    // Inputs which were dropped because too many of them were queued
+    pub overflowed: u32
+
+}
+
+impl InputStats {
+
+    pub fn add(&mut self, other: &InputStats) {
+        self.consumed += other.consumed;
+        self.late += other.late;
+        self.starved += other.starved;
+        self.early += other.early;
+        self.overflowed += other.overflowed;
     }
 
 }
 
-impl Default for EntityInput {
-    fn default() -> EntityInput {
-        EntityInput {
-            tick: 0,
-            fields: 0
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::collections::VecDeque;
+    use mock::MockInput;
+    use network::DecodeError;
+    use super::{
+        serialize_inputs, from_serialized_inputs, max_serialized_inputs, MAX_SERIALIZED_SIZE
+    };
+
+    fn inputs(count: u8) -> Vec<MockInput> {
+        (0..count).map(|tick| MockInput {
+            tick: tick,
+            dx: 1
+
+        }).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sent = inputs(30);
+        let data = serialize_inputs(None, &sent);
+        assert_eq!(from_serialized_inputs::<MockInput>(&data, &VecDeque::new(), 30).unwrap(), sent);
+    }
+
+    #[test]
+    fn test_exceeds_max_inputs() {
+
+        let data = serialize_inputs(None, &inputs(31));
+        match from_serialized_inputs::<MockInput>(&data, &VecDeque::new(), 30) {
+            Err(DecodeError::Invalid) => {},
+            _ => panic!("expected too many inputs to be rejected")
         }
+
+        // A single run claiming the maximum length
+        let mut data = serialize_inputs(None, &inputs(1));
+        data[4] = 255;
+        assert!(from_serialized_inputs::<MockInput>(&data, &VecDeque::new(), 30).is_err());
+
     }
+
+    #[test]
+    fn test_max_serialized_inputs() {
+
+        // Every input starts a new run
+        let max = max_serialized_inputs::<MockInput>();
+        let sent: Vec<MockInput> = (0..max).map(|i| MockInput {
+            tick: i as u8,
+            dx: i as u8 % 2
+
+        }).collect();
+
+        let data = serialize_inputs(None, &sent);
+        assert!(data.len() <= MAX_SERIALIZED_SIZE);
+        assert_eq!(from_serialized_inputs::<MockInput>(&data, &VecDeque::new(), max).unwrap(), sent);
+
+    }
+
 }
 
@@ -4,6 +4,10 @@ use bincode::rustc_serialize::{encode, decode};
 
 
 // Internal Dependencies ------------------------------------------------------
+use network::DecodeError;
+
+
+// Entity Manager Configuration -----------------------------------------------
 #[derive(Debug, RustcEncodable, RustcDecodable)]
 pub struct EntityManagerConfig {
 
@@ -24,8 +28,13 @@ impl EntityManagerConfig {
         3
     }
 
-    pub fn from_serialized(data: &[u8]) -> Self {
-        decode::<Self>(data).unwrap()
+    pub fn from_serialized(data: &[u8]) -> Result<Self, DecodeError> {
+        if data.len() >= EntityManagerConfig::encoded_size() {
+            decode::<Self>(data).map_err(DecodeError::from)
+
+        } else {
+            Err(DecodeError::Truncated)
+        }
     }
 
     pub fn serialize(&self) -> Vec<u8> {
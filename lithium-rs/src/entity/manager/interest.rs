@@ -0,0 +1,53 @@
+// External Dependencies ------------------------------------------------------
+use std::collections::HashMap;
+
+
+// Internal Dependencies ------------------------------------------------------
+use entity::EntityState;
+use entity::manager::snapshot::SnapshotEntity;
+
+
+// Lowest relevance of entities at the edge of the view radius, ensures that
+// even distant entities eventually receive an update
+pub const MIN_RELEVANCE: f32 = 0.1;
+
+
+// Connection Interest --------------------------------------------------------
+// Per connection priority accumulators, entities which are skipped due to the
+// state budget keep accumulating priority until they get sent
+pub struct Interest<S: EntityState> {
+
+    // Accumulated priority of all relevant entities
+    pub priorities: HashMap<u16, f32>,
+
+    // Last state sent for each entity, re-sent in place of a fresh state
+    // when an entity does not fit into the budget
+    pub sent: HashMap<u16, SnapshotEntity<S>>
+
+}
+
+impl<S: EntityState> Interest<S> {
+
+    pub fn new() -> Interest<S> {
+        Interest {
+            priorities: HashMap::new(),
+            sent: HashMap::new()
+        }
+    }
+
+    // Relevance of an entity at the given distance, scaling linearly from 1.0
+    // at the origin down to MIN_RELEVANCE at the view radius
+    pub fn relevance(distance: f32, radius: Option<f32>) -> Option<f32> {
+        match radius {
+            Some(radius) => if distance > radius {
+                None
+
+            } else {
+                Some((1.0 - distance / radius).max(MIN_RELEVANCE))
+            },
+            None => Some(1.0)
+        }
+    }
+
+}
+
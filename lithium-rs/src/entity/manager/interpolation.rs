@@ -0,0 +1,145 @@
+// Weight of each new transit time deviation on the jitter estimate, as used
+// by RFC 3550
+const JITTER_SMOOTHING: f64 = 1.0 / 16.0;
+
+// Number of average jitter deviations the delay covers on top of its minimum
+const JITTER_DELAY: f64 = 2.0;
+
+// Minimum delay in ticks behind the most recent snapshot
+const MIN_DELAY: f64 = 1.0;
+
+// Fraction of the delay change which is applied each tick
+const DELAY_SMOOTHING: f64 = 0.05;
+
+// Fraction of the playback error which is corrected each tick
+const PLAYBACK_CORRECTION: f64 = 0.1;
+
+// Maximum relative deviation of the playback rate
+const MAX_PLAYBACK_SCALE: f64 = 0.1;
+
+
+// Snapshot Interpolation -----------------------------------------------------
+// Tracks the arrival times of snapshots and derives the tick remote entities
+// are drawn at. It trails the most recent snapshot by a delay which adapts to
+// the measured arrival jitter and is approached by smoothly speeding up or
+// slowing down the playback.
+pub struct Interpolation {
+
+    // Local clock in ticks, independent of the simulation tick
+    local_tick: u32,
+
+    // Most recent snapshot tick along with the local tick it arrived at
+    latest: Option<(u32, u32)>,
+
+    // Transit time of the last snapshot and smoothed deviation of the transit
+    // times in ticks
+    transit: Option<f64>,
+    jitter: Option<f64>,
+
+    // Current delay and fractional snapshot tick to draw at
+    delay: f64,
+    render_tick: Option<f64>
+
+}
+
+impl Interpolation {
+
+    pub fn new() -> Interpolation {
+        Interpolation {
+            local_tick: 0,
+            latest: None,
+            transit: None,
+            jitter: None,
+            delay: 0.0,
+            render_tick: None
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.latest = None;
+        self.transit = None;
+        self.jitter = None;
+        self.delay = 0.0;
+        self.render_tick = None;
+    }
+
+    pub fn delay(&self) -> f32 {
+        self.delay as f32
+    }
+
+    pub fn jitter(&self) -> f32 {
+        self.jitter.unwrap_or(0.0) as f32
+    }
+
+    pub fn render_tick(&self) -> Option<f64> {
+        self.render_tick
+    }
+
+    pub fn receive(&mut self, snapshot_tick: u32) {
+
+        // Snapshots arriving out of order carry no new timing information
+        if self.latest.map_or(false, |(tick, _)| snapshot_tick <= tick) {
+            return;
+        }
+
+        let transit = self.local_tick as f64 - snapshot_tick as f64;
+        if let Some(last_transit) = self.transit {
+            let deviation = (transit - last_transit).abs();
+            let jitter = self.jitter.unwrap_or(deviation);
+            self.jitter = Some(jitter + (deviation - jitter) * JITTER_SMOOTHING);
+        }
+
+        self.transit = Some(transit);
+        self.latest = Some((snapshot_tick, self.local_tick));
+
+    }
+
+    // Advances the playback by one tick. The base delay is used until the
+    // jitter was measured, the delay never exceeds the given maximum.
+    pub fn tick(&mut self, base_delay: f64, max_delay: f64) {
+
+        self.local_tick = self.local_tick.wrapping_add(1);
+
+        // Estimate the snapshot tick which would arrive right now
+        let newest = match self.latest {
+            Some((tick, arrival)) => tick as f64 + self.local_tick.wrapping_sub(arrival) as f64,
+            None => return
+        };
+
+        let max_delay = max_delay.max(MIN_DELAY);
+        let target_delay = match self.jitter {
+            Some(jitter) => MIN_DELAY + jitter * JITTER_DELAY,
+            None => base_delay
+
+        }.max(MIN_DELAY).min(max_delay);
+
+        let render_tick = self.render_tick;
+        self.render_tick = Some(match render_tick {
+            Some(render_tick) => {
+
+                self.delay += (target_delay - self.delay) * DELAY_SMOOTHING;
+
+                // Jump straight to the target when being too far off
+                let target = newest - self.delay;
+                let error = target - (render_tick + 1.0);
+                if error.abs() > max_delay {
+                    target
+
+                } else {
+                    render_tick + 1.0 + (error * PLAYBACK_CORRECTION).max(
+                        -MAX_PLAYBACK_SCALE
+
+                    ).min(MAX_PLAYBACK_SCALE)
+                }
+
+            },
+            None => {
+                self.delay = target_delay;
+                newest - target_delay
+            }
+        });
+
+    }
+
+}
+
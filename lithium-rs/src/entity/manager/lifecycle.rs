@@ -0,0 +1,187 @@
+// External Dependencies ------------------------------------------------------
+use std::mem;
+use std::collections::HashMap;
+
+
+// Internal Dependencies ------------------------------------------------------
+use network::{DecodeError, LIFECYCLE_DESPAWN, LIFECYCLE_SPAWN};
+use entity::{EntityHandle, EntityState};
+use entity::manager::snapshot::{Snapshot, SnapshotEntity};
+
+
+// Lifecycle Records ----------------------------------------------------------
+pub enum LifecycleRecord<S: EntityState> {
+    Spawn(u16, SnapshotEntity<S>),
+    Despawn(EntityHandle)
+}
+
+impl<S: EntityState> LifecycleRecord<S> {
+
+    pub fn spawn_header_size() -> usize {
+        7
+    }
+
+    pub fn despawn_size() -> usize {
+        4
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        match *self {
+            LifecycleRecord::Spawn(id, ref entity) => {
+                let mut data = [
+                    LIFECYCLE_SPAWN,
+                    (id >> 8) as u8, id as u8,
+                    entity.generation,
+                    entity.type_id,
+                    entity.confirmed_tick,
+                    entity.state.is_some() as u8
+
+                ].to_vec();
+
+                if let Some(ref state) = entity.state {
+                    data.extend(state.serialize());
+                }
+
+                data
+            },
+            LifecycleRecord::Despawn(handle) => {
+                vec![
+                    LIFECYCLE_DESPAWN,
+                    (handle.id >> 8) as u8, handle.id as u8,
+                    handle.generation
+                ]
+            }
+        }
+    }
+
+    pub fn from_serialized(mut data: &[u8]) -> Result<Vec<LifecycleRecord<S>>, DecodeError> {
+
+        let mut records = Vec::new();
+        while !data.is_empty() {
+
+            let (record, len) = match data[0] {
+
+                LIFECYCLE_DESPAWN => if data.len() >= LifecycleRecord::<S>::despawn_size() {
+                    (
+                        LifecycleRecord::Despawn(EntityHandle::new(
+                            (data[1] as u16) << 8 | data[2] as u16,
+                            data[3]
+                        )),
+                        LifecycleRecord::<S>::despawn_size()
+                    )
+
+                } else {
+                    return Err(DecodeError::Truncated);
+                },
+
+                LIFECYCLE_SPAWN => {
+
+                    let header_size = LifecycleRecord::<S>::spawn_header_size();
+                    if data.len() < header_size {
+                        return Err(DecodeError::Truncated);
+                    }
+
+                    let (state, len) = match data[6] {
+                        0 => (None, header_size),
+                        1 if data.len() < header_size + S::encoded_size() => {
+                            return Err(DecodeError::Truncated);
+                        },
+                        1 => match S::from_serialized(&data[header_size..]) {
+                            Ok(state) => (Some(state), header_size + S::encoded_size()),
+                            Err(err) => return Err(err)
+                        },
+                        _ => return Err(DecodeError::Invalid)
+                    };
+
+                    (LifecycleRecord::Spawn(
+                        (data[1] as u16) << 8 | data[2] as u16,
+                        SnapshotEntity {
+                            type_id: data[4],
+                            generation: data[3],
+                            confirmed_tick: data[5],
+                            state: state
+                        }
+
+                    ), len)
+
+                },
+
+                _ => return Err(DecodeError::Invalid)
+
+            };
+
+            records.push(record);
+            data = &data[len..];
+
+        }
+
+        Ok(records)
+
+    }
+
+}
+
+
+// Connection Lifecycle -------------------------------------------------------
+// Generations of the entities a connection has been told about
+pub struct Lifecycle {
+    known: HashMap<u16, u8>,
+    pending: Vec<u8>
+}
+
+impl Lifecycle {
+
+    pub fn new() -> Lifecycle {
+        Lifecycle {
+            known: HashMap::new(),
+            pending: Vec::new()
+        }
+    }
+
+    // Generates the records needed to bring the connection up to date with
+    // the entities contained in the snapshot, despawns always come first so
+    // re-used ids are spawned again afterwards. Entities which merely left
+    // the snapshot, e.g. by moving out of the interest radius, stay known
+    // until they no longer exist on the server
+    pub fn update<S: EntityState, F: Fn(&EntityHandle) -> bool>(
+        &mut self,
+        snapshot: &Snapshot<S>,
+        exists: F
+    ) {
+
+        let despawned: Vec<EntityHandle> = self.known.iter().map(|(id, generation)| {
+            EntityHandle::new(*id, *generation)
+
+        }).filter(|handle| match snapshot.entities.get(&handle.id) {
+            Some(entity) => entity.generation != handle.generation,
+            None => !exists(handle)
+
+        }).collect();
+
+        for handle in despawned {
+            self.known.remove(&handle.id);
+            self.pending.extend(LifecycleRecord::<S>::Despawn(handle).serialize());
+        }
+
+        for (id, entity) in &snapshot.entities {
+            if !self.known.contains_key(id) {
+                self.known.insert(*id, entity.generation);
+                self.pending.extend(
+                    LifecycleRecord::Spawn(*id, entity.clone()).serialize()
+                );
+            }
+        }
+
+    }
+
+    pub fn serialize(&mut self) -> Option<Vec<u8>> {
+        if self.pending.is_empty() {
+            None
+
+        } else {
+            Some(mem::replace(&mut self.pending, Vec::new()))
+        }
+    }
+
+}
+
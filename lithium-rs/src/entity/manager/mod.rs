@@ -1,47 +1,146 @@
 // External Dependencies ------------------------------------------------------
-use std::collections::HashMap;
+use std::cmp;
+use std::f32;
+use std::mem;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Values;
 use cobalt::ConnectionID;
 
 
 // Internal Dependencies ------------------------------------------------------
 pub mod config;
+pub mod interest;
+pub mod interpolation;
+pub mod lifecycle;
+pub mod owned;
 pub mod registry;
+pub mod snapshot;
 
 use client;
 use server;
 use event::Event;
 use idpool::IdPool;
+use network::DecodeError;
 use renderer::Renderer;
 use level::{Level, BaseLevel};
-use entity::{Entity, EntityState, EntityEvent};
+use entity::{Entity, EntityHandle, EntityInput, EntityState, EntityEvent, InputStats};
+use entity::entity::tick_from_u8;
+use entity::input;
+use entity::collision::{CollisionGrid, CollisionShape};
 use self::config::EntityManagerConfig;
+use self::interest::Interest;
+use self::interpolation::Interpolation;
+use self::lifecycle::{Lifecycle, LifecycleRecord};
+use self::owned::{OwnedEntities, OwnedEntitiesMut};
 use self::registry::EntityRegistry;
+use self::snapshot::{Snapshot, SnapshotHistory};
+
+
+// Number of snapshots kept around as potential delta baselines
+const SNAPSHOT_HISTORY_SIZE: usize = 32;
+
+// Default limit for server side lag compensation
+const DEFAULT_MAX_REWIND_MS: u32 = 250;
+
+// Default cell size of the collision broadphase grid
+const DEFAULT_COLLISION_CELL_SIZE: f32 = 32.0;
+
+// Default number of unconfirmed inputs which are re-sent every tick
+const DEFAULT_INPUT_REDUNDANCY: usize = 10;
+
+// Default limit for extrapolating remote entities beyond their latest state
+const DEFAULT_MAX_EXTRAPOLATION_MS: u32 = 250;
+
+// Default time over which corrections of locally predicted entities are
+// smoothed out
+const DEFAULT_CORRECTION_SMOOTHING_MS: u32 = 100;
 
 
 // Entity Manager Implementation ----------------------------------------------
-pub struct EntityManager<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> {
+pub struct EntityManager<S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, I, L, R>> {
 
     // Id pool for entities
     id_pool: IdPool<u16>,
 
     // Vector of entities
-    entities: HashMap<u16, Entity<S, L, R>>,
+    entities: HashMap<u16, Entity<S, I, L, R>>,
+
+    // Ids of the entities owned by each connection (server only)
+    owned_entities: HashMap<ConnectionID, Vec<u16>>,
+
+    // Locally predicted entities along with their creation tick (client only)
+    predicted_entities: HashMap<u16, (u32, Entity<S, I, L, R>)>,
+
+    // Remote entity ids for confirmed predicted entities (client only)
+    predicted_links: HashMap<EntityHandle, u16>,
 
     // Configuration
     config: EntityManagerConfig,
 
-    // Current tick
-    tick: u8,
+    // Current tick, only its lower 8 bits are ever sent over the network
+    tick: u32,
+
+    // Maximum number of ticks entities can be rewound (server only)
+    max_rewind_ticks: u32,
+
+    // Number of ticks each connection draws remote entities behind the
+    // tick of its most recent inputs (server only)
+    view_delays: HashMap<ConnectionID, u8>,
+
+    // Number of unconfirmed inputs re-sent every tick (client only)
+    input_redundancy: usize,
+
+    // Maximum time remote entities are extrapolated for (client only)
+    max_extrapolation_ms: u32,
+
+    // Time over which mispredictions of local entities are smoothed out
+    // (client only)
+    correction_smoothing_ms: u32,
+
+    // Broadphase and collisions detected during the last tick (server only)
+    collision_grid: CollisionGrid,
+    collisions: Vec<(u16, u16)>,
 
     // Wether to run in server mode
     server_mode: bool,
 
+    // Snapshots sent to each connection (server only)
+    sent_snapshots: HashMap<ConnectionID, SnapshotHistory<S>>,
+
+    // Delay in ticks and buffered snapshots for connections which receive
+    // their state delayed (server only)
+    delayed_snapshots: HashMap<ConnectionID, (usize, VecDeque<Snapshot<S>>)>,
+
+    // Entities spawned on each connection (server only)
+    lifecycles: HashMap<ConnectionID, Lifecycle>,
+
+    // Input statistics of the entities owned by each connection (server only)
+    input_stats: HashMap<ConnectionID, InputStats>,
+
+    // Radius around a connection's entity outside of which other entities
+    // are not sent to it, with optional per connection overrides (server only)
+    interest_radius: Option<f32>,
+    interest_radii: HashMap<ConnectionID, f32>,
+
+    // Maximum number of bytes per serialized state (server only)
+    state_budget: Option<usize>,
+
+    // Priority accumulators for each connection (server only)
+    interests: HashMap<ConnectionID, Interest<S>>,
+
+    // Snapshots received from the server (client only)
+    received_snapshots: SnapshotHistory<S>,
+
+    // Playback of the received snapshots for remote entities (client only)
+    interpolation: Interpolation,
+
     // Entity Registry
     registry: G
 
 }
 
-impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> EntityManager<S, L, R, G> {
+impl<S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, I, L, R>> EntityManager<S, I, L, R, G> {
 
     pub fn new(
         tick_rate: u8,
@@ -50,25 +149,76 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
         server_mode: bool,
         registry: G
 
-    ) -> EntityManager<S, L, R, G> {
-        EntityManager {
+    ) -> EntityManager<S, I, L, R, G> {
+        let mut manager = EntityManager {
             id_pool: IdPool::new(),
             entities: HashMap::new(),
+            owned_entities: HashMap::new(),
+            predicted_entities: HashMap::new(),
+            predicted_links: HashMap::new(),
             config: EntityManagerConfig {
                 buffered_ticks: (buffer_ms as f32 / (1000.0 / tick_rate as f32)).floor() as u8,
                 interpolation_ticks: (interp_ms as f32 / (1000.0 / tick_rate as f32)).ceil() as u8,
                 tick_rate: tick_rate,
             },
             tick: 0,
+            max_rewind_ticks: 0,
+            view_delays: HashMap::new(),
+            input_redundancy: DEFAULT_INPUT_REDUNDANCY,
+            max_extrapolation_ms: DEFAULT_MAX_EXTRAPOLATION_MS,
+            correction_smoothing_ms: DEFAULT_CORRECTION_SMOOTHING_MS,
+            collision_grid: CollisionGrid::new(DEFAULT_COLLISION_CELL_SIZE),
+            collisions: Vec::new(),
             server_mode: server_mode,
+            sent_snapshots: HashMap::new(),
+            delayed_snapshots: HashMap::new(),
+            lifecycles: HashMap::new(),
+            input_stats: HashMap::new(),
+            interest_radius: None,
+            interest_radii: HashMap::new(),
+            state_budget: None,
+            interests: HashMap::new(),
+            received_snapshots: SnapshotHistory::new(SNAPSHOT_HISTORY_SIZE),
+            interpolation: Interpolation::new(),
             registry: registry
-        }
+        };
+        manager.set_max_rewind(DEFAULT_MAX_REWIND_MS);
+
+        // Ids are not re-used while snapshots which might still be used as
+        // delta baselines could refer to them
+        manager.id_pool.set_quarantine(SNAPSHOT_HISTORY_SIZE as u32);
+        manager
     }
 
-    pub fn tick(&self) -> u8 {
+    pub fn tick(&self) -> u32 {
         self.tick
     }
 
+    // Moves the simulation to another tick in order to align it with the
+    // server. Inputs of local entities were generated for the previous ticks
+    // and are dropped, predicted entities keep their age so they still
+    // expire on time (client only).
+    pub fn set_tick(&mut self, tick: u32) {
+
+        if tick != self.tick {
+
+            for (_, entity) in &mut self.entities {
+                if entity.local() {
+                    entity.clear_inputs();
+                }
+            }
+
+            let previous = self.tick;
+            for (_, predicted) in &mut self.predicted_entities {
+                predicted.0 = tick.saturating_sub(previous.saturating_sub(predicted.0));
+            }
+
+        }
+
+        self.tick = tick;
+
+    }
+
     pub fn dt(&self) -> f32 {
         1.0 / self.config.tick_rate as f32
     }
@@ -86,11 +236,28 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
 
         assert_eq!(self.entities.len(), 0);
         self.entities.clear();
+        self.owned_entities.clear();
+
+        let local_ids: Vec<u16> = self.predicted_entities.keys().map(|id| *id).collect();
+        for local_id in local_ids {
+            self.destroy_predicted(local_id);
+        }
+
+        self.predicted_links.clear();
 
         assert_eq!(self.id_pool.len(), 0);
         self.id_pool.reset();
         self.tick = 0;
 
+        self.sent_snapshots.clear();
+        self.delayed_snapshots.clear();
+        self.lifecycles.clear();
+        self.input_stats.clear();
+        self.view_delays.clear();
+        self.interests.clear();
+        self.received_snapshots.clear();
+        self.interpolation.reset();
+
     }
 
 
@@ -101,16 +268,23 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
         state: Option<S>,
         owner: Option<&ConnectionID>
 
-    ) -> Option<&mut Entity<S, L, R>> {
+    ) -> Option<&mut Entity<S, I, L, R>> {
+
+        let mut entity = match self.registry.entity_from_type_id(type_id) {
+            Some(entity) => entity,
+            None => return None
+        };
+
         if let Some(id) = self.id_pool.get_id() {
 
-            let mut entity = self.registry.entity_from_type_id(type_id);
             entity.set_buffer_size(self.config.buffered_ticks as usize);
             entity.set_id(id);
+            entity.set_generation(self.id_pool.generation(id));
             entity.set_alive(true);
 
             if let Some(owner) = owner {
                 entity.set_owner(*owner);
+                self.owned_entities.entry(*owner).or_insert_with(Vec::new).push(id);
             }
 
             if let Some(state) = state {
@@ -127,23 +301,46 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
         }
     }
 
-    pub fn tick_server<E: Event, H: server::Handler<R, G, L, E, S>>(
+    pub fn tick_server<E: Event, H: server::Handler<R, G, L, E, S, I>>(
         &mut self, level: &Level<S, L>, handler: &mut H
-    ) {
+
+    ) -> Vec<(ConnectionID, u16, I)> {
+
+        let mut applied = Vec::new();
 
         let dt = self.dt();
-        for (_, entity) in &mut self.entities {
+        for (id, entity) in &mut self.entities {
+
+            // Every tick applies exactly one input from the entity's queue
+            if let Some(input) = entity.consume_input(self.tick) {
+                if let Some(owner) = entity.owner() {
+                    applied.push((*owner, *id, input));
+                }
+            }
+
             handler.tick_entity_before(level, entity, self.tick, dt);
             entity.event(EntityEvent::Tick(self.tick, dt));
             entity.tick(level, self.tick, dt, self.server_mode);
             handler.tick_entity_after(level, entity, self.tick, dt);
+
+            if let Some(owner) = entity.owner().cloned() {
+                self.input_stats.entry(owner).or_insert_with(InputStats::default).add(
+                    &entity.take_input_stats()
+                );
+            }
+
         }
 
+        self.update_collisions(level);
+
+        self.id_pool.tick();
         self.tick = self.tick.wrapping_add(1);
 
+        applied
+
     }
 
-    pub fn tick_client<E: Event, H: client::Handler<R, G, L, E, S>>(
+    pub fn tick_client<E: Event, H: client::Handler<R, G, L, E, S, I>>(
         &mut self,
         renderer: &mut R,
         level: &Level<S, L>,
@@ -151,7 +348,7 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
 
     ) -> Option<Vec<u8>> {
 
-        let mut local_inputs: Option<Vec<u8>> = None;
+        let mut local_inputs = Vec::new();
 
         let dt = self.dt();
         for (_, entity) in &mut self.entities {
@@ -162,31 +359,83 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
 
             handler.tick_entity_after(renderer, level, entity, self.tick, dt);
 
-            // Use serialized inputs from the locally controlled entities
+            // Collect serialized inputs from all locally controlled entities,
+            // tagged with their id and the length of the encoded inputs
             if let Some(inputs) = entity.serialized_inputs() {
-                local_inputs = Some(inputs);
+
+                // The entity limits the number of re-sent inputs so they
+                // always fit into a block, a larger one could not be decoded
+                debug_assert!(inputs.len() <= input::MAX_SERIALIZED_SIZE);
+                if inputs.len() <= input::MAX_SERIALIZED_SIZE {
+                    local_inputs.push((entity.id() >> 8) as u8);
+                    local_inputs.push(entity.id() as u8);
+                    local_inputs.push((inputs.len() >> 8) as u8);
+                    local_inputs.push(inputs.len() as u8);
+                    local_inputs.extend(inputs);
+                }
+
             }
 
         }
 
+        // Predicted entities are simulated locally until they get replaced by
+        // their remote counterpart
+        for (_, &mut (_, ref mut entity)) in &mut self.predicted_entities {
+            handler.tick_entity_before(renderer, level, entity, self.tick, dt);
+            entity.event(EntityEvent::Tick(self.tick, dt));
+            entity.tick(level, self.tick, dt, true);
+            handler.tick_entity_after(renderer, level, entity, self.tick, dt);
+        }
+
+        self.update_predicted();
+
+        // The server rewinds to the states the player was seeing while
+        // generating the inputs for this tick
+        let view_delay = self.view_delay();
+
         self.tick = self.tick.wrapping_add(1);
 
-        local_inputs
+        if local_inputs.is_empty() {
+            None
+
+        } else {
+            let mut data = vec![view_delay];
+            data.extend(local_inputs);
+            Some(data)
+        }
 
     }
 
     pub fn draw(&mut self, renderer: &mut R, level: &Level<S, L>) {
+
+        let render_tick = self.interpolation.render_tick();
         for (_, entity) in &mut self.entities {
             if entity.is_visible() {
-                entity.draw(renderer, level);
+                entity.draw(renderer, level, render_tick);
             }
         }
+
+        for (_, &mut (_, ref mut entity)) in &mut self.predicted_entities {
+            entity.draw(renderer, level, None);
+        }
+
     }
 
-    pub fn destroy(&mut self, entity_id: u16) -> Option<Entity<S, L, R>> {
+    pub fn destroy(&mut self, entity_id: u16) -> Option<Entity<S, I, L, R>> {
 
         if let Some(mut entity) = self.entities.remove(&entity_id) {
-            // TODO can be an issue if re-used directly
+
+            let owned_empty = entity.owner().and_then(|owner| {
+                self.owned_entities.get_mut(owner).map(|ids| {
+                    ids.retain(|id| *id != entity_id);
+                    ids.is_empty()
+                })
+            });
+
+            if let Some(true) = owned_empty {
+                self.owned_entities.remove(entity.owner().unwrap());
+            }
+
             self.id_pool.release_id(entity_id);
             entity.set_alive(false);
             entity.event(EntityEvent::Destroyed(self.tick, entity_id));
@@ -198,28 +447,312 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
 
     }
 
-    pub fn get_entity_for_owner(
-        &mut self, owner: &ConnectionID
+    // Collisions -------------------------------------------------------------
+    pub fn set_collision_cell_size(&mut self, cell_size: f32) {
+        self.collision_grid.set_cell_size(cell_size);
+    }
 
-    ) -> Option<&mut Entity<S, L, R>> {
-        for (_, entity) in &mut self.entities {
-            if entity.owned_by(owner) {
-                return Some(entity);
+    pub fn collisions(&mut self) -> Vec<(u16, u16)> {
+        mem::replace(&mut self.collisions, Vec::new())
+    }
+
+    fn update_collisions(&mut self, level: &Level<S, L>) {
+
+        let colliders: Vec<(u16, (f32, f32), CollisionShape)> = self.entities.values().filter_map(|entity| {
+            entity.collision_shape().map(|shape| {
+                (entity.id(), entity.state().position(), shape)
+            })
+
+        }).collect();
+
+        self.collisions = self.collision_grid.collisions(
+            &colliders,
+            level.wrap_bounds()
+        );
+
+    }
+
+
+    // Predicted Entities -----------------------------------------------------
+    pub fn create_predicted(
+        &mut self,
+        local_id: u16,
+        type_id: u8,
+        mut state: S
+
+    ) -> Option<&mut Entity<S, I, L, R>> {
+
+        if self.predicted_entities.contains_key(&local_id) {
+            return None;
+        }
+
+        let mut entity = match self.registry.entity_from_type_id(type_id) {
+            Some(entity) => entity,
+            None => return None
+        };
+
+        entity.set_buffer_size(self.config.buffered_ticks as usize);
+        entity.set_id(local_id);
+        entity.set_alive(true);
+
+        // Predicted entities are always drawn with their latest state
+        let flags = state.flags();
+        state.set_flags(flags | 0x01);
+        entity.set_state(state);
+        entity.show(self.tick);
+
+        entity.event(EntityEvent::Created(self.tick, local_id));
+
+        self.predicted_entities.insert(local_id, (self.tick, entity));
+        self.predicted_entities.get_mut(&local_id).map(|p| &mut p.1)
+
+    }
+
+    pub fn confirm_predicted(&mut self, local_id: u16, handle: EntityHandle) {
+        if self.predicted_entities.contains_key(&local_id) {
+            self.predicted_links.insert(handle, local_id);
+            self.update_predicted();
+        }
+    }
+
+    fn update_predicted(&mut self) {
+
+        // Remove predicted entities once their remote counterpart is visible
+        let mut replaced_ids = Vec::new();
+        for (handle, local_id) in &self.predicted_links {
+            if self.get_entity_by_handle(*handle).map_or(false, |e| e.is_visible()) {
+                replaced_ids.push((*handle, *local_id));
+            }
+        }
+
+        for (handle, local_id) in replaced_ids {
+            self.predicted_links.remove(&handle);
+            self.destroy_predicted(local_id);
+        }
+
+        // Remove predicted entities which were never confirmed or whose
+        // remote counterpart was already destroyed again
+        let tick = self.tick;
+        let max_ticks = self.config.buffered_ticks as u32;
+        let expired_ids: Vec<u16> = self.predicted_entities.iter().filter(|&(_, p)| {
+            tick.saturating_sub(p.0) > max_ticks
+
+        }).map(|(id, _)| *id).collect();
+
+        for local_id in expired_ids {
+
+            let handles: Vec<EntityHandle> = self.predicted_links.iter().filter(|&(_, id)| {
+                *id == local_id
+
+            }).map(|(handle, _)| *handle).collect();
+
+            for handle in handles {
+                self.predicted_links.remove(&handle);
             }
+
+            self.destroy_predicted(local_id);
+
+        }
+
+    }
+
+    fn destroy_predicted(&mut self, local_id: u16) {
+        if let Some((_, mut entity)) = self.predicted_entities.remove(&local_id) {
+            entity.set_alive(false);
+            entity.event(EntityEvent::Destroyed(self.tick, local_id));
         }
-        None
     }
 
-    pub fn get_entity_id_for_owner(
-        &mut self, owner: &ConnectionID
 
-    ) -> Option<u16> {
+    // Entity Access ----------------------------------------------------------
+    pub fn get_entity(&self, entity_id: u16) -> Option<&Entity<S, I, L, R>> {
+        self.entities.get(&entity_id)
+    }
+
+    pub fn get_entity_mut(&mut self, entity_id: u16) -> Option<&mut Entity<S, I, L, R>> {
+        self.entities.get_mut(&entity_id)
+    }
+
+    // Returns None for stale handles whose entity was destroyed and whose id
+    // has since been re-used
+    pub fn get_entity_by_handle(&self, handle: EntityHandle) -> Option<&Entity<S, I, L, R>> {
+        self.entities.get(&handle.id).and_then(|e| {
+            if e.generation() == handle.generation {
+                Some(e)
+
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn get_entity_by_handle_mut(&mut self, handle: EntityHandle) -> Option<&mut Entity<S, I, L, R>> {
+        self.entities.get_mut(&handle.id).and_then(|e| {
+            if e.generation() == handle.generation {
+                Some(e)
+
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn entities(&self) -> Values<u16, Entity<S, I, L, R>> {
+        self.entities.values()
+    }
+
+    pub fn get_entities_for_owner(&self, owner: &ConnectionID) -> OwnedEntities<S, I, L, R> {
+        let ids = self.owned_entities.get(owner).map_or(&[][..], |ids| &ids[..]);
+        OwnedEntities::new(ids.iter(), &self.entities)
+    }
+
+    pub fn get_entities_for_owner_mut(&mut self, owner: &ConnectionID) -> OwnedEntitiesMut<S, I, L, R> {
+        let count = self.owned_entities.get(owner).map_or(0, |ids| ids.len());
+        OwnedEntitiesMut::new(*owner, count, self.entities.values_mut())
+    }
+
+
+    // Input ------------------------------------------------------------------
+    // Number of the most recent unconfirmed inputs which get re-sent with
+    // every tick in order to compensate for lost packets (client only)
+    pub fn set_input_redundancy(&mut self, ticks: usize) {
+        self.input_redundancy = cmp::max(ticks, 1);
         for (_, entity) in &mut self.entities {
-            if entity.owned_by(owner) {
-                return Some(entity.id());
+            entity.set_input_redundancy(self.input_redundancy);
+        }
+    }
+
+    // Input statistics of all entities owned by the connection (server only)
+    pub fn input_stats(&self, owner: &ConnectionID) -> InputStats {
+        self.input_stats.get(owner).map_or(InputStats::default(), |stats| *stats)
+    }
+
+    // Queues the inputs received from a connection on the entities it owns,
+    // inputs for entities owned by someone else are skipped. Decoding stops
+    // at the first malformed block of inputs. The view delay the message
+    // starts with is kept for rewinding.
+    //
+    // Message: [view_delay, block...]
+    // Block: [id, id, len, len, has_base, base_tick, run_count, run...]
+    pub fn receive_input(
+        &mut self, owner: &ConnectionID, mut data: &[u8]
+
+    ) -> Result<Vec<(u16, I)>, DecodeError> {
+
+        if data.is_empty() {
+            return Err(DecodeError::Truncated);
+        }
+
+        self.view_delays.insert(*owner, data[0]);
+        data = &data[1..];
+
+        let mut inputs = Vec::new();
+        while !data.is_empty() {
+
+            if data.len() < 4 {
+                return Err(DecodeError::Truncated);
+            }
+
+            let entity_id = (data[0] as u16) << 8 | data[1] as u16;
+            let len = (data[2] as usize) << 8 | data[3] as usize;
+            if data.len() < 4 + len {
+                return Err(DecodeError::Truncated);
+            }
+
+            if let Some(entity) = self.entities.get_mut(&entity_id) {
+                if entity.owned_by(owner) {
+                    match entity.remote_inputs(&data[4..4 + len], self.tick) {
+                        Ok(decoded) => {
+                            for input in decoded {
+                                inputs.push((entity_id, input));
+                            }
+                        },
+                        Err(err) => return Err(err)
+                    }
+                }
             }
+
+            data = &data[4 + len..];
+
         }
-        None
+
+        Ok(inputs)
+
+    }
+
+
+    // Interpolation ----------------------------------------------------------
+    // Limits for how long remote entities keep moving on their own once no
+    // more recent state arrived in time (client only)
+    pub fn set_max_extrapolation(&mut self, extrapolation_ms: u32) {
+        self.max_extrapolation_ms = extrapolation_ms;
+        let ticks = self.max_extrapolation_ticks();
+        for (_, entity) in &mut self.entities {
+            entity.set_max_extrapolation(ticks);
+        }
+    }
+
+    fn max_extrapolation_ticks(&self) -> f32 {
+        self.max_extrapolation_ms as f32 / (1000.0 / self.config.tick_rate as f32)
+    }
+
+    // Advances the playback of remote entities, must be called exactly once
+    // per transport tick no matter how many ticks the simulation skips or
+    // repeats to keep up with the server (client only)
+    pub fn tick_interpolation(&mut self) {
+        self.interpolation.tick(
+            self.config.interpolation_ticks as f64,
+            self.config.buffered_ticks as f64
+        );
+    }
+
+    // Current number of ticks remote entities are drawn behind the most
+    // recently received state (client only)
+    pub fn interpolation_delay(&self) -> f32 {
+        self.interpolation.delay()
+    }
+
+    // Number of ticks remote entities are drawn behind the current tick of
+    // the simulation (client only)
+    pub fn view_delay(&self) -> u8 {
+        match self.interpolation.render_tick() {
+            Some(render_tick) => {
+                (self.tick as f64 - render_tick).round().max(0.0).min(255.0) as u8
+            },
+            None => self.config.interpolation_ticks
+        }
+    }
+
+    // Average deviation of the state arrival times in ticks (client only)
+    pub fn state_jitter(&self) -> f32 {
+        self.interpolation.jitter()
+    }
+
+
+    // Prediction -------------------------------------------------------------
+    // Time over which the visual error of mispredicted local entities decays
+    // after a correction, zero makes them snap (client only)
+    pub fn set_correction_smoothing(&mut self, smoothing_ms: u32) {
+        self.correction_smoothing_ms = smoothing_ms;
+        for (_, entity) in &mut self.entities {
+            entity.set_correction_smoothing(smoothing_ms as f32 / 1000.0);
+        }
+    }
+
+    // Largest distance between the predicted and the corrected state of any
+    // local entity during their most recent corrections (client only)
+    pub fn misprediction(&self) -> f32 {
+        self.entities.values().filter(|e| e.local()).fold(0.0, |max: f32, e| {
+            max.max(e.misprediction())
+        })
+    }
+
+    // Largest average distance between the predicted and the corrected state
+    // of any local entity during their most recent corrections (client only)
+    pub fn average_misprediction(&self) -> f32 {
+        self.entities.values().filter(|e| e.local()).fold(0.0, |max: f32, e| {
+            max.max(e.average_misprediction())
+        })
     }
 
 
@@ -228,77 +761,153 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
         self.config.serialize()
     }
 
-    pub fn receive_config<'a>(&mut self, data: &'a [u8]) -> &'a [u8] {
-        self.config = EntityManagerConfig::from_serialized(data);
-        &data[EntityManagerConfig::encoded_size()..]
+    pub fn receive_config<'a>(&mut self, data: &'a [u8]) -> Result<&'a [u8], DecodeError> {
+        let config = match EntityManagerConfig::from_serialized(data) {
+            Ok(config) => config,
+            Err(err) => return Err(err)
+        };
+        self.config = config;
+        Ok(&data[EntityManagerConfig::encoded_size()..])
     }
 
-    pub fn serialize_state(&self, owner: &ConnectionID) -> Vec<u8> {
+    pub fn serialize_state(&mut self, owner: &ConnectionID, level: &Level<S, L>) -> Vec<u8> {
 
-        let mut state = Vec::new();
+        // Serialize the state of all entities relevant to the connection
+        let mut snapshot = self.relevant_snapshot(owner, level);
 
-        // Serialize entity state for the connection
-        for entity in self.entities.values() {
-            state.extend(entity.serialize_state(owner));
+        // Delayed connections receive the state from a fixed number of ticks
+        // ago, until enough states are buffered the oldest one is repeated
+        if let Some(&mut (delay, ref mut buffer)) = self.delayed_snapshots.get_mut(owner) {
+            buffer.push_back(snapshot);
+            snapshot = if buffer.len() > delay {
+                buffer.pop_front().unwrap()
+
+            } else {
+                buffer.front().unwrap().clone()
+            };
         }
 
+        // Spawn entities on the connection as they first enter its
+        // snapshots and despawn them once they were destroyed
+        let entities = &self.entities;
+        self.lifecycles.entry(*owner).or_insert_with(Lifecycle::new).update(&snapshot, |handle| {
+            entities.get(&handle.id).map_or(false, |e| e.generation() == handle.generation)
+        });
+
+        // Only send the differences to the last snapshot which was
+        // acknowledged by the connection
+        let history = self.sent_snapshots.entry(*owner).or_insert_with(|| {
+            SnapshotHistory::new(SNAPSHOT_HISTORY_SIZE)
+        });
+
+        let state = snapshot.serialize(history.baseline());
+        history.push(snapshot);
         state
 
     }
 
-    pub fn receive_state(&mut self, data: &[u8]) {
+    // Returns the pending spawn and despawn records for the connection, these
+    // must be delivered reliably and in order
+    pub fn serialize_lifecycle(&mut self, owner: &ConnectionID) -> Option<Vec<u8>> {
+        self.lifecycles.get_mut(owner).and_then(|l| l.serialize())
+    }
 
-        let tick = self.tick;
-        let registry = &self.registry;
-        let buffer_size = self.config.buffered_ticks as usize;
+    // Serializes the complete state as seen by the connection without
+    // affecting any of the delta baselines
+    pub fn serialize_full_state(&self, owner: &ConnectionID) -> Vec<u8> {
+        self.snapshot(owner).serialize(None)
+    }
 
-        // Mark all entities as dead
-        for (_, entity) in &mut self.entities {
-            entity.set_alive(false);
+    // The acknowledged tick is reconstructed against the most recent state
+    // sent to the connection, which lags behind for delayed connections
+    pub fn acknowledge_state(&mut self, owner: &ConnectionID, tick: u8) {
+        if let Some(history) = self.sent_snapshots.get_mut(owner) {
+            if let Some(latest) = history.latest_tick() {
+                history.acknowledge(tick_from_u8(tick, latest));
+            }
         }
+    }
 
-        // Parse received state
-        let mut i = 0;
-        while i + Entity::<S, L, R>::header_size() <= data.len() {
+    pub fn reset_state(&mut self, owner: &ConnectionID) {
+        self.sent_snapshots.remove(owner);
+        self.delayed_snapshots.remove(owner);
+        self.lifecycles.remove(owner);
+        self.input_stats.remove(owner);
+        self.view_delays.remove(owner);
+        self.interest_radii.remove(owner);
+        self.interests.remove(owner);
+    }
 
-            // Entity ID / Type
-            let entity_id = (data[i] as u16) << 8 | (data[i + 1] as u16);
-            let entity_type = data[i + 2];
-            let entity_confirmed_tick = data[i + 3];
-            let entity_is_visible = data[i + 4] == 1;
-            i += Entity::<S, L, R>::header_size();
+    // Delays the state sent to the connection by the given number of
+    // milliseconds, e.g. to prevent spectators from relaying information to
+    // players
+    pub fn set_state_delay(&mut self, owner: &ConnectionID, delay_ms: u32) {
+        let ticks = (delay_ms as f32 / (1000.0 / self.config.tick_rate as f32)).ceil() as usize;
+        if ticks > 0 {
+            self.delayed_snapshots.insert(*owner, (ticks, VecDeque::with_capacity(ticks + 1)));
 
-            // Read serialized entity state data for visible entities
-            let entity_state = if entity_is_visible && S::encoded_size() <= data.len() {
-                let state = S::from_serialized(&data[i..]);
-                i += S::encoded_size();
-                Some(state)
+        } else {
+            self.delayed_snapshots.remove(owner);
+        }
+    }
 
-            } else {
-                None
+    pub fn receive_state(&mut self, data: &[u8]) -> Result<u32, DecodeError> {
+
+        let (tick, base_tick) = match Snapshot::<S>::header(data) {
+            Ok(header) => header,
+            Err(err) => return Err(err)
+        };
+
+        // Reconstruct the full tick against the most recently received one,
+        // the very first snapshot is taken as is
+        let reference = self.received_snapshots.latest_tick().unwrap_or(tick as u32);
+        let tick = tick_from_u8(tick, reference);
+
+        // Reconstruct the full snapshot from the baseline it was encoded
+        // against, in case we no longer have the baseline the state is
+        // dropped and the server will fall back to an older one
+        let snapshot = {
+            let base = match base_tick {
+                Some(base_tick) => match self.received_snapshots.get(tick_from_u8(base_tick, tick)) {
+                    Some(base) => Some(base),
+                    None => return Err(DecodeError::MissingBaseline)
+                },
+                None => None
             };
 
-            // Create entities which do not yet exist
-            let mut entity = self.entities.entry(entity_id).or_insert_with(|| {
-
-                let mut entity = registry.entity_from_type_id(entity_type);
-                entity.set_buffer_size(buffer_size);
-                entity.set_id(entity_id);
-
-                // Set state if we got any
-                if let Some(ref state) = entity_state {
-                    entity.set_state(state.clone());
-                    entity.show(tick);
+            match Snapshot::from_serialized(data, tick, base) {
+                Ok(snapshot) => snapshot,
+                Err(err) => return Err(err)
+            }
+        };
+
+        // Snapshots which arrive after a more recent one are only kept as
+        // baselines, applying them would roll the entities back in time
+        let snapshot_tick = snapshot.tick;
+        if self.received_snapshots.latest_tick().map_or(false, |latest| snapshot_tick <= latest) {
+            self.received_snapshots.push(snapshot);
+            return Ok(snapshot_tick);
+        }
 
-                } else {
-                    entity.hide(tick);
-                }
+        // Apply received state
+        let tick = self.tick;
+        for (entity_id, snapshot_entity) in &snapshot.entities {
 
-                entity.event(EntityEvent::Created(tick, entity_id));
+            // States of entities which were not spawned yet or which were
+            // replaced since the snapshot was sent are ignored
+            let entity = match self.entities.get_mut(entity_id) {
+                Some(entity) => entity,
+                None => continue
+            };
 
-                entity
+            if entity.generation() != snapshot_entity.generation
+                || entity.type_id() != snapshot_entity.type_id {
+                continue;
+            }
 
-            });
+            let entity_confirmed_tick = snapshot_entity.confirmed_tick;
+            let entity_state = snapshot_entity.state.as_ref().map(|s| s.clone());
+            let entity_is_visible = entity_state.is_some();
 
             // Handle entities which get hidden
             if entity.is_visible() {
@@ -314,60 +923,260 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
                 }
             }
 
-            // Mark entity as alive
-            entity.set_alive(true);
-
             // Set confirmed state if we got any...
             if let Some(state) = entity_state {
                 if entity.local() {
-                    entity.set_confirmed_state(entity_confirmed_tick, state);
+                    entity.set_confirmed_state(tick_from_u8(entity_confirmed_tick, tick), state);
 
                 // ...or overwrite local state
                 } else {
                     // But keep last_state intact for interpolation purposes
-                    entity.set_remote_state(state);
+                    entity.set_remote_state(snapshot_tick, state);
                 }
             }
 
         }
 
-        // Destroy dead entities...
-        let mut destroyed_ids = Vec::new();
-        for (_, entity) in &mut self.entities {
-            if !entity.alive() {
-                let id = entity.id();
-                entity.event(EntityEvent::Destroyed(tick, id));
-                destroyed_ids.push(id);
+        // Entities missing from the snapshot are out of interest or no
+        // longer visible to us, they stay around hidden until either their
+        // state is received again or they get despawned
+        for (entity_id, entity) in &mut self.entities {
+            if entity.is_visible() && !snapshot.entities.contains_key(entity_id) {
+                entity.hide(tick);
             }
         }
 
-        // ...then remove them from the map
-        for id in &destroyed_ids {
-            self.entities.remove(&id);
+        // Keep the snapshot around as a baseline for future states
+        self.interpolation.receive(snapshot_tick);
+        self.received_snapshots.push(snapshot);
+
+        Ok(snapshot_tick)
+
+    }
+
+
+    // Entities are only ever created and destroyed through the reliably
+    // delivered lifecycle records, never by the states themselves
+    pub fn receive_lifecycle(&mut self, data: &[u8]) -> Result<(), DecodeError> {
+
+        let records = match LifecycleRecord::<S>::from_serialized(data) {
+            Ok(records) => records,
+            Err(err) => return Err(err)
+        };
+
+        // Create all spawned entities up front so a record with an unknown
+        // type rejects the whole message before any of it was applied
+        let mut pending = Vec::with_capacity(records.len());
+        for record in records {
+
+            let entity = if let LifecycleRecord::Spawn(_, ref snapshot_entity) = record {
+                match self.registry.entity_from_type_id(snapshot_entity.type_id) {
+                    Some(entity) => Some(entity),
+                    None => return Err(DecodeError::Invalid)
+                }
+
+            } else {
+                None
+            };
+
+            pending.push((record, entity));
+
+        }
+
+        let tick = self.tick;
+        for (record, entity) in pending {
+            match record {
+
+                LifecycleRecord::Spawn(entity_id, snapshot_entity) => {
+
+                    let mut entity = match entity {
+                        Some(entity) => entity,
+                        None => continue
+                    };
+
+                    // Replace any entity which still uses the id
+                    let replaced = self.entities.get(&entity_id).map(|e| e.handle());
+                    if let Some(handle) = replaced {
+                        self.despawn(handle);
+                    }
+
+                    entity.set_buffer_size(self.config.buffered_ticks as usize);
+                    entity.set_input_redundancy(self.input_redundancy);
+                    entity.set_max_extrapolation(self.max_extrapolation_ticks());
+                    entity.set_correction_smoothing(self.correction_smoothing_ms as f32 / 1000.0);
+                    entity.set_id(entity_id);
+                    entity.set_generation(snapshot_entity.generation);
+                    entity.set_alive(true);
+
+                    // Set state if we got any
+                    if let Some(state) = snapshot_entity.state {
+                        entity.set_state(state);
+                        entity.show(tick);
+
+                    } else {
+                        entity.hide(tick);
+                    }
+
+                    entity.event(EntityEvent::Created(tick, entity_id));
+
+                    self.entities.insert(entity_id, entity);
+
+                },
+
+                LifecycleRecord::Despawn(handle) => self.despawn(handle)
+
+            }
         }
 
+        Ok(())
+
     }
 
+    // Despawns of entities whose id was already re-used are ignored
+    fn despawn(&mut self, handle: EntityHandle) {
+        if self.get_entity_by_handle(handle).is_some() {
+            if let Some(mut entity) = self.entities.remove(&handle.id) {
+                entity.set_alive(false);
+                entity.event(EntityEvent::Destroyed(self.tick, handle.id));
+            }
+        }
+    }
 
-    // State Rewinding --------------------------------------------------------
-    pub fn offset_states(&self, remote_tick: u8, rtt: f32) -> HashMap<u16, S> {
+    // Entities which are not visible to the connection are left out
+    // completely
+    fn snapshot(&self, owner: &ConnectionID) -> Snapshot<S> {
+        let mut snapshot = Snapshot::new(self.tick);
+        for (id, entity) in self.entities.iter().filter(|&(_, e)| e.visible_to(owner)) {
+            snapshot.entities.insert(*id, entity.serialize_state(owner));
+        }
+        snapshot
+    }
+
+
+    // Interest Management ----------------------------------------------------
+    // Entities further away than the radius from the entity owned by a
+    // connection are not sent to it at all, None disables the filtering
+    pub fn set_interest_radius(&mut self, radius: Option<f32>) {
+        self.interest_radius = radius;
+    }
+
+    pub fn set_connection_interest_radius(&mut self, owner: &ConnectionID, radius: Option<f32>) {
+        if let Some(radius) = radius {
+            self.interest_radii.insert(*owner, radius);
+
+        } else {
+            self.interest_radii.remove(owner);
+        }
+    }
+
+    // Limits the size of each serialized state, entities are sent in order
+    // of their accumulated priority until the budget is used up
+    pub fn set_state_budget(&mut self, bytes: Option<usize>) {
+        self.state_budget = bytes;
+    }
+
+    fn relevant_snapshot(&mut self, owner: &ConnectionID, level: &Level<S, L>) -> Snapshot<S> {
+
+        let radius = self.interest_radii.get(owner).cloned().or(self.interest_radius);
+        if radius.is_none() && self.state_budget.is_none() {
+            return self.snapshot(owner);
+        }
+
+        // Distances are measured from the closest entity controlled by the
+        // connection, connections without one are interested in everything
+        let origins: Vec<(f32, f32)> = self.get_entities_for_owner(owner).map(|e| {
+            e.state().position()
+
+        }).collect();
+
+        let interest = self.interests.entry(*owner).or_insert_with(Interest::new);
+
+        // Accumulate priorities of all relevant entities, owned entities are
+        // always sent
+        let mut priorities = HashMap::with_capacity(interest.priorities.len());
+        for (id, entity) in self.entities.iter().filter(|&(_, e)| e.visible_to(owner)) {
 
-        // TODO add 
+            let priority = if entity.owned_by(owner) {
+                f32::MAX
 
-        // Calculate the server side state tick the client was actual displaying 
-        // when they performed their action at "remote_tick".
-        //
-        // The client's server side state tick is delayed by both rtt / 2 and
-        // the configured interpolation_ticks.
-        let corrected_tick = remote_tick.wrapping_sub((
-                // Ticks taken by ping
-                (rtt / 2.0) / (1000.0 / self.config.tick_rate as f32)
+            } else {
+
+                let position = entity.state().position();
+                let distance = if origins.is_empty() {
+                    0.0
+
+                } else {
+                    origins.iter().map(|origin| {
+                        let (dx, dy) = level.delta(*origin, position);
+                        (dx * dx + dy * dy).sqrt()
+
+                    }).fold(f32::MAX, f32::min)
+                };
+
+                match Interest::<S>::relevance(distance, radius) {
+                    Some(relevance) => {
+                        interest.priorities.get(id).cloned().unwrap_or(0.0)
+                            + entity.priority() * relevance
+                    },
+                    None => continue
+                }
+
+            };
+
+            priorities.insert(*id, priority);
+
+        }
+
+        let mut candidates: Vec<(u16, f32)> = priorities.iter().map(|(id, p)| (*id, *p)).collect();
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        // The budget assumes full states, delta encoded ones end up smaller
+        let entity_size = Snapshot::<S>::entity_header_size() + S::encoded_size();
+        let mut budget = self.state_budget.map(|b| {
+            b.saturating_sub(Snapshot::<S>::header_size())
+        });
+
+        let mut snapshot = Snapshot::new(self.tick);
+        for (id, priority) in candidates {
+
+            let entity = &self.entities[&id];
+            if priority == f32::MAX || budget.map_or(true, |b| b >= entity_size) {
+                budget = budget.map(|b| b.saturating_sub(entity_size));
+                snapshot.entities.insert(id, entity.serialize_state(owner));
+                priorities.insert(id, 0.0);
+
+            // Entities which do not fit keep their last sent state so they
+            // are not removed on the client
+            } else if let Some(sent) = interest.sent.get(&id) {
+                if sent.type_id == entity.type_id() && sent.generation == entity.generation() {
+                    snapshot.entities.insert(id, sent.clone());
+                }
+            }
+
+        }
 
-            // Interpolation tick delay on the client
-            ).ceil() as u8).wrapping_sub(self.config.interpolation_ticks);
+        interest.priorities = priorities;
+        interest.sent = snapshot.entities.iter().map(|(id, entity)| {
+            (*id, entity.clone())
 
-        // Calculate tick offset into entity state buffers 
-        let tick_offset = self.tick.wrapping_sub(corrected_tick) as usize;
+        }).collect();
+
+        snapshot
+
+    }
+
+
+    // State Rewinding --------------------------------------------------------
+    pub fn set_max_rewind(&mut self, rewind_ms: u32) {
+        self.max_rewind_ticks = cmp::min(
+            (rewind_ms as f32 / (1000.0 / self.config.tick_rate as f32)).ceil() as u32,
+            self.config.buffered_ticks as u32
+        );
+    }
+
+    pub fn offset_states(&self, owner: &ConnectionID, remote_tick: u32) -> HashMap<u16, S> {
+
+        let tick_offset = self.tick_offset(owner, remote_tick);
 
         // Create a copy of all entity states at the given offset
         let mut states = HashMap::with_capacity(self.entities.len());
@@ -378,5 +1187,656 @@ impl<S: EntityState, L: BaseLevel<S>, R: Renderer, G: EntityRegistry<S, L, R>> E
 
     }
 
+    pub fn rewind(&mut self, owner: &ConnectionID, remote_tick: u32) {
+        let tick_offset = self.tick_offset(owner, remote_tick);
+        for (_, entity) in &mut self.entities {
+            entity.rewind_state(tick_offset);
+        }
+    }
+
+    pub fn forward(&mut self) {
+        for (_, entity) in &mut self.entities {
+            entity.forward_state();
+        }
+    }
+
+    fn tick_offset(&self, owner: &ConnectionID, remote_tick: u32) -> usize {
+
+        // Calculate the state tick the client was actually displaying when it
+        // generated its input for "remote_tick", based on the delay it last
+        // reported along with its inputs
+        let delay = self.view_delays.get(owner).map_or(
+            self.config.interpolation_ticks,
+            |delay| *delay
+        );
+        let viewed_tick = remote_tick.saturating_sub(delay as u32);
+
+        // Ticks from the future are not rewound at all
+        if viewed_tick > self.tick {
+            0
+
+        // Calculate tick offset into entity state buffers but never go back
+        // further than allowed in order to prevent abuse
+        } else {
+            cmp::min(
+                self.tick - viewed_tick,
+                self.max_rewind_ticks
+
+            ) as usize
+        }
+
+    }
+
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use cobalt::ConnectionID;
+
+    use mock;
+    use mock::{MockState, MockInput, MockLevel, MockRegistry};
+    use network::DecodeError;
+    use renderer::DefaultRenderer;
+    use entity::EntityState;
+    use entity::input::serialize_inputs;
+    use super::{EntityManager, SNAPSHOT_HISTORY_SIZE};
+    use super::snapshot::Snapshot;
+
+    type Manager = EntityManager<MockState, MockInput, MockLevel, DefaultRenderer, MockRegistry>;
+
+    fn manager(server_mode: bool) -> Manager {
+        EntityManager::new(30, 75, 0, server_mode, MockRegistry)
+    }
+
+    fn random_buffers() -> Vec<Vec<u8>> {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        (0..2000).map(|_| {
+            let len = rng.gen_range(0, 96);
+            (0..len).map(|_| rng.gen::<u8>()).collect()
+
+        }).collect()
+    }
+
+    // Returns the lifecycle and state messages of a server with a single
+    // entity owned by the connection
+    fn server_messages() -> (Vec<u8>, Vec<u8>) {
+        let owner = ConnectionID(1);
+        let mut server = manager(true);
+        server.create(0, Some(MockState::new(1.0, 2.0)), Some(&owner)).unwrap();
+        let state = server.serialize_state(&owner, &mock::level());
+        (server.serialize_lifecycle(&owner).unwrap(), state)
+    }
+
+    #[test]
+    fn test_receive_lifecycle_unknown_type() {
+
+        let mut lifecycle = server_messages().0;
+        lifecycle[4] = 255;
+
+        let mut client = manager(false);
+        match client.receive_lifecycle(&lifecycle) {
+            Err(DecodeError::Invalid) => {},
+            _ => panic!("expected an invalid lifecycle")
+        }
+        assert_eq!(client.entities().count(), 0);
+
+    }
+
+    #[test]
+    fn test_receive_lifecycle_malformed() {
+
+        let (lifecycle, _) = server_messages();
+        assert!(manager(false).receive_lifecycle(&lifecycle).is_ok());
+
+        for len in 1..lifecycle.len() {
+            assert!(manager(false).receive_lifecycle(&lifecycle[..len]).is_err());
+        }
+
+        let mut client = manager(false);
+        for data in random_buffers() {
+            let _ = client.receive_lifecycle(&data);
+        }
+
+    }
+
+    #[test]
+    fn test_receive_state_malformed() {
+
+        let (lifecycle, state) = server_messages();
+
+        let mut client = manager(false);
+        client.receive_lifecycle(&lifecycle).unwrap();
+        assert!(client.receive_state(&state).is_ok());
+
+        // A snapshot header without any entities is still a valid state
+        for len in 1..state.len() {
+            if len != Snapshot::<MockState>::header_size() {
+                assert!(client.receive_state(&state[..len]).is_err());
+            }
+        }
+
+        for data in random_buffers() {
+            let _ = client.receive_state(&data);
+        }
+
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+
+        let owner = ConnectionID(1);
+        let level = mock::level();
+        let mut server = manager(true);
+        let mut client = manager(false);
+        let moving = server.create(0, Some(MockState::new(0.0, 0.0)), None).unwrap().id();
+        let resting = server.create(0, Some(MockState::new(50.0, 0.0)), None).unwrap().id();
+
+        let full = server.serialize_state(&owner, &level);
+        client.receive_lifecycle(&server.serialize_lifecycle(&owner).unwrap()).unwrap();
+        let acked = client.receive_state(&full).unwrap();
+        server.acknowledge_state(&owner, acked as u8);
+
+        server.tick += 1;
+        server.get_entity_mut(moving).unwrap().set_state(MockState::new(10.0, 5.0));
+
+        // Only the changes to the acknowledged state are sent
+        let delta = server.serialize_state(&owner, &level);
+        assert_eq!(Snapshot::<MockState>::header(&delta).unwrap(), (1, Some(0)));
+        assert!(delta.len() < full.len());
+
+        assert_eq!(client.receive_state(&delta).unwrap(), 1);
+        assert_eq!(*client.get_entity(moving).unwrap().state(), MockState::new(10.0, 5.0));
+        assert_eq!(*client.get_entity(resting).unwrap().state(), MockState::new(50.0, 0.0));
+
+        // States encoded against a baseline the client never received are
+        // rejected
+        let mut other = manager(false);
+        match other.receive_state(&delta) {
+            Err(DecodeError::MissingBaseline) => {},
+            _ => panic!("expected a missing baseline")
+        }
+
+    }
+
+    #[test]
+    fn test_receive_state_out_of_order() {
+
+        let owner = ConnectionID(1);
+        let level = mock::level();
+        let mut server = manager(true);
+        let mut client = manager(false);
+        let id = server.create(0, Some(MockState::new(0.0, 0.0)), None).unwrap().id();
+
+        let older = server.serialize_state(&owner, &level);
+        client.receive_lifecycle(&server.serialize_lifecycle(&owner).unwrap()).unwrap();
+
+        server.tick += 1;
+        server.get_entity_mut(id).unwrap().set_state(MockState::new(10.0, 0.0));
+        let newer = server.serialize_state(&owner, &level);
+
+        assert_eq!(client.receive_state(&newer).unwrap(), 1);
+        assert_eq!(client.receive_state(&older).unwrap(), 0);
+
+        // The late state does not roll the entity back but is still kept as
+        // a baseline
+        assert_eq!(client.get_entity(id).unwrap().state().x, 10.0);
+        assert_eq!(client.received_snapshots.latest_tick(), Some(1));
+        assert!(client.received_snapshots.get(0).is_some());
+
+    }
+
+    #[test]
+    fn test_invisible_entities() {
+
+        let owner = ConnectionID(1);
+        let mut server = manager(true);
+        let visible = server.create(0, Some(MockState::new(0.0, 0.0)), None).unwrap().id();
+        let invisible = server.create(1, Some(MockState::new(0.0, 0.0)), None).unwrap().id();
+
+        let state = server.serialize_state(&owner, &mock::level());
+        let lifecycle = server.serialize_lifecycle(&owner).unwrap();
+
+        // Invisible entities are neither spawned nor part of the state
+        let mut client = manager(false);
+        client.receive_lifecycle(&lifecycle).unwrap();
+        client.receive_state(&state).unwrap();
+        assert!(client.get_entity(visible).is_some());
+        assert!(client.get_entity(invisible).is_none());
+        assert_eq!(state.len(), Snapshot::<MockState>::header_size()
+                              + Snapshot::<MockState>::entity_header_size()
+                              + MockState::encoded_size());
+
+    }
+
+    #[test]
+    fn test_connection_interest_radius() {
+
+        let (owner, other) = (ConnectionID(1), ConnectionID(2));
+        let mut server = manager(true);
+        server.set_interest_radius(Some(50.0));
+        server.create(0, Some(MockState::new(0.0, 0.0)), Some(&owner)).unwrap();
+        let distant = server.create(0, Some(MockState::new(100.0, 0.0)), Some(&other)).unwrap().id();
+
+        let level = mock::level();
+        server.serialize_state(&owner, &level);
+        assert!(server.relevant_snapshot(&owner, &level).entities.get(&distant).is_none());
+
+        server.set_connection_interest_radius(&owner, Some(150.0));
+        assert!(server.relevant_snapshot(&owner, &level).entities.get(&distant).is_some());
+
+        server.set_connection_interest_radius(&owner, None);
+        assert!(server.relevant_snapshot(&owner, &level).entities.get(&distant).is_none());
+
+    }
+
+    #[test]
+    fn test_owned_entities() {
+
+        let (owner, other) = (ConnectionID(1), ConnectionID(2));
+        let mut server = manager(true);
+        let a = server.create(0, Some(MockState::new(0.0, 0.0)), Some(&owner)).unwrap().id();
+        let b = server.create(0, Some(MockState::new(200.0, 0.0)), Some(&owner)).unwrap().id();
+        let c = server.create(0, Some(MockState::new(230.0, 0.0)), Some(&other)).unwrap().id();
+
+        let ids: Vec<u16> = server.get_entities_for_owner(&owner).map(|e| e.id()).collect();
+        assert_eq!(ids, vec![a, b]);
+        assert_eq!(server.get_entities_for_owner_mut(&owner).count(), 2);
+
+        // Interest is measured from the closest owned entity
+        server.set_interest_radius(Some(50.0));
+        assert!(server.relevant_snapshot(&owner, &mock::level()).entities.get(&c).is_some());
+
+        server.destroy(b);
+        let ids: Vec<u16> = server.get_entities_for_owner(&owner).map(|e| e.id()).collect();
+        assert_eq!(ids, vec![a]);
+        assert!(server.relevant_snapshot(&owner, &mock::level()).entities.get(&c).is_none());
+
+        server.destroy(a);
+        assert!(server.get_entities_for_owner(&owner).next().is_none());
+        assert!(server.get_entities_for_owner_mut(&owner).next().is_none());
+        assert_eq!(server.get_entities_for_owner(&other).count(), 1);
+
+    }
+
+    #[test]
+    fn test_interest_lifecycle() {
+
+        let (owner, other) = (ConnectionID(1), ConnectionID(2));
+        let level = mock::level();
+        let mut server = manager(true);
+        let mut client = manager(false);
+        server.set_interest_radius(Some(50.0));
+        server.create(0, Some(MockState::new(0.0, 0.0)), Some(&owner)).unwrap();
+        let distant = server.create(0, Some(MockState::new(0.0, 0.0)), Some(&other)).unwrap().id();
+
+        let exchange = |server: &mut Manager, client: &mut Manager| {
+            let state = server.serialize_state(&owner, &level);
+            let lifecycle = server.serialize_lifecycle(&owner);
+            if let Some(ref lifecycle) = lifecycle {
+                client.receive_lifecycle(lifecycle).unwrap();
+            }
+            client.receive_state(&state).unwrap();
+            server.tick += 1;
+            lifecycle
+        };
+
+        assert!(exchange(&mut server, &mut client).is_some());
+        assert!(client.get_entity(distant).unwrap().is_visible());
+
+        // Leaving the interest radius only hides the entity...
+        server.get_entity_mut(distant).unwrap().set_state(MockState::new(100.0, 0.0));
+        assert!(exchange(&mut server, &mut client).is_none());
+        assert!(!client.get_entity(distant).unwrap().is_visible());
+
+        // ...and entering it again shows it without a new spawn
+        server.get_entity_mut(distant).unwrap().set_state(MockState::new(10.0, 0.0));
+        assert!(exchange(&mut server, &mut client).is_none());
+        assert!(client.get_entity(distant).unwrap().is_visible());
+
+        // Destroyed entities are despawned, even outside the radius
+        server.get_entity_mut(distant).unwrap().set_state(MockState::new(100.0, 0.0));
+        exchange(&mut server, &mut client);
+        server.destroy(distant);
+        assert!(exchange(&mut server, &mut client).is_some());
+        assert!(client.get_entity(distant).is_none());
+
+    }
+
+    #[test]
+    fn test_acknowledge_delayed_state() {
+
+        let owner = ConnectionID(1);
+        let level = mock::level();
+        let mut server = manager(true);
+        server.create(0, Some(MockState::new(0.0, 0.0)), Some(&owner)).unwrap();
+
+        // Roughly 300 ticks behind the server
+        server.set_state_delay(&owner, 10000);
+        for _ in 0..400 {
+            server.serialize_state(&owner, &level);
+            server.tick += 1;
+        }
+
+        // Acknowledging the most recently sent state makes it the baseline
+        let sent = server.sent_snapshots[&owner].latest_tick().unwrap();
+        assert!(server.tick - sent > 128);
+        server.acknowledge_state(&owner, sent as u8);
+
+        let state = server.serialize_state(&owner, &level);
+        assert_eq!(
+            Snapshot::<MockState>::header(&state).unwrap(),
+            ((sent + 1) as u8, Some(sent as u8))
+        );
+
+    }
+
+    #[test]
+    fn test_state_delay() {
+
+        let owner = ConnectionID(1);
+        let level = mock::level();
+        let mut server = manager(true);
+        server.create(0, Some(MockState::new(0.0, 0.0)), None).unwrap();
+
+        // Until enough states are buffered the oldest one is repeated
+        server.set_state_delay(&owner, 100);
+        let mut ticks = Vec::new();
+        for _ in 0..6 {
+            let state = server.serialize_state(&owner, &level);
+            ticks.push(Snapshot::<MockState>::header(&state).unwrap().0);
+            server.tick += 1;
+        }
+        assert_eq!(ticks, vec![0, 0, 0, 0, 1, 2]);
+
+        // Removing the delay sends the current state again
+        server.set_state_delay(&owner, 0);
+        let state = server.serialize_state(&owner, &level);
+        assert_eq!(Snapshot::<MockState>::header(&state).unwrap().0, 6);
+
+    }
+
+    #[test]
+    fn test_set_tick_predicted() {
+
+        let mut client = manager(false);
+        let max_ticks = client.config.buffered_ticks as u32;
+        client.set_tick(1000);
+        client.create_predicted(1, 0, MockState::new(0.0, 0.0)).unwrap();
+
+        // Jumping ahead does not expire predicted entities right away...
+        client.set_tick(1500);
+        client.update_predicted();
+        assert_eq!(client.predicted_entities.len(), 1);
+
+        // ...and jumping back does not keep them around forever
+        client.set_tick(500);
+        client.tick += max_ticks + 1;
+        client.update_predicted();
+        assert_eq!(client.predicted_entities.len(), 0);
+
+    }
+
+    #[test]
+    fn test_entity_handles() {
+
+        let mut server = manager(true);
+        let first = server.create(0, None, None).unwrap().handle();
+        assert!(server.get_entity_by_handle_mut(first).is_some());
+
+        // Once the id is re-used, handles to the destroyed entity no longer
+        // resolve to the new one
+        server.destroy(first.id);
+        for _ in 0..SNAPSHOT_HISTORY_SIZE + 1 {
+            server.id_pool.tick();
+        }
+
+        let second = server.create(0, None, None).unwrap().handle();
+        assert_eq!(second.id, first.id);
+        assert!(server.get_entity_by_handle(first).is_none());
+        assert!(server.get_entity_by_handle_mut(first).is_none());
+        assert!(server.get_entity_by_handle(second).is_some());
+
+    }
+
+    #[test]
+    fn test_confirm_predicted() {
+
+        let owner = ConnectionID(1);
+        let level = mock::level();
+        let mut server = manager(true);
+        let mut client = manager(false);
+
+        // Two shots are predicted, only one of them is confirmed
+        client.create_predicted(5, 0, MockState::new(0.0, 0.0)).unwrap();
+        client.create_predicted(6, 0, MockState::new(0.0, 0.0)).unwrap();
+        assert!(client.create_predicted(5, 0, MockState::new(0.0, 0.0)).is_none());
+
+        let handle = server.create(0, Some(MockState::new(0.0, 0.0)), None).unwrap().handle();
+        client.confirm_predicted(5, handle);
+        client.confirm_predicted(7, handle);
+        assert_eq!(client.predicted_links.len(), 1);
+
+        // The predicted entity stays around until its remote counterpart
+        // becomes visible
+        client.update_predicted();
+        assert_eq!(client.predicted_entities.len(), 2);
+
+        let state = server.serialize_state(&owner, &level);
+        client.receive_lifecycle(&server.serialize_lifecycle(&owner).unwrap()).unwrap();
+        client.receive_state(&state).unwrap();
+        client.update_predicted();
+        assert!(client.predicted_entities.get(&5).is_none());
+        assert!(client.predicted_entities.get(&6).is_some());
+        assert!(client.predicted_links.is_empty());
+
+        // Unconfirmed entities expire after the buffered ticks
+        client.tick += client.config.buffered_ticks as u32 + 1;
+        client.update_predicted();
+        assert!(client.predicted_entities.is_empty());
+
+    }
+
+    #[test]
+    fn test_set_tick_inputs() {
+
+        let owner = ConnectionID(1);
+        let mut server = manager(true);
+        let id = server.create(0, None, Some(&owner)).unwrap().id();
+        server.tick = 100;
+
+        let send = |server: &mut Manager, tick: u8, dx: u8| {
+            let block = serialize_inputs(None, &[MockInput {
+                tick: tick,
+                dx: dx
+            }]);
+            let mut data = vec![0, (id >> 8) as u8, id as u8, 0, block.len() as u8];
+            data.extend(block);
+            server.receive_input(&owner, &data).unwrap();
+        };
+
+        // Inputs sent after the client moved back replace the queued ones
+        send(&mut server, 101, 1);
+        send(&mut server, 101, 5);
+        server.tick = 101;
+        server.get_entity_mut(id).unwrap().consume_input(101);
+        let inputs: Vec<u8> = server.get_entity(id).unwrap().inputs().map(|i| i.dx).collect();
+        assert_eq!(inputs, vec![5]);
+
+        // Local inputs no longer line up after a jump and are dropped
+        let mut client = manager(false);
+        let local = client.create(0, Some(MockState {
+            x: 0.0,
+            y: 0.0,
+            flags: 0x01
+
+        }), None).unwrap().id();
+        client.get_entity_mut(local).unwrap().local_input(MockInput {
+            tick: 0,
+            dx: 1
+        });
+        assert_eq!(client.get_entity(local).unwrap().inputs().count(), 1);
+
+        client.set_tick(50);
+        assert_eq!(client.get_entity(local).unwrap().inputs().count(), 0);
+        assert!(client.get_entity_mut(local).unwrap().serialized_inputs().is_none());
+
+    }
+
+    #[test]
+    fn test_consume_input() {
+
+        let owner = ConnectionID(1);
+        let mut server = manager(true);
+        let id = server.create(0, None, Some(&owner)).unwrap().id();
+        let max_ticks = server.config.buffered_ticks;
+        server.tick = 100;
+
+        for ticks in &[[98, 99], [100, 101 + max_ticks]] {
+
+            let inputs: Vec<MockInput> = ticks.iter().map(|tick| {
+                MockInput {
+                    tick: *tick,
+                    dx: *tick
+                }
+
+            }).collect();
+
+            let block = serialize_inputs(None, &inputs);
+            let mut data = vec![0, (id >> 8) as u8, id as u8, 0, block.len() as u8];
+            data.extend(block);
+            server.receive_input(&owner, &data).unwrap();
+
+        }
+
+        // Late inputs are applied one per tick, oldest first
+        let mut applied = Vec::new();
+        for tick in 100..104 {
+            let entity = server.get_entity_mut(id).unwrap();
+            entity.consume_input(tick);
+            applied.push(entity.inputs().last().unwrap().dx);
+        }
+        assert_eq!(applied, vec![98, 99, 100, 0]);
+
+        let stats = server.get_entity_mut(id).unwrap().take_input_stats();
+        assert_eq!(stats.consumed, 3);
+        assert_eq!(stats.late, 3);
+        assert_eq!(stats.starved, 1);
+        assert_eq!(stats.early, 1);
+        assert_eq!(stats.overflowed, 0);
+
+    }
+
+    #[test]
+    fn test_tick_interpolation() {
+
+        let owner = ConnectionID(1);
+        let level = mock::level();
+        let mut server = manager(true);
+        let mut client = manager(false);
+        server.create(0, Some(MockState::new(0.0, 0.0)), None).unwrap();
+
+        // Simulation ticks do not advance the playback on their own
+        client.receive_state(&server.serialize_state(&owner, &level)).unwrap();
+        client.tick += 10;
+        assert!(client.interpolation.render_tick().is_none());
+
+        // States arriving once per transport tick are played back at the
+        // same rate
+        client.tick_interpolation();
+        let start = client.interpolation.render_tick().unwrap();
+        for _ in 0..30 {
+            server.tick += 1;
+            client.receive_state(&server.serialize_state(&owner, &level)).unwrap();
+            client.tick_interpolation();
+        }
+
+        assert_eq!(client.interpolation.render_tick().unwrap() - start, 30.0);
+        assert_eq!(client.state_jitter(), 0.0);
+
+    }
+
+    #[test]
+    fn test_receive_input_malformed() {
+
+        let owner = ConnectionID(1);
+        let mut server = manager(true);
+        let id = server.create(0, None, Some(&owner)).unwrap().id();
+
+        // The buffer holds two ticks, a client never sends more inputs
+        let inputs: Vec<MockInput> = (1..3).map(|tick| {
+            MockInput {
+                tick: tick,
+                dx: tick
+            }
+
+        }).collect();
+
+        let block = serialize_inputs(None, &inputs);
+        let mut data = vec![0, (id >> 8) as u8, id as u8, 0, block.len() as u8];
+        data.extend(block);
+
+        // The view delay on its own is a valid message without any inputs
+        assert!(server.receive_input(&owner, &[]).is_err());
+        for len in 2..data.len() {
+            assert!(server.receive_input(&owner, &data[..len]).is_err());
+        }
+
+        assert_eq!(server.receive_input(&owner, &data).unwrap().len(), 2);
+
+        // Random blocks addressed to the owned entity as well as completely
+        // random data
+        for payload in random_buffers() {
+            let mut data = vec![0, (id >> 8) as u8, id as u8, 0, payload.len() as u8];
+            data.extend(payload.iter().cloned());
+            let _ = server.receive_input(&owner, &data);
+            let _ = server.receive_input(&owner, &payload);
+        }
+
+    }
+
+    #[test]
+    fn test_rewind() {
+
+        let owner = ConnectionID(1);
+        let level = mock::level();
+        let mut server: Manager = EntityManager::new(30, 250, 0, true, MockRegistry);
+        let id = server.create(0, None, Some(&owner)).unwrap().id();
+        let dt = server.dt();
+
+        // Move the entity by 10 units every tick
+        for tick in 0..6 {
+            {
+                let entity = server.get_entity_mut(id).unwrap();
+                entity.set_state(MockState::new(tick as f32 * 10.0, 0.0));
+                entity.tick(&level, tick, dt, true);
+            }
+            server.tick = tick + 1;
+        }
+
+        // The client draws remote entities two ticks behind its inputs
+        server.receive_input(&owner, &[2]).unwrap();
+        assert_eq!(server.offset_states(&owner, 5)[&id].x, 20.0);
+
+        server.rewind(&owner, 5);
+        assert_eq!(server.get_entity(id).unwrap().state().x, 20.0);
+
+        server.forward();
+        assert_eq!(server.get_entity(id).unwrap().state().x, 50.0);
+
+        // Larger delays are limited by the maximum rewind
+        server.set_max_rewind(100);
+        server.receive_input(&owner, &[255]).unwrap();
+        server.rewind(&owner, 5);
+        assert_eq!(server.get_entity(id).unwrap().state().x, 20.0);
+
+        server.forward();
+        assert_eq!(server.get_entity(id).unwrap().state().x, 50.0);
+
+    }
+
 }
 
@@ -0,0 +1,88 @@
+// External Dependencies ------------------------------------------------------
+use std::slice::Iter;
+use std::collections::HashMap;
+use std::collections::hash_map::ValuesMut;
+use cobalt::ConnectionID;
+
+
+// Internal Dependencies ------------------------------------------------------
+use renderer::Renderer;
+use level::BaseLevel;
+use entity::{Entity, EntityInput, EntityState};
+
+
+// Owned Entity Iterators -----------------------------------------------------
+pub struct OwnedEntities<'a, S: EntityState + 'a, I: EntityInput + 'a, L: BaseLevel<S> + 'a, R: Renderer + 'a> {
+    ids: Iter<'a, u16>,
+    entities: &'a HashMap<u16, Entity<S, I, L, R>>
+}
+
+impl<'a, S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer> OwnedEntities<'a, S, I, L, R> {
+
+    pub fn new(
+        ids: Iter<'a, u16>,
+        entities: &'a HashMap<u16, Entity<S, I, L, R>>
+
+    ) -> OwnedEntities<'a, S, I, L, R> {
+        OwnedEntities {
+            ids: ids,
+            entities: entities
+        }
+    }
+
+}
+
+impl<'a, S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer> Iterator for OwnedEntities<'a, S, I, L, R> {
+
+    type Item = &'a Entity<S, I, L, R>;
+
+    fn next(&mut self) -> Option<&'a Entity<S, I, L, R>> {
+        let entities = self.entities;
+        self.ids.next().and_then(|id| entities.get(id))
+    }
+
+}
+
+// Mutable borrows of several map values cannot be handed out by id, instead
+// the values are scanned until all owned entities have been found
+pub struct OwnedEntitiesMut<'a, S: EntityState + 'a, I: EntityInput + 'a, L: BaseLevel<S> + 'a, R: Renderer + 'a> {
+    owner: ConnectionID,
+    remaining: usize,
+    entities: ValuesMut<'a, u16, Entity<S, I, L, R>>
+}
+
+impl<'a, S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer> OwnedEntitiesMut<'a, S, I, L, R> {
+
+    pub fn new(
+        owner: ConnectionID,
+        remaining: usize,
+        entities: ValuesMut<'a, u16, Entity<S, I, L, R>>
+
+    ) -> OwnedEntitiesMut<'a, S, I, L, R> {
+        OwnedEntitiesMut {
+            owner: owner,
+            remaining: remaining,
+            entities: entities
+        }
+    }
+
+}
+
+impl<'a, S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer> Iterator for OwnedEntitiesMut<'a, S, I, L, R> {
+
+    type Item = &'a mut Entity<S, I, L, R>;
+
+    fn next(&mut self) -> Option<&'a mut Entity<S, I, L, R>> {
+        if self.remaining > 0 {
+            while let Some(entity) = self.entities.next() {
+                if entity.owned_by(&self.owner) {
+                    self.remaining -= 1;
+                    return Some(entity);
+                }
+            }
+        }
+        None
+    }
+
+}
+
@@ -1,11 +1,12 @@
 // Internal Dependencies ------------------------------------------------------
 use level::BaseLevel;
 use renderer::Renderer;
-use entity::{Entity, EntityState};
+use entity::{Entity, EntityInput, EntityState};
 
 
 // Entity Registry Trait ------------------------------------------------------
-pub trait EntityRegistry<S: EntityState, L: BaseLevel<S>, R: Renderer> {
-    fn entity_from_type_id(&self, type_id: u8) -> Entity<S, L, R>;
+// Type ids are received from the network, unknown ones must return None
+pub trait EntityRegistry<S: EntityState, I: EntityInput, L: BaseLevel<S>, R: Renderer> {
+    fn entity_from_type_id(&self, type_id: u8) -> Option<Entity<S, I, L, R>>;
 }
 
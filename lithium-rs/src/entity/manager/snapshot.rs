@@ -0,0 +1,332 @@
+// External Dependencies ------------------------------------------------------
+use std::collections::{HashMap, VecDeque};
+
+
+// Internal Dependencies ------------------------------------------------------
+use network::DecodeError;
+use entity::EntityState;
+
+
+// Entity Record Kinds --------------------------------------------------------
+const STATE_HIDDEN: u8 = 0;
+const STATE_FULL: u8 = 1;
+const STATE_DELTA: u8 = 2;
+const STATE_REMOVED: u8 = 3;
+const STATE_UNCHANGED: u8 = 4;
+
+
+// Snapshot Entity ------------------------------------------------------------
+pub struct SnapshotEntity<S: EntityState> {
+    pub type_id: u8,
+    pub generation: u8,
+    pub confirmed_tick: u8,
+    pub state: Option<S>
+}
+
+impl<S: EntityState> SnapshotEntity<S> {
+
+    pub fn clone(&self) -> SnapshotEntity<S> {
+        SnapshotEntity {
+            type_id: self.type_id,
+            generation: self.generation,
+            confirmed_tick: self.confirmed_tick,
+            state: self.state.as_ref().map(|s| s.clone())
+        }
+    }
+
+}
+
+
+// Entity State Snapshot ------------------------------------------------------
+pub struct Snapshot<S: EntityState> {
+    pub tick: u32,
+    pub entities: HashMap<u16, SnapshotEntity<S>>
+}
+
+impl<S: EntityState> Snapshot<S> {
+
+    pub fn new(tick: u32) -> Snapshot<S> {
+        Snapshot {
+            tick: tick,
+            entities: HashMap::new()
+        }
+    }
+
+    pub fn clone(&self) -> Snapshot<S> {
+        Snapshot {
+            tick: self.tick,
+            entities: self.entities.iter().map(|(id, entity)| {
+                (*id, entity.clone())
+
+            }).collect()
+        }
+    }
+
+    pub fn header_size() -> usize {
+        3
+    }
+
+    pub fn entity_header_size() -> usize {
+        6
+    }
+
+    // Returns the lower 8 bits of the snapshot tick and of the tick of the
+    // baseline it was encoded against, if any
+    pub fn header(data: &[u8]) -> Result<(u8, Option<u8>), DecodeError> {
+        if data.len() >= Snapshot::<S>::header_size() {
+            match data[2] {
+                0 => Ok((data[0], None)),
+                1 => Ok((data[0], Some(data[1]))),
+                _ => Err(DecodeError::Invalid)
+            }
+
+        } else {
+            Err(DecodeError::Truncated)
+        }
+    }
+
+    pub fn serialize(&self, base: Option<&Snapshot<S>>) -> Vec<u8> {
+
+        let mut data = [
+            self.tick as u8,
+            base.map_or(0, |b| b.tick as u8),
+            base.is_some() as u8
+
+        ].to_vec();
+
+        for (id, entity) in &self.entities {
+
+            // Only entities which existed with the same type and generation
+            // in the baseline can be delta encoded
+            let base_entity = base.and_then(|b| b.entities.get(id)).and_then(|b| {
+                if b.type_id == entity.type_id && b.generation == entity.generation {
+                    Some(b)
+
+                } else {
+                    None
+                }
+            });
+
+            let (kind, state) = match (base_entity, entity.state.as_ref()) {
+
+                // Entity is not visible to the receiver
+                (Some(b), None) => if b.state.is_none() && b.confirmed_tick == entity.confirmed_tick {
+                    continue;
+
+                } else {
+                    (STATE_HIDDEN, None)
+                },
+
+                (None, None) => (STATE_HIDDEN, None),
+
+                // Send only the changed fields relative to the baseline
+                (Some(b), Some(state)) => if let Some(ref base_state) = b.state {
+                    match state.serialize_delta(base_state) {
+                        Some(delta) => (STATE_DELTA, Some(delta)),
+                        None => if b.confirmed_tick == entity.confirmed_tick {
+                            continue;
+
+                        } else {
+                            (STATE_UNCHANGED, None)
+                        }
+                    }
+
+                } else {
+                    (STATE_FULL, Some(state.serialize()))
+                },
+
+                (None, Some(state)) => (STATE_FULL, Some(state.serialize()))
+
+            };
+
+            data.extend_from_slice(&[
+                (*id >> 8) as u8, *id as u8,
+                entity.generation,
+                entity.type_id,
+                entity.confirmed_tick,
+                kind
+            ]);
+
+            if let Some(state) = state {
+                data.extend(state);
+            }
+
+        }
+
+        // Explicitly remove entities which are no longer part of the snapshot
+        if let Some(base) = base {
+            for (id, entity) in &base.entities {
+                if !self.entities.contains_key(id) {
+                    data.extend_from_slice(&[
+                        (*id >> 8) as u8, *id as u8,
+                        entity.generation,
+                        entity.type_id,
+                        entity.confirmed_tick,
+                        STATE_REMOVED
+                    ]);
+                }
+            }
+        }
+
+        data
+
+    }
+
+    // The full snapshot tick is reconstructed by the caller since the header
+    // only contains its lower 8 bits
+    pub fn from_serialized(
+        data: &[u8], tick: u32, base: Option<&Snapshot<S>>
+
+    ) -> Result<Snapshot<S>, DecodeError> {
+
+        if let Err(err) = Snapshot::<S>::header(data) {
+            return Err(err);
+        }
+
+        // Start out with all entities from the baseline, entities which did
+        // not change are omitted from the serialized data
+        let mut snapshot = Snapshot::new(tick);
+        if let Some(base) = base {
+            for (id, entity) in &base.entities {
+                snapshot.entities.insert(*id, entity.clone());
+            }
+        }
+
+        let mut i = Snapshot::<S>::header_size();
+        while i < data.len() {
+
+            if i + Snapshot::<S>::entity_header_size() > data.len() {
+                return Err(DecodeError::Truncated);
+            }
+
+            let entity_id = (data[i] as u16) << 8 | (data[i + 1] as u16);
+            let generation = data[i + 2];
+            let type_id = data[i + 3];
+            let confirmed_tick = data[i + 4];
+            let kind = data[i + 5];
+            i += Snapshot::<S>::entity_header_size();
+
+            let state = match kind {
+
+                STATE_HIDDEN => None,
+
+                STATE_FULL => match S::from_serialized(&data[i..]) {
+                    Ok(state) => {
+                        i += S::encoded_size();
+                        Some(state)
+                    },
+                    Err(err) => return Err(err)
+                },
+
+                STATE_DELTA | STATE_UNCHANGED => {
+
+                    let base_state = match snapshot.entities.get(&entity_id) {
+                        Some(&SnapshotEntity { state: Some(ref state), .. }) => state.clone(),
+                        _ => return Err(DecodeError::Invalid)
+                    };
+
+                    if kind == STATE_DELTA {
+                        match S::from_serialized_delta(&base_state, &data[i..]) {
+                            Ok((state, len)) => {
+                                i += len;
+                                Some(state)
+                            },
+                            Err(err) => return Err(err)
+                        }
+
+                    } else {
+                        Some(base_state)
+                    }
+
+                },
+
+                STATE_REMOVED => {
+                    snapshot.entities.remove(&entity_id);
+                    continue;
+                },
+
+                _ => return Err(DecodeError::Invalid)
+
+            };
+
+            snapshot.entities.insert(entity_id, SnapshotEntity {
+                type_id: type_id,
+                generation: generation,
+                confirmed_tick: confirmed_tick,
+                state: state
+            });
+
+        }
+
+        Ok(snapshot)
+
+    }
+
+}
+
+
+// Snapshot History -----------------------------------------------------------
+pub struct SnapshotHistory<S: EntityState> {
+    snapshots: VecDeque<Snapshot<S>>,
+    acknowledged_tick: Option<u32>,
+    size: usize
+}
+
+impl<S: EntityState> SnapshotHistory<S> {
+
+    pub fn new(size: usize) -> SnapshotHistory<S> {
+        SnapshotHistory {
+            snapshots: VecDeque::with_capacity(size),
+            acknowledged_tick: None,
+            size: size
+        }
+    }
+
+    // Snapshots are kept ordered from the newest to the oldest one, even if
+    // they were received out of order
+    pub fn push(&mut self, snapshot: Snapshot<S>) {
+
+        let index = self.snapshots.iter().position(|s| s.tick <= snapshot.tick);
+        match index {
+            Some(i) if self.snapshots[i].tick == snapshot.tick => return,
+            Some(i) => self.snapshots.insert(i, snapshot),
+            None => self.snapshots.push_back(snapshot)
+        }
+
+        // Drop outdated snapshots
+        if self.snapshots.len() > self.size {
+            self.snapshots.pop_back();
+        }
+
+    }
+
+    pub fn get(&self, tick: u32) -> Option<&Snapshot<S>> {
+        self.snapshots.iter().find(|s| s.tick == tick)
+    }
+
+    pub fn latest_tick(&self) -> Option<u32> {
+        self.snapshots.front().map(|s| s.tick)
+    }
+
+    pub fn acknowledge(&mut self, tick: u32) {
+
+        let is_more_recent = self.acknowledged_tick.map_or(true, |acked| {
+            tick > acked
+        });
+
+        if is_more_recent && self.get(tick).is_some() {
+            self.acknowledged_tick = Some(tick);
+        }
+
+    }
+
+    pub fn baseline(&self) -> Option<&Snapshot<S>> {
+        self.acknowledged_tick.and_then(|tick| self.get(tick))
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.acknowledged_tick = None;
+    }
+
+}
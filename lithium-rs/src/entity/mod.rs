@@ -1,6 +1,8 @@
 // Internal Dependencies ------------------------------------------------------
+pub mod collision;
 mod entity;
 mod event;
+mod handle;
 mod input;
 mod manager;
 pub mod traits;
@@ -8,9 +10,12 @@ pub mod traits;
 
 // Re-Exports -----------------------------------------------------------------
 pub use self::entity::Entity;
+pub use self::collision::CollisionShape;
 pub use self::traits::EntityState;
 pub use self::event::EntityEvent;
-pub use self::input::EntityInput;
+pub use self::handle::EntityHandle;
+pub use self::traits::EntityInput;
+pub use self::input::InputStats;
 pub use self::traits::BaseEntity;
 pub use self::traits::DrawableEntity;
 pub use self::manager::EntityManager;
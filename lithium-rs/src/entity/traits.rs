@@ -5,23 +5,38 @@ use rustc_serialize::{Encodable, Decodable};
 
 // Internal Dependencies ------------------------------------------------------
 use renderer::Renderer;
+use network::DecodeError;
 use level::{Level, BaseLevel};
-use entity::{EntityEvent, EntityInput};
+use entity::{CollisionShape, EntityEvent};
 
 
 // Basic Entity Traits --------------------------------------------------------
-pub trait BaseEntity<S: EntityState, L: BaseLevel<S>> {
+pub trait BaseEntity<S: EntityState, I: EntityInput, L: BaseLevel<S>> {
 
     fn type_id(&self) -> u8;
 
     fn apply_input(
-        &mut self, level: &Level<S, L>, state: &mut S, input: &EntityInput, dt: f32
+        &mut self, level: &Level<S, L>, state: &mut S, input: &I, dt: f32
     );
 
+    // Invoked once per tick for authoritative (server side or locally
+    // predicted) entities after all inputs have been applied
+    fn update(&mut self, _: &Level<S, L>, _: &mut S, _: f32) {}
+
+    fn collision_shape(&self, _: &S) -> Option<CollisionShape> {
+        None
+    }
+
     fn visible_to(&self, _: &ConnectionID) -> bool {
         true
     }
 
+    // Relative importance when distributing the state budget, scaled by the
+    // distance to the receiving connection's entity
+    fn priority(&self, _: &S) -> f32 {
+        1.0
+    }
+
     fn serialize_state(&self, _: &mut S, _: &ConnectionID) {}
 
     fn event(&mut self, _: &EntityEvent, _: &S) {}
@@ -40,17 +55,67 @@ pub trait EntityState: Encodable + Decodable + Default {
 
     fn encoded_size() -> usize where Self: Sized;
 
-    fn from_serialized(data: &[u8]) -> Self where Self: Sized;
+    fn from_serialized(data: &[u8]) -> Result<Self, DecodeError> where Self: Sized;
 
     fn serialize(&self) -> Vec<u8>;
 
+    // Field level diffing against a previously acknowledged state, returns
+    // None in case nothing changed
+    fn serialize_delta(&self, _: &Self) -> Option<Vec<u8>> {
+        Some(self.serialize())
+    }
+
+    // Returns the reconstructed state and the number of bytes read
+    fn from_serialized_delta(
+        _: &Self, data: &[u8]
+
+    ) -> Result<(Self, usize), DecodeError> where Self: Sized {
+        Self::from_serialized(data).map(|state| (state, Self::encoded_size()))
+    }
+
     fn set_to(&mut self, state: &Self);
 
     fn clone(&self) -> Self;
 
     fn flags(&self) -> u8;
 
+    fn position(&self) -> (f32, f32);
+
     fn set_flags(&mut self, u8);
 
 }
 
+pub trait EntityInput: Encodable + Decodable + Default + Clone {
+
+    fn encoded_size() -> usize where Self: Sized;
+
+    fn from_serialized(data: &[u8]) -> Result<Self, DecodeError> where Self: Sized;
+
+    fn serialize(&self) -> Vec<u8>;
+
+    // Tick at which the input was generated
+    fn tick(&self) -> u8;
+
+    fn set_tick(&mut self, tick: u8);
+
+    // Used in place of missing inputs on the server, repeats the last input
+    // by default
+    fn extrapolate(&self) -> Self where Self: Sized {
+        self.clone()
+    }
+
+    // Field level diffing against the previously sent input
+    fn serialize_delta(&self, _: &Self) -> Vec<u8> {
+        self.serialize()
+    }
+
+    // Returns the reconstructed input and the number of bytes read
+    fn from_serialized_delta(
+        _: &Self, data: &[u8]
+
+    ) -> Result<(Self, usize), DecodeError> where Self: Sized {
+        Self::from_serialized(data).map(|input| (input, Self::encoded_size()))
+    }
+
+}
+
@@ -1,18 +1,37 @@
 // External Dependencies ------------------------------------------------------
+use std::collections::HashMap;
 use bincode::SizeLimit;
 use cobalt::ConnectionID;
 use rustc_serialize::{Encodable, Decodable};
 use bincode::rustc_serialize::{encode,decode_from};
 
 
+// Internal Dependencies ------------------------------------------------------
+use network::DecodeError;
+
+
 // Abstract Event -------------------------------------------------------------
 pub trait Event: Encodable + Decodable + Default {}
 
 
+// Event Targets --------------------------------------------------------------
+#[derive(Debug, Clone)]
+pub enum EventTarget {
+    All,
+    Connection(ConnectionID),
+    Connections(Vec<ConnectionID>),
+    AllExcept(ConnectionID),
+    // Connections to which the entity with the given id is visible
+    Entity(u16),
+    Team(u8)
+}
+
+
 // Event Handler --------------------------------------------------------------
 pub struct EventHandler<T: Event> {
     incoming: Option<Vec<(ConnectionID, T)>>,
-    outgoing: Vec<(Option<ConnectionID>, T)>
+    outgoing: Vec<(EventTarget, T)>,
+    teams: HashMap<ConnectionID, u8>
 }
 
 impl<T: Event> EventHandler<T> {
@@ -20,51 +39,58 @@ impl<T: Event> EventHandler<T> {
     pub fn new() -> EventHandler<T> {
         EventHandler {
             incoming: None,
-            outgoing: Vec::new()
+            outgoing: Vec::new(),
+            teams: HashMap::new()
         }
     }
 
-    pub fn send_to(&mut self, receiver: Option<ConnectionID>, event: T) {
-        self.outgoing.push((receiver, event));
+    pub fn send_to(&mut self, target: EventTarget, event: T) {
+        self.outgoing.push((target, event));
     }
 
     pub fn send(&mut self, event: T) {
-        self.outgoing.push((None, event));
+        self.outgoing.push((EventTarget::All, event));
+    }
+
+    pub fn set_team(&mut self, receiver: &ConnectionID, team: Option<u8>) {
+        if let Some(team) = team {
+            self.teams.insert(*receiver, team);
+
+        } else {
+            self.teams.remove(receiver);
+        }
     }
 
     pub fn received(&mut self) -> Option<Vec<(ConnectionID, T)>> {
         self.incoming.take()
     }
 
-    pub fn receive_events(&mut self, owner_id: ConnectionID, mut data: &[u8]) {
+    pub fn receive_events(
+        &mut self, owner_id: ConnectionID, mut data: &[u8]
 
+    ) -> Result<(), DecodeError> {
+
+        // Events are only accepted in case the whole message could be decoded
         let mut incoming = Vec::new();
-        while let Ok(event) = decode_from::<&[u8], T>(
-            &mut data, SizeLimit::Bounded(256)
-        ) {
-            incoming.push((owner_id, event));
+        while !data.is_empty() {
+            match decode_from::<&[u8], T>(&mut data, SizeLimit::Bounded(256)) {
+                Ok(event) => incoming.push((owner_id, event)),
+                Err(err) => return Err(DecodeError::from(err))
+            }
         }
 
-        self.incoming = Some(incoming);
+        // Multiple messages might be received before the events are handled
+        let mut events = self.incoming.take().unwrap_or_else(Vec::new);
+        events.extend(incoming);
+        self.incoming = Some(events);
+        Ok(())
 
     }
 
-    pub fn serialize_events(&mut self, receiver: Option<&ConnectionID>) -> Option<Vec<u8>> {
-
-        let outgoing: Vec<u8> = self.outgoing.iter().filter(|event| {
-            if let Some(r) = receiver {
-                match event.0 {
-                    Some(target) => {
-                        target == *r
-                    }
-                    None => true
-                }
-
-            } else {
-                true
-            }
+    // Serializes all outgoing events regardless of their targets
+    pub fn serialize_events(&self) -> Option<Vec<u8>> {
 
-        }).fold(Vec::new(), |mut data, event| {
+        let outgoing: Vec<u8> = self.outgoing.iter().fold(Vec::new(), |mut data, event| {
             data.extend(encode(&event.1, SizeLimit::Infinite).unwrap());
             data
         });
@@ -78,9 +104,145 @@ impl<T: Event> EventHandler<T> {
 
     }
 
+    // Serializes the outgoing events for each of the receivers, every event
+    // is only encoded once and then shared by all of its receivers
+    pub fn serialize_targeted<F: Fn(u16, &ConnectionID) -> bool>(
+        &self, receivers: &[ConnectionID], visible: F
+
+    ) -> HashMap<ConnectionID, Vec<u8>> {
+
+        let mut outgoing: HashMap<ConnectionID, Vec<u8>> = HashMap::new();
+        for &(ref target, ref event) in &self.outgoing {
+
+            let data = encode(event, SizeLimit::Infinite).unwrap();
+            for receiver in receivers {
+
+                let is_target = match *target {
+                    EventTarget::All => true,
+                    EventTarget::Connection(id) => id == *receiver,
+                    EventTarget::Connections(ref ids) => ids.contains(receiver),
+                    EventTarget::AllExcept(id) => id != *receiver,
+                    EventTarget::Entity(entity_id) => visible(entity_id, receiver),
+                    EventTarget::Team(team) => self.teams.get(receiver) == Some(&team)
+                };
+
+                if is_target {
+                    outgoing.entry(*receiver).or_insert_with(Vec::new).extend_from_slice(&data);
+                }
+
+            }
+
+        }
+
+        outgoing
+
+    }
+
     pub fn flush(&mut self) {
         self.outgoing.clear();
     }
 
 }
 
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use cobalt::ConnectionID;
+
+    use mock::MockEvent;
+    use super::{EventHandler, EventTarget};
+
+    fn values(data: Option<&Vec<u8>>) -> Vec<u32> {
+        let mut receiver = EventHandler::<MockEvent>::new();
+        if let Some(data) = data {
+            receiver.receive_events(ConnectionID(0), data).unwrap();
+        }
+        receiver.received().map_or(Vec::new(), |events| {
+            events.into_iter().map(|(_, e)| e.value).collect()
+        })
+    }
+
+    #[test]
+    fn test_receive_events_malformed() {
+
+        let mut sender = EventHandler::new();
+        sender.send(MockEvent {
+            name: "fire".to_string(),
+            value: 7
+        });
+
+        let data = sender.serialize_events().unwrap();
+        let mut receiver = EventHandler::<MockEvent>::new();
+        for len in 1..data.len() {
+            assert!(receiver.receive_events(ConnectionID(1), &data[..len]).is_err());
+        }
+
+        // Partially decoded messages must not leave any events behind
+        assert!(receiver.received().is_none());
+
+        receiver.receive_events(ConnectionID(1), &data).unwrap();
+        assert_eq!(receiver.received().unwrap()[0].1.value, 7);
+
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        for _ in 0..2000 {
+            let len = rng.gen_range(0, 96);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen::<u8>()).collect();
+            let _ = receiver.receive_events(ConnectionID(1), &data);
+        }
+
+    }
+
+    #[test]
+    fn test_serialize_targeted() {
+
+        let (a, b, c) = (ConnectionID(1), ConnectionID(2), ConnectionID(3));
+        let mut handler = EventHandler::new();
+        handler.set_team(&a, Some(1));
+        handler.set_team(&b, Some(1));
+        handler.set_team(&c, Some(2));
+
+        let targets = vec![
+            EventTarget::All,
+            EventTarget::Connection(b),
+            EventTarget::Connections(vec![a, c]),
+            EventTarget::AllExcept(a),
+            EventTarget::Entity(7),
+            EventTarget::Team(1)
+        ];
+
+        for (value, target) in targets.into_iter().enumerate() {
+            handler.send_to(target, MockEvent {
+                name: "event".to_string(),
+                value: value as u32
+            });
+        }
+
+        // Entity 7 is only visible to the third connection
+        let outgoing = handler.serialize_targeted(&[a, b, c], |id, receiver| {
+            id == 7 && *receiver == c
+        });
+
+        assert_eq!(values(outgoing.get(&a)), vec![0, 2, 5]);
+        assert_eq!(values(outgoing.get(&b)), vec![0, 1, 3, 5]);
+        assert_eq!(values(outgoing.get(&c)), vec![0, 2, 3, 4]);
+
+        // Leaving the team stops team events from being delivered
+        handler.flush();
+        handler.set_team(&b, None);
+        handler.send_to(EventTarget::Team(1), MockEvent {
+            name: "event".to_string(),
+            value: 6
+        });
+
+        let outgoing = handler.serialize_targeted(&[a, b, c], |_, _| false);
+        assert_eq!(values(outgoing.get(&a)), vec![6]);
+        assert!(outgoing.get(&b).is_none());
+        assert!(outgoing.get(&c).is_none());
+
+    }
+
+}
@@ -1,14 +1,20 @@
 // External Dependencies ------------------------------------------------------
 use std;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use num::{Zero, One, Bounded};
 
 
 // Pooled, re-usable ID generator----------------------------------------------
 pub struct IdPool<T> {
+    // Ids which are in use (true) or quarantined (false)
     acquired_ids: HashMap<T, bool>,
-    released_ids: Vec<T>,
+    released_ids: VecDeque<T>,
+    quarantined_ids: VecDeque<(T, u32)>,
+    generations: HashMap<T, u8>,
+    quarantine_ticks: u32,
+    tick: u32,
     used_ids: T,
+    quarantined: T,
     next_id: T
 }
 
@@ -28,8 +34,13 @@ impl<T> IdPool<T>
         IdPool::<T> {
             acquired_ids: HashMap::new(),
             used_ids: T::zero(),
+            quarantined: T::zero(),
             next_id: T::zero(),
-            released_ids: Vec::new()
+            released_ids: VecDeque::new(),
+            quarantined_ids: VecDeque::new(),
+            generations: HashMap::new(),
+            quarantine_ticks: 0,
+            tick: 0
         }
     }
 
@@ -37,21 +48,54 @@ impl<T> IdPool<T>
         self.used_ids
     }
 
+    // Number of ticks a released id must rest before it can be re-used
+    pub fn set_quarantine(&mut self, ticks: u32) {
+        self.quarantine_ticks = ticks;
+    }
+
+    // Generation of the id, incremented every time the id is released
+    pub fn generation(&self, id: T) -> u8 {
+        self.generations.get(&id).cloned().unwrap_or(0)
+    }
+
     pub fn reset(&mut self) {
         self.acquired_ids.clear();
         self.used_ids = T::zero();
+        self.quarantined = T::zero();
         self.next_id = T::zero();
         self.released_ids.clear();
+        self.quarantined_ids.clear();
+        self.generations.clear();
+        self.tick = 0;
+    }
+
+    pub fn tick(&mut self) {
+
+        self.tick = self.tick.wrapping_add(1);
+
+        // Make ids available again once their quarantine is over
+        while self.quarantined_ids.front().map_or(false, |&(_, tick)| {
+            self.tick.wrapping_sub(tick) >= self.quarantine_ticks
+
+        }) {
+            let (id, _) = self.quarantined_ids.pop_front().unwrap();
+            self.quarantined = self.quarantined - T::one();
+            self.acquired_ids.remove(&id);
+            self.released_ids.push_back(id);
+        }
+
     }
 
     pub fn get_id(&mut self) -> Option<T> {
 
-        // Don't exceed the pool size
-        if self.used_ids < T::max_value() {
+        // Don't exceed the pool size, quarantined ids cannot be handed out
+        // either so probing would never find a free one
+        if self.used_ids + self.quarantined < T::max_value() {
 
-            // Use released ids first this is O(1)
-            let id = if !self.released_ids.is_empty() {
-                self.released_ids.pop().unwrap()
+            // Use released ids first this is O(1), the ones released the
+            // longest time ago are re-used first
+            let id = if let Some(id) = self.released_ids.pop_front() {
+                id
 
             // If there are no released ids available, probe the id space
             // - O(1) best case
@@ -78,14 +122,64 @@ impl<T> IdPool<T>
     }
 
     pub fn release_id(&mut self, id: T) {
-        // Remove the id and push it into the released ids pool for quick
-        // re-use
-        if let Some(_) = self.acquired_ids.remove(&id) {
+
+        // Released ids are quarantined first so that they are not re-used
+        // while packets referring to them might still be in flight
+        if let Some(true) = self.acquired_ids.get(&id).cloned() {
+
             self.used_ids = self.used_ids - T::one();
-            // TODO for now we do not want to instantly re-use the IDs in
-            // order to prevent re-usage network / issues
-            //self.released_ids.push(id);
+
+            let generation = self.generation(id).wrapping_add(1);
+            self.generations.insert(id, generation);
+
+            if self.quarantine_ticks > 0 {
+                self.acquired_ids.insert(id, false);
+                self.quarantined_ids.push_back((id, self.tick));
+                self.quarantined = self.quarantined + T::one();
+
+            } else {
+                self.acquired_ids.remove(&id);
+                self.released_ids.push_back(id);
+            }
+
         }
+
+    }
+
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use super::IdPool;
+
+    #[test]
+    fn test_exhausted_by_quarantine() {
+
+        let mut pool = IdPool::<u8>::new();
+        pool.set_quarantine(32);
+
+        let ids: Vec<u8> = (0..255).map(|_| pool.get_id().unwrap()).collect();
+        assert_eq!(pool.get_id(), None);
+
+        // All ids are quarantined, none of them can be handed out yet
+        for id in ids {
+            pool.release_id(id);
+        }
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.get_id(), None);
+
+        for _ in 0..31 {
+            pool.tick();
+            assert_eq!(pool.get_id(), None);
+        }
+
+        pool.tick();
+        assert_eq!(pool.get_id(), Some(0));
+        assert_eq!(pool.generation(0), 1);
+
     }
 
 }
@@ -5,6 +5,7 @@ use std::ops::{Deref, DerefMut};
 // Internal Dependencies ------------------------------------------------------
 use renderer::Renderer;
 use entity::EntityState;
+use entity::collision;
 
 mod traits;
 pub use level::traits::BaseLevel;
@@ -35,6 +36,11 @@ impl<S: EntityState, L: BaseLevel<S>> Level<S, L> {
         self.level.limit_state(state);
     }
 
+    // Wrap around aware distance between two positions
+    pub fn delta(&self, from: (f32, f32), to: (f32, f32)) -> (f32, f32) {
+        collision::delta(from, to, self.level.wrap_bounds())
+    }
+
     pub fn interpolate_entity_state(
         &self,
         renderer: &mut Renderer,
@@ -44,6 +50,25 @@ impl<S: EntityState, L: BaseLevel<S>> Level<S, L> {
         self.level.interpolate_entity_state(renderer, current, last)
     }
 
+    pub fn blend_entity_state(&self, from: &S, to: &S, u: f32) -> S {
+        self.level.blend_entity_state(from, to, u)
+    }
+
+    pub fn extrapolate_entity_state(&self, state: &S, ticks: f32) -> S {
+        self.level.extrapolate_entity_state(state, ticks)
+    }
+
+    pub fn entity_state_distance(&self, from: &S, to: &S) -> (f32, f32) {
+        self.level.entity_state_distance(from, to)
+    }
+
+    // Offsets a simulation space state, which is then limited to the level
+    // just like simulated ones so it wraps around instead of leaving it
+    pub fn offset_entity_state(&self, state: &mut S, offset: (f32, f32)) {
+        self.level.offset_entity_state(state, offset);
+        self.level.limit_state(state);
+    }
+
     pub fn draw(&mut self, renderer: &mut Renderer) {
         self.drawable.draw(renderer, &self.level);
     }
@@ -1,5 +1,6 @@
 // Internal Dependencies ------------------------------------------------------
 use entity::EntityState;
+use network::DecodeError;
 use renderer::Renderer;
 
 
@@ -8,11 +9,43 @@ pub trait BaseLevel<S: EntityState> {
 
     fn limit_state(&self, state: &mut S);
 
+    // Size of the world in case it wraps around at its edges
+    fn wrap_bounds(&self) -> Option<(f32, f32)> {
+        None
+    }
+
     fn interpolate_entity_state(&self,_: &mut Renderer,_: &S, _: &S) -> S;
 
+    // Blends between two received states of a remote entity with u ranging
+    // from 0.0 to 1.0, by default the closer one of both states is used
+    fn blend_entity_state(&self, from: &S, to: &S, u: f32) -> S {
+        if u < 0.5 {
+            from.clone()
+
+        } else {
+            to.clone()
+        }
+    }
+
+    // Predicts the state of a remote entity the given number of ticks after
+    // its most recently received state
+    fn extrapolate_entity_state(&self, state: &S, _: f32) -> S {
+        state.clone()
+    }
+
+    // Wrap around aware distance between the positions of two states, used to
+    // smooth out corrections of locally predicted entities. By default the
+    // states are considered to be at the same position.
+    fn entity_state_distance(&self, _: &S, _: &S) -> (f32, f32) {
+        (0.0, 0.0)
+    }
+
+    // Moves a state which is about to be drawn by the given distance
+    fn offset_entity_state(&self, _: &mut S, _: (f32, f32)) {}
+
     fn encoded_size() -> usize where Self: Sized;
 
-    fn from_serialized(data: &[u8]) -> Self where Self: Sized;
+    fn from_serialized(data: &[u8]) -> Result<Self, DecodeError> where Self: Sized;
 
     fn serialize(&self) -> Vec<u8>;
 
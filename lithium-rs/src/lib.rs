@@ -12,13 +12,17 @@ extern crate rustc_serialize;
 // Module Declarations --------------------------------------------------------
 #[macro_use] mod timer;
 mod client;
+mod clock;
 pub mod entity;
 mod event;
 mod idpool;
 mod level;
+#[cfg(test)] mod mock;
 pub mod network;
 mod renderer;
+mod replay;
 mod server;
+pub mod transport;
 
 
 // Re-Exports -----------------------------------------------------------------
@@ -37,9 +41,36 @@ pub use event::Event;
 #[doc(inline)]
 pub use event::EventHandler;
 
+#[doc(inline)]
+pub use event::EventTarget;
+
 #[doc(inline)]
 pub use timer::TimerId;
 
+#[doc(inline)]
+pub use network::Incompatibility;
+
+#[doc(inline)]
+pub use network::DecodeError;
+
+#[doc(inline)]
+pub use replay::Replay;
+
+#[doc(inline)]
+pub use replay::ReplayError;
+
+#[doc(inline)]
+pub use replay::ReplayRecord;
+
+#[doc(inline)]
+pub use replay::ReplayWriter;
+
+#[doc(inline)]
+pub use transport::replay::ReplayPlayer;
+
+#[doc(inline)]
+pub use clock::ClientClock;
+
 #[doc(inline)]
 pub use client::Client;
 
@@ -64,6 +95,18 @@ pub use server::Handler as ServerHandler;
 #[doc(inline)]
 pub use server::Timer as ServerTimer;
 
+#[doc(inline)]
+pub use transport::Connection;
+
+#[doc(inline)]
+pub use transport::ConnectionSet;
+
+#[doc(inline)]
+pub use transport::ServerTransport;
+
+#[doc(inline)]
+pub use transport::ClientTransport;
+
 #[doc(inline)]
 pub use renderer::Renderer;
 
@@ -0,0 +1,271 @@
+// External Dependencies ------------------------------------------------------
+use cobalt::ConnectionID;
+use bincode::SizeLimit;
+use bincode::rustc_serialize::{encode, decode};
+
+
+// Internal Dependencies ------------------------------------------------------
+use event::Event;
+use network::DecodeError;
+use renderer::{Renderer, DefaultRenderer};
+use level::{Level, BaseLevel, DrawableLevel};
+use entity::{
+    Entity,
+    EntityInput,
+    EntityRegistry,
+    EntityState,
+    BaseEntity,
+    DrawableEntity
+};
+
+
+// Mock State -----------------------------------------------------------------
+#[derive(Debug, Default, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct MockState {
+    pub x: f32,
+    pub y: f32,
+    pub flags: u8
+}
+
+impl MockState {
+    pub fn new(x: f32, y: f32) -> MockState {
+        MockState {
+            x: x,
+            y: y,
+            flags: 0
+        }
+    }
+}
+
+impl EntityState for MockState {
+
+    fn encoded_size() -> usize {
+        9
+    }
+
+    fn from_serialized(data: &[u8]) -> Result<MockState, DecodeError> {
+        if data.len() >= MockState::encoded_size() {
+            decode::<MockState>(data).map_err(DecodeError::from)
+
+        } else {
+            Err(DecodeError::Truncated)
+        }
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        encode(&self, SizeLimit::Infinite).unwrap()
+    }
+
+    // Unchanged states are skipped, changed ones are sent in full
+    fn serialize_delta(&self, base: &MockState) -> Option<Vec<u8>> {
+        if self == base {
+            None
+
+        } else {
+            Some(self.serialize())
+        }
+    }
+
+    fn set_to(&mut self, state: &MockState) {
+        self.x = state.x;
+        self.y = state.y;
+        self.flags = state.flags;
+    }
+
+    fn clone(&self) -> MockState {
+        MockState {
+            x: self.x,
+            y: self.y,
+            flags: self.flags
+        }
+    }
+
+    fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    fn position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    fn set_flags(&mut self, flags: u8) {
+        self.flags = flags;
+    }
+
+}
+
+
+// Mock Input -----------------------------------------------------------------
+// Moves the entity along the x axis by the given number of units per tick
+#[derive(Debug, Default, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct MockInput {
+    pub tick: u8,
+    pub dx: u8
+}
+
+impl EntityInput for MockInput {
+
+    fn encoded_size() -> usize {
+        2
+    }
+
+    fn from_serialized(data: &[u8]) -> Result<MockInput, DecodeError> {
+        if data.len() >= MockInput::encoded_size() {
+            decode::<MockInput>(data).map_err(DecodeError::from)
+
+        } else {
+            Err(DecodeError::Truncated)
+        }
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        encode(&self, SizeLimit::Infinite).unwrap()
+    }
+
+    fn tick(&self) -> u8 {
+        self.tick
+    }
+
+    fn set_tick(&mut self, tick: u8) {
+        self.tick = tick;
+    }
+
+    // Missing inputs do not move so the server applies exactly the sent ones
+    fn extrapolate(&self) -> MockInput {
+        MockInput {
+            tick: self.tick,
+            dx: 0
+        }
+    }
+
+}
+
+
+// Mock Event -----------------------------------------------------------------
+#[derive(Debug, Default, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct MockEvent {
+    pub name: String,
+    pub value: u32
+}
+
+impl Event for MockEvent {}
+
+
+// Mock Level -----------------------------------------------------------------
+// Wraps around at x = 1000, states are drawn shifted by the border just like
+// in bordered game levels
+pub struct MockLevel {
+    pub border: f32
+}
+impl BaseLevel<MockState> for MockLevel {
+
+    fn limit_state(&self, state: &mut MockState) {
+        if state.x < 0.0 {
+            state.x += 1000.0;
+
+        } else if state.x >= 1000.0 {
+            state.x -= 1000.0;
+        }
+    }
+
+    fn interpolate_entity_state(
+        &self, _: &mut Renderer, _: &MockState, current: &MockState
+
+    ) -> MockState {
+        MockState {
+            x: current.x - self.border,
+            .. current.clone()
+        }
+    }
+
+    fn entity_state_distance(&self, from: &MockState, to: &MockState) -> (f32, f32) {
+        (to.x - from.x, to.y - from.y)
+    }
+
+    fn offset_entity_state(&self, state: &mut MockState, offset: (f32, f32)) {
+        state.x += offset.0;
+        state.y += offset.1;
+    }
+
+    fn encoded_size() -> usize {
+        0
+    }
+
+    fn from_serialized(_: &[u8]) -> Result<MockLevel, DecodeError> {
+        Ok(MockLevel {
+            border: 0.0
+        })
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+}
+
+pub struct MockDrawableLevel;
+impl DrawableLevel<MockState> for MockDrawableLevel {}
+
+pub fn level() -> Level<MockState, MockLevel> {
+    bordered_level(0.0)
+}
+
+pub fn bordered_level(border: f32) -> Level<MockState, MockLevel> {
+    Level::new(MockLevel {
+        border: border
+    }, Box::new(MockDrawableLevel))
+}
+
+
+// Mock Entity ----------------------------------------------------------------
+// Entities of type 1 are not visible to any connection
+pub struct MockEntity {
+    type_id: u8
+}
+
+impl BaseEntity<MockState, MockInput, MockLevel> for MockEntity {
+
+    fn type_id(&self) -> u8 {
+        self.type_id
+    }
+
+    fn visible_to(&self, _: &ConnectionID) -> bool {
+        self.type_id != 1
+    }
+
+    fn apply_input(
+        &mut self,
+        _: &Level<MockState, MockLevel>,
+        state: &mut MockState,
+        input: &MockInput,
+        _: f32
+    ) {
+        state.x += input.dx as f32;
+    }
+
+}
+
+pub struct MockDrawable;
+impl DrawableEntity<MockState, MockLevel, DefaultRenderer> for MockDrawable {}
+
+
+// Mock Registry --------------------------------------------------------------
+// Knows about the visible and the invisible mock entity types
+pub struct MockRegistry;
+impl EntityRegistry<MockState, MockInput, MockLevel, DefaultRenderer> for MockRegistry {
+    fn entity_from_type_id(
+        &self, type_id: u8
+
+    ) -> Option<Entity<MockState, MockInput, MockLevel, DefaultRenderer>> {
+        match type_id {
+            0 | 1 => Some(Entity::new(
+                Box::new(MockEntity {
+                    type_id: type_id
+                }),
+                Box::new(MockDrawable)
+            )),
+            _ => None
+        }
+    }
+}
+
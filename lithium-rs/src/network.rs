@@ -1,3 +1,7 @@
+// External Dependencies ------------------------------------------------------
+use bincode::rustc_serialize::DecodingError;
+
+
 // Network Message Types ------------------------------------------------------
 pub enum Message {
     ServerConfig = 0,
@@ -5,11 +9,26 @@ pub enum Message {
     ServerEvents = 2,
     ClientInput = 3,
     ClientEvents = 4,
+    ClientAck = 5,
+    ServerEntities = 6,
     Unknown = 255
 }
 
 impl Message {
 
+    // Splits a raw message into its type and payload
+    pub fn parse(data: &[u8]) -> Result<(Message, &[u8]), DecodeError> {
+        if data.is_empty() {
+            Err(DecodeError::Truncated)
+
+        } else {
+            match Message::from_u8(data[0]) {
+                Message::Unknown => Err(DecodeError::UnknownMessage(data[0])),
+                message => Ok((message, &data[1..]))
+            }
+        }
+    }
+
     pub fn from_u8(id: u8) -> Message {
         match id {
             0 => Message::ServerConfig,
@@ -17,8 +36,238 @@ impl Message {
             2 => Message::ServerEvents,
             3 => Message::ClientInput,
             4 => Message::ClientEvents,
+            5 => Message::ClientAck,
+            6 => Message::ServerEntities,
             _ => Message::Unknown
         }
     }
 }
 
+
+
+// Entity Lifecycle Records ---------------------------------------------------
+// Spawns and despawns are sent reliably instead of being inferred from the
+// entity states
+//
+// Despawn: [kind, id, id, generation]
+// Spawn:   [kind, id, id, generation, type_id, confirmed_tick, has_state, state...]
+pub const LIFECYCLE_DESPAWN: u8 = 0;
+pub const LIFECYCLE_SPAWN: u8 = 1;
+
+
+// Clock Sync -----------------------------------------------------------------
+// Every state acknowledgement can carry the client's local clock tick as a
+// sync request, which the server answers with the next state it sends. The
+// answer also reports how many ticks ahead of time the client's inputs arrive.
+//
+// ClientAck:   [state_tick, (client_tick x4)]
+// ServerState: [has_sync, (client_tick x4, server_tick x4, input_depth), snapshot...]
+
+
+// Client Input ---------------------------------------------------------------
+// Inputs are grouped by the entity they control, so a single connection can
+// own several entities. Each block carries the most recent unconfirmed inputs
+// as runs which are delta encoded against the input last acknowledged by the
+// server. The message starts with the number of ticks the client draws
+// remote entities behind its inputs, which the server rewinds by for hit
+// detection.
+//
+// Message: [view_delay, block...]
+// Block:  [id, id, len, len, has_base, base_tick, run_count, run...]
+// Run:    [tick, length, delta...]
+
+
+// Protocol Versioning --------------------------------------------------------
+pub const PROTOCOL_VERSION: u16 = 7;
+
+// Delta compressed entity state snapshots
+pub const CAPABILITY_DELTA_STATE: u32 = 0x0000_0001;
+
+// Client side predicted entities
+pub const CAPABILITY_PREDICTED_ENTITIES: u32 = 0x0000_0002;
+
+// Capabilities supported by this version of the library
+pub const CAPABILITIES: u32 = CAPABILITY_DELTA_STATE | CAPABILITY_PREDICTED_ENTITIES;
+
+
+// Server Config Header -------------------------------------------------------
+#[derive(Debug, Copy, Clone)]
+pub struct ConfigHeader {
+    pub protocol_version: u16,
+    pub game_version: u16,
+    pub capabilities: u32
+}
+
+impl ConfigHeader {
+
+    pub fn new(game_version: u16) -> ConfigHeader {
+        ConfigHeader {
+            protocol_version: PROTOCOL_VERSION,
+            game_version: game_version,
+            capabilities: CAPABILITIES
+        }
+    }
+
+    pub fn encoded_size() -> usize {
+        8
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        vec![
+            (self.protocol_version >> 8) as u8,
+            self.protocol_version as u8,
+            (self.game_version >> 8) as u8,
+            self.game_version as u8,
+            (self.capabilities >> 24) as u8,
+            (self.capabilities >> 16) as u8,
+            (self.capabilities >> 8) as u8,
+            self.capabilities as u8
+        ]
+    }
+
+    pub fn from_serialized(data: &[u8]) -> Result<ConfigHeader, DecodeError> {
+        if data.len() >= ConfigHeader::encoded_size() {
+            Ok(ConfigHeader {
+                protocol_version: (data[0] as u16) << 8 | data[1] as u16,
+                game_version: (data[2] as u16) << 8 | data[3] as u16,
+                capabilities: (data[4] as u32) << 24 | (data[5] as u32) << 16
+                            | (data[6] as u32) << 8 | data[7] as u32
+            })
+
+        } else {
+            Err(DecodeError::Truncated)
+        }
+    }
+
+    // Checks whether a remote header can be used with this local one, all
+    // capabilities the local side supports must also be offered remotely
+    pub fn compatible(&self, remote: &ConfigHeader) -> Result<(), Incompatibility> {
+        if remote.protocol_version != self.protocol_version {
+            Err(Incompatibility::ProtocolVersion {
+                local: self.protocol_version,
+                remote: remote.protocol_version
+            })
+
+        } else if remote.game_version != self.game_version {
+            Err(Incompatibility::GameVersion {
+                local: self.game_version,
+                remote: remote.game_version
+            })
+
+        } else if remote.capabilities & self.capabilities != self.capabilities {
+            Err(Incompatibility::Capabilities {
+                missing: self.capabilities & !remote.capabilities
+            })
+
+        } else {
+            Ok(())
+        }
+    }
+
+}
+
+
+// Incompatibility Reasons ----------------------------------------------------
+#[derive(Debug)]
+pub enum Incompatibility {
+    ProtocolVersion {
+        local: u16,
+        remote: u16
+    },
+    GameVersion {
+        local: u16,
+        remote: u16
+    },
+    Capabilities {
+        missing: u32
+    },
+    InvalidConfig(DecodeError)
+}
+
+
+// Decoding Errors ------------------------------------------------------------
+#[derive(Debug)]
+pub enum DecodeError {
+
+    // Data ended before all expected bytes were read
+    Truncated,
+
+    // Data contained values which could not be decoded
+    Invalid,
+
+    // Message type is not known
+    UnknownMessage(u8),
+
+    // State was delta encoded against a snapshot which is no longer available
+    MissingBaseline
+
+}
+
+impl From<DecodingError> for DecodeError {
+    fn from(_: DecodingError) -> DecodeError {
+        DecodeError::Invalid
+    }
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use super::{ConfigHeader, Incompatibility, CAPABILITY_DELTA_STATE, PROTOCOL_VERSION};
+
+    #[test]
+    fn test_config_header_round_trip() {
+
+        let header = ConfigHeader::new(3);
+        let data = header.serialize();
+        assert_eq!(data.len(), ConfigHeader::encoded_size());
+
+        let decoded = ConfigHeader::from_serialized(&data).unwrap();
+        assert_eq!(decoded.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(decoded.game_version, 3);
+        assert_eq!(decoded.capabilities, header.capabilities);
+        assert!(header.compatible(&decoded).is_ok());
+
+        for len in 0..data.len() {
+            assert!(ConfigHeader::from_serialized(&data[..len]).is_err());
+        }
+
+    }
+
+    #[test]
+    fn test_config_header_compatible() {
+
+        let local = ConfigHeader::new(3);
+
+        let mut remote = ConfigHeader::new(3);
+        remote.protocol_version += 1;
+        match local.compatible(&remote) {
+            Err(Incompatibility::ProtocolVersion { local: l, remote: r }) => {
+                assert_eq!((l, r), (PROTOCOL_VERSION, PROTOCOL_VERSION + 1));
+            },
+            result => panic!("Unexpected result: {:?}", result)
+        }
+
+        match local.compatible(&ConfigHeader::new(4)) {
+            Err(Incompatibility::GameVersion { local: 3, remote: 4 }) => {},
+            result => panic!("Unexpected result: {:?}", result)
+        }
+
+        let mut remote = ConfigHeader::new(3);
+        remote.capabilities &= !CAPABILITY_DELTA_STATE;
+        match local.compatible(&remote) {
+            Err(Incompatibility::Capabilities { missing }) => {
+                assert_eq!(missing, CAPABILITY_DELTA_STATE);
+            },
+            result => panic!("Unexpected result: {:?}", result)
+        }
+
+        // Additional remote capabilities are fine
+        let mut remote = ConfigHeader::new(3);
+        remote.capabilities |= 0x8000_0000;
+        assert!(local.compatible(&remote).is_ok());
+
+    }
+
+}
@@ -1,7 +1,7 @@
 // Internal -------------------------------------------------------------------
 use event::Event;
 use level::BaseLevel;
-use entity::{EntityState, EntityRegistry};
+use entity::{EntityState, EntityInput, EntityRegistry};
 use client::{Client, Handler};
 
 
@@ -10,13 +10,14 @@ pub trait Renderer {
 
     // Statics ----------------------------------------------------------------
     fn run<
-        H: Handler<Self, G, L, E, S>,
+        H: Handler<Self, G, L, E, S, I>,
         E: Event,
         S: EntityState,
+        I: EntityInput,
         L: BaseLevel<S>,
-        G: EntityRegistry<S, L, Self>
+        G: EntityRegistry<S, I, L, Self>
 
-    >(_: Client<H, Self, G, L, E, S>) where Self: Sized {}
+    >(_: Client<H, Self, G, L, E, S, I>) where Self: Sized {}
 
     // Time Related -----------------------------------------------------------
     fn time(&self) -> f64;
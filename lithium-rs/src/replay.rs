@@ -0,0 +1,473 @@
+// External Dependencies ------------------------------------------------------
+use std::fs::File;
+use std::path::Path;
+use std::collections::HashMap;
+use std::io::{BufWriter, Error, Read, Write};
+use cobalt::ConnectionID;
+
+
+// Internal Dependencies ------------------------------------------------------
+use network::{ConfigHeader, DecodeError};
+use entity::{EntityHandle, EntityManagerConfig};
+
+
+// Replay Format --------------------------------------------------------------
+pub const REPLAY_VERSION: u16 = 3;
+
+const REPLAY_MAGIC: &'static [u8] = b"LREP";
+
+// Connection ID under which states and events are serialized for replays,
+// the replay viewer acts as a spectator which owns no entities
+pub const REPLAY_CONNECTION_ID: ConnectionID = ConnectionID(0xFFFF_FFFF);
+
+const RECORD_STATE: u8 = 0;
+const RECORD_EVENTS: u8 = 1;
+const RECORD_INPUT: u8 = 2;
+const RECORD_CLIENT_EVENTS: u8 = 3;
+const RECORD_CREATED: u8 = 4;
+const RECORD_DESTROYED: u8 = 5;
+
+
+// Replay Records -------------------------------------------------------------
+#[derive(Debug, PartialEq)]
+pub enum ReplayRecord {
+
+    // Full, non delta encoded entity state snapshot
+    State(Vec<u8>),
+
+    // Serialized events which were sent to all clients
+    Events(Vec<u8>),
+
+    // Serialized input received from a client for one of its entities
+    Input(ConnectionID, u16, Vec<u8>),
+
+    // Serialized events received from a client
+    ClientEvents(ConnectionID, Vec<u8>),
+
+    // Entity creation, along with its type and owner
+    Created(EntityHandle, u8, Option<ConnectionID>),
+
+    Destroyed(EntityHandle)
+
+}
+
+impl ReplayRecord {
+
+    fn serialize(&self) -> (u8, Vec<u8>) {
+        match *self {
+            ReplayRecord::State(ref data) => (RECORD_STATE, data.clone()),
+            ReplayRecord::Events(ref data) => (RECORD_EVENTS, data.clone()),
+            ReplayRecord::Input(ref owner, entity_id, ref input) => {
+                let mut data = write_u32(owner.0);
+                data.push((entity_id >> 8) as u8);
+                data.push(entity_id as u8);
+                data.extend_from_slice(input);
+                (RECORD_INPUT, data)
+            },
+            ReplayRecord::ClientEvents(ref owner, ref events) => {
+                let mut data = write_u32(owner.0);
+                data.extend_from_slice(events);
+                (RECORD_CLIENT_EVENTS, data)
+            },
+            ReplayRecord::Created(handle, type_id, ref owner) => {
+                let mut data = write_handle(handle);
+                data.push(type_id);
+                data.push(owner.is_some() as u8);
+                data.extend(write_u32(owner.map_or(0, |o| o.0)));
+                (RECORD_CREATED, data)
+            },
+            ReplayRecord::Destroyed(handle) => {
+                (RECORD_DESTROYED, write_handle(handle))
+            }
+        }
+    }
+
+    fn from_serialized(kind: u8, data: &[u8]) -> Result<ReplayRecord, DecodeError> {
+        match kind {
+            RECORD_STATE => Ok(ReplayRecord::State(data.to_vec())),
+            RECORD_EVENTS => Ok(ReplayRecord::Events(data.to_vec())),
+            RECORD_INPUT => if data.len() >= 6 {
+                Ok(ReplayRecord::Input(
+                    ConnectionID(read_u32(data)),
+                    (data[4] as u16) << 8 | data[5] as u16,
+                    data[6..].to_vec()
+                ))
+
+            } else {
+                Err(DecodeError::Truncated)
+            },
+            RECORD_CLIENT_EVENTS => if data.len() >= 4 {
+                Ok(ReplayRecord::ClientEvents(
+                    ConnectionID(read_u32(data)),
+                    data[4..].to_vec()
+                ))
+
+            } else {
+                Err(DecodeError::Truncated)
+            },
+            RECORD_CREATED => if data.len() >= 9 {
+                Ok(ReplayRecord::Created(
+                    read_handle(data),
+                    data[3],
+                    match data[4] {
+                        0 => None,
+                        1 => Some(ConnectionID(read_u32(&data[5..]))),
+                        _ => return Err(DecodeError::Invalid)
+                    }
+                ))
+
+            } else {
+                Err(DecodeError::Truncated)
+            },
+            RECORD_DESTROYED => if data.len() >= 3 {
+                Ok(ReplayRecord::Destroyed(read_handle(data)))
+
+            } else {
+                Err(DecodeError::Truncated)
+            },
+            _ => Err(DecodeError::Invalid)
+        }
+    }
+
+}
+
+
+// Replay Writer --------------------------------------------------------------
+pub struct ReplayWriter {
+    writer: Box<Write>,
+    frame: u32,
+    entities: HashMap<EntityHandle, (u8, Option<ConnectionID>)>
+}
+
+impl ReplayWriter {
+
+    pub fn create(path: &Path, config: &[u8]) -> Result<ReplayWriter, Error> {
+        match File::create(path) {
+            Ok(file) => ReplayWriter::new(Box::new(BufWriter::new(file)), config),
+            Err(err) => Err(err)
+        }
+    }
+
+    // Writes the replay header, the config is the payload of the server's
+    // config message and is handed to replaying clients as is
+    pub fn new(mut writer: Box<Write>, config: &[u8]) -> Result<ReplayWriter, Error> {
+
+        let mut header = REPLAY_MAGIC.to_vec();
+        header.push((REPLAY_VERSION >> 8) as u8);
+        header.push(REPLAY_VERSION as u8);
+        header.extend(write_u32(config.len() as u32));
+        header.extend_from_slice(config);
+
+        match writer.write_all(&header) {
+            Ok(_) => Ok(ReplayWriter {
+                writer: writer,
+                frame: 0,
+                entities: HashMap::new()
+            }),
+            Err(err) => Err(err)
+        }
+
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn write(&mut self, record: &ReplayRecord) -> Result<(), Error> {
+        let (kind, data) = record.serialize();
+        let mut bytes = vec![kind];
+        bytes.extend(write_u32(self.frame));
+        bytes.extend(write_u32(data.len() as u32));
+        bytes.extend(data);
+        self.writer.write_all(&bytes)
+    }
+
+    // Records all entities which were created or destroyed since the last
+    // call, destructions come first so re-used ids are created afterwards
+    pub fn write_entities(
+        &mut self, entities: HashMap<EntityHandle, (u8, Option<ConnectionID>)>
+
+    ) -> Result<(), Error> {
+
+        let mut records = Vec::new();
+        for handle in self.entities.keys() {
+            if !entities.contains_key(handle) {
+                records.push(ReplayRecord::Destroyed(*handle));
+            }
+        }
+
+        for (handle, &(type_id, owner)) in &entities {
+            if !self.entities.contains_key(handle) {
+                records.push(ReplayRecord::Created(*handle, type_id, owner));
+            }
+        }
+
+        self.entities = entities;
+
+        for record in &records {
+            if let Err(err) = self.write(record) {
+                return Err(err);
+            }
+        }
+
+        Ok(())
+
+    }
+
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()
+    }
+
+}
+
+
+// Replay Reader --------------------------------------------------------------
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(Error),
+    InvalidFormat,
+    Version {
+        local: u16,
+        remote: u16
+    },
+    Invalid(DecodeError)
+}
+
+#[derive(Debug)]
+pub struct Replay {
+    config: Vec<u8>,
+    frames: Vec<Vec<ReplayRecord>>
+}
+
+impl Replay {
+
+    pub fn open(path: &Path) -> Result<Replay, ReplayError> {
+        let mut data = Vec::new();
+        match File::open(path).and_then(|mut file| file.read_to_end(&mut data)) {
+            Ok(_) => Replay::from_serialized(&data),
+            Err(err) => Err(ReplayError::Io(err))
+        }
+    }
+
+    pub fn from_serialized(data: &[u8]) -> Result<Replay, ReplayError> {
+
+        if data.len() < 10 || &data[0..4] != REPLAY_MAGIC {
+            return Err(ReplayError::InvalidFormat);
+        }
+
+        let version = (data[4] as u16) << 8 | data[5] as u16;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::Version {
+                local: REPLAY_VERSION,
+                remote: version
+            });
+        }
+
+        let config_size = read_u32(&data[6..]) as usize;
+        let mut data = &data[10..];
+        if data.len() < config_size {
+            return Err(ReplayError::Invalid(DecodeError::Truncated));
+        }
+
+        let config = data[..config_size].to_vec();
+        data = &data[config_size..];
+
+        // Records are stored in frame order, frames without any records
+        // are kept as empty ones
+        let mut frames: Vec<Vec<ReplayRecord>> = Vec::new();
+        while !data.is_empty() {
+
+            if data.len() < 9 {
+                return Err(ReplayError::Invalid(DecodeError::Truncated));
+            }
+
+            let kind = data[0];
+            let frame = read_u32(&data[1..]) as usize;
+            let size = read_u32(&data[5..]) as usize;
+            if data.len() < 9 + size {
+                return Err(ReplayError::Invalid(DecodeError::Truncated));
+
+            } else if frame + 1 < frames.len() {
+                return Err(ReplayError::InvalidFormat);
+            }
+
+            let record = match ReplayRecord::from_serialized(kind, &data[9..9 + size]) {
+                Ok(record) => record,
+                Err(err) => return Err(ReplayError::Invalid(err))
+            };
+
+            while frames.len() <= frame {
+                frames.push(Vec::new());
+            }
+
+            frames[frame].push(record);
+            data = &data[9 + size..];
+
+        }
+
+        Ok(Replay {
+            config: config,
+            frames: frames
+        })
+
+    }
+
+    // Payload of the server config message the replay was recorded with
+    pub fn config(&self) -> &[u8] {
+        &self.config
+    }
+
+    pub fn tick_rate(&self) -> Result<u8, DecodeError> {
+        if self.config.len() < ConfigHeader::encoded_size() {
+            Err(DecodeError::Truncated)
+
+        } else {
+            EntityManagerConfig::from_serialized(
+                &self.config[ConfigHeader::encoded_size()..]
+
+            ).map(|config| config.tick_rate)
+        }
+    }
+
+    pub fn frames(&self) -> &[Vec<ReplayRecord>] {
+        &self.frames
+    }
+
+}
+
+
+// Helpers --------------------------------------------------------------------
+fn write_u32(value: u32) -> Vec<u8> {
+    vec![
+        (value >> 24) as u8,
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8
+    ]
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    (data[0] as u32) << 24 | (data[1] as u32) << 16
+        | (data[2] as u32) << 8 | data[3] as u32
+}
+
+fn write_handle(handle: EntityHandle) -> Vec<u8> {
+    vec![(handle.id >> 8) as u8, handle.id as u8, handle.generation]
+}
+
+fn read_handle(data: &[u8]) -> EntityHandle {
+    EntityHandle::new((data[0] as u16) << 8 | data[1] as u16, data[2])
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use std::io::{Error, Write};
+    use cobalt::ConnectionID;
+
+    use entity::EntityHandle;
+    use super::{Replay, ReplayRecord, ReplayWriter};
+
+    // Keeps the written bytes accessible after the writer was boxed
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+
+        fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+            self.0.borrow_mut().extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+    }
+
+    #[test]
+    fn test_round_trip() {
+
+        let buffer = Rc::new(RefCell::new(Vec::new()));
+        let mut writer = ReplayWriter::new(
+            Box::new(SharedBuffer(buffer.clone())),
+            &[1, 2, 3]
+
+        ).unwrap();
+
+        let a = EntityHandle::new(1, 0);
+        let b = EntityHandle::new(2, 5);
+
+        // First frame
+        writer.write_entities(vec![
+            (a, (0, Some(ConnectionID(7))))
+
+        ].into_iter().collect()).unwrap();
+        writer.write(&ReplayRecord::State(vec![4, 5])).unwrap();
+        writer.write(&ReplayRecord::Input(ConnectionID(7), 1, vec![6])).unwrap();
+        writer.next_frame();
+
+        // Empty frame
+        writer.next_frame();
+
+        // Third frame
+        writer.write_entities(vec![
+            (b, (1, None))
+
+        ].into_iter().collect()).unwrap();
+        writer.write(&ReplayRecord::Events(vec![8, 9])).unwrap();
+        writer.write(&ReplayRecord::ClientEvents(ConnectionID(7), vec![10])).unwrap();
+        writer.next_frame();
+        writer.flush().unwrap();
+
+        let replay = Replay::from_serialized(&buffer.borrow()).unwrap();
+        assert_eq!(replay.config(), &[1, 2, 3]);
+        assert_eq!(replay.frames(), &[
+            vec![
+                ReplayRecord::Created(a, 0, Some(ConnectionID(7))),
+                ReplayRecord::State(vec![4, 5]),
+                ReplayRecord::Input(ConnectionID(7), 1, vec![6])
+            ],
+            vec![],
+            vec![
+                ReplayRecord::Destroyed(a),
+                ReplayRecord::Created(b, 1, None),
+                ReplayRecord::Events(vec![8, 9]),
+                ReplayRecord::ClientEvents(ConnectionID(7), vec![10])
+            ]
+        ]);
+
+    }
+
+    #[test]
+    fn test_truncated() {
+
+        let buffer = Rc::new(RefCell::new(Vec::new()));
+        let mut writer = ReplayWriter::new(
+            Box::new(SharedBuffer(buffer.clone())),
+            &[1, 2, 3]
+
+        ).unwrap();
+
+        writer.write(&ReplayRecord::State(vec![4, 5])).unwrap();
+
+        // The header along with the config is a valid replay on its own
+        let data = buffer.borrow();
+        let header_size = 10 + 3;
+        assert!(Replay::from_serialized(&data).is_ok());
+        for len in 0..data.len() {
+            if len != header_size {
+                assert!(Replay::from_serialized(&data[..len]).is_err());
+            }
+        }
+
+    }
+
+}
@@ -1,11 +1,12 @@
 // External Dependencies ------------------------------------------------------
 use std::cmp;
 use std::io::Error;
+use std::path::Path;
 use std::net::SocketAddr;
 use std::collections::{BinaryHeap, HashSet};
 use cobalt::{
     Config,
-    Connection,
+    Connection as CobaltConnection,
     ConnectionID,
     ConnectionMap,
     MessageKind,
@@ -16,17 +17,23 @@ use cobalt::{
 
 // Internal Dependencies ------------------------------------------------------
 use network;
+use network::DecodeError;
 use entity::{
     Entity,
     EntityState,
     EntityInput,
     EntityManager,
     EntityManagerConfig,
-    EntityRegistry
+    EntityRegistry,
+    InputStats
 };
+use clock;
+use clock::ServerClock;
 use level::{Level, BaseLevel};
 use event::{Event, EventHandler};
 use renderer::Renderer;
+use replay::{ReplayRecord, ReplayWriter, REPLAY_CONNECTION_ID};
+use transport::{Connection, ConnectionSet, ServerTransport};
 
 
 // Macros ---------------------------------------------------------------------
@@ -45,25 +52,29 @@ macro_rules! handle {
 
 // Server Abstraction ---------------------------------------------------------
 pub struct Server<
-    H: Handler<R, G, L, E, S>, R: Renderer,
-    G: EntityRegistry<S, L, R>, L: BaseLevel<S>, E: Event, S: EntityState
+    H: Handler<R, G, L, E, S, I>, R: Renderer,
+    G: EntityRegistry<S, I, L, R>, L: BaseLevel<S>, E: Event, S: EntityState, I: EntityInput
 > {
     handler: H,
-    manager: EntityManager<S, L, R, G>,
+    manager: EntityManager<S, I, L, R, G>,
     events: EventHandler<E>,
     level: Level<S, L>,
-    timer: Timer<H, R, G, L, E, S>
+    timer: Timer<H, R, G, L, E, S, I>,
+    clock: ServerClock,
+    header: network::ConfigHeader,
+    replay: Option<ReplayWriter>,
+    replay_error: Option<Error>
 }
 
 impl<
-    H: Handler<R, G, L, E, S>, R: Renderer,
-    G: EntityRegistry<S, L, R>, L: BaseLevel<S>, E: Event, S: EntityState
+    H: Handler<R, G, L, E, S, I>, R: Renderer,
+    G: EntityRegistry<S, I, L, R>, L: BaseLevel<S>, E: Event, S: EntityState, I: EntityInput
 
-> Server<H, R, G, L, E, S> {
+> Server<H, R, G, L, E, S, I> {
 
     // Statics ----------------------------------------------------------------
     pub fn run(
-        addr: SocketAddr, mut server: Server<H, R, G, L, E, S>
+        addr: SocketAddr, mut server: Server<H, R, G, L, E, S, I>
 
     ) -> Result<(), Error> where Self: Sized {
 
@@ -82,7 +93,7 @@ impl<
         registry: G,
         handler: H
 
-    ) -> Server<H, R, G, L, E, S> {
+    ) -> Server<H, R, G, L, E, S, I> {
         Server {
             handler: handler,
             manager: EntityManager::new(
@@ -92,7 +103,11 @@ impl<
             ),
             events: EventHandler::new(),
             level: level,
-            timer: Timer::new()
+            timer: Timer::new(),
+            clock: ServerClock::new(),
+            header: network::ConfigHeader::new(0),
+            replay: None,
+            replay_error: None
         }
     }
 
@@ -102,57 +117,76 @@ impl<
         self.manager.config()
     }
 
-}
+    pub fn set_max_rewind(&mut self, rewind_ms: u32) {
+        self.manager.set_max_rewind(rewind_ms);
+    }
 
-impl<
-    H: Handler<R, G, L, E, S>, R: Renderer,
-    G: EntityRegistry<S, L, R>, L: BaseLevel<S>, E: Event, S: EntityState
+    pub fn set_interest_radius(&mut self, radius: Option<f32>) {
+        self.manager.set_interest_radius(radius);
+    }
 
-> CobaltHandler<CobaltServer> for Server<H, R, G, L, E, S> {
+    pub fn set_state_budget(&mut self, bytes: Option<usize>) {
+        self.manager.set_state_budget(bytes);
+    }
 
-    fn bind(&mut self, server: &mut CobaltServer) {
+    pub fn set_game_version(&mut self, version: u16) {
+        self.header.game_version = version;
+    }
+
+    // Records all ticks into a replay file at the given path, must be called
+    // after the game version has been set
+    pub fn record(&mut self, path: &Path) -> Result<(), Error> {
+        match ReplayWriter::create(path, &self.serialize_config()) {
+            Ok(replay) => {
+                self.replay = Some(replay);
+                Ok(())
+            },
+            Err(err) => Err(err)
+        }
+    }
+
+
+    // Transport Handling -----------------------------------------------------
+    pub fn on_bind(&mut self, server: &mut ServerTransport) {
         self.handler.bind(handle!(self, server));
     }
 
-    fn connection(&mut self, server: &mut CobaltServer, conn: &mut Connection) {
+    pub fn on_connection(&mut self, server: &mut ServerTransport, conn: &mut Connection) {
 
         let mut config = [network::Message::ServerConfig as u8].to_vec();
-        config.extend(self.manager.serialize_config());
-        config.extend(self.level.serialize());
+        config.extend(self.serialize_config());
         conn.send(MessageKind::Reliable, config);
 
+        // Make sure to start with a full state
+        self.manager.reset_state(&conn.id());
+        self.clock.reset(&conn.id());
+
         self.handler.connect(handle!(self, server), conn);
 
     }
 
-    fn tick_connections(
-        &mut self, server: &mut CobaltServer, connections: &mut ConnectionMap
+    pub fn on_tick(
+        &mut self, server: &mut ServerTransport, connections: &mut ConnectionSet
     ) {
 
         // Receive Data
-        for (id, conn) in connections.iter_mut() {
-            for msg in conn.received() {
-                match network::Message::from_u8(msg[0]) {
-
-                    network::Message::ClientInput => {
-                        // Extract all unconfirmed inputs the client sent us
-                        if let Some(entity) = self.manager.get_entity_for_owner(id) {
-                            let msg = &msg[1..];
-                            for i in msg.chunks(EntityInput::encoded_size()) {
-                                entity.remote_input(
-                                    EntityInput::from_serialized(i)
-                                );
-                            }
-                        }
-                    },
-
-                    network::Message::ClientEvents => {
-                        self.events.receive_events(*id, &msg[1..]);
-                    },
-
-                    _=> println!("Unknown Client Message {:?}", msg)
+        for id in connections.ids() {
+            if let Some(conn) = connections.get_mut(&id) {
+
+                // Stop processing messages from a connection once it sent
+                // malformed data
+                let mut error = None;
+                for msg in conn.received() {
+                    if let Err(err) = self.receive_message(&id, &msg) {
+                        error = Some(err);
+                        break;
+                    }
+                }
 
+                if let Some(err) = error {
+                    self.handler.invalid_message(handle!(self, server), conn, err);
                 }
+
             }
         }
 
@@ -165,7 +199,18 @@ impl<
 
         // Tick Entities
         self.handler.tick_before(handle!(self, server), connections);
-        self.manager.tick_server(&self.level, &mut self.handler);
+        // Only inputs which were actually applied end up in the replay,
+        // redundant and rejected ones are never simulated
+        let inputs = self.manager.tick_server(&self.level, &mut self.handler);
+        for (owner, entity_id, input) in inputs {
+            self.write_replay(ReplayRecord::Input(owner, entity_id, input.serialize()));
+        }
+
+        // Handle collisions
+        for (a, b) in self.manager.collisions() {
+            self.handler.collision(handle!(self, server), a, b);
+        }
+
         self.handler.tick_after(handle!(self, server), connections);
 
         // Run Timers
@@ -175,34 +220,229 @@ impl<
             f(&mut self.handler, handle!(self, server));
         }
 
+        // Filter events once for all connections, replays receive the events
+        // as seen by a spectator
+        let mut receivers = connections.ids();
+        if self.replay.is_some() {
+            receivers.push(REPLAY_CONNECTION_ID);
+        }
+
+        let mut events = {
+            let manager = &self.manager;
+            self.events.serialize_targeted(&receivers, |entity_id, receiver| {
+                manager.get_entity(entity_id).map_or(false, |e| e.visible_to(receiver))
+            })
+        };
+
         // Send Data
-        for (id, conn) in connections.iter_mut() {
-
-            // Send entity states to all clients (We don't care about dropped packets)
-            let mut data = [network::Message::ServerState as u8].to_vec();
-            data.extend(self.manager.serialize_state(id));
-            conn.send(MessageKind::Instant, data);
-
-            // Send events to all clients (Make sure the arrive eventually)
-            // TODO event visibility handling
-            if let Some(events) = self.events.serialize_events(Some(&id)) {
-                let mut data = [network::Message::ServerEvents as u8].to_vec();
-                data.extend(events);
-                conn.send(MessageKind::Ordered, data);
-            }
+        for id in connections.ids() {
+            if let Some(conn) = connections.get_mut(&id) {
+
+                // Send entity states to all clients (We don't care about dropped packets)
+                let mut data = [network::Message::ServerState as u8].to_vec();
+                data.extend(self.clock.response(&id, self.manager.tick()));
+                data.extend(self.manager.serialize_state(&id, &self.level));
+
+                // Spawns and despawns must never get lost
+                if let Some(lifecycle) = self.manager.serialize_lifecycle(&id) {
+                    let mut entities = [network::Message::ServerEntities as u8].to_vec();
+                    entities.extend(lifecycle);
+                    conn.send(MessageKind::Ordered, entities);
+                }
+
+                conn.send(MessageKind::Instant, data);
+
+                // Send events to all clients (Make sure the arrive eventually)
+                if let Some(events) = events.remove(&id) {
+                    let mut data = [network::Message::ServerEvents as u8].to_vec();
+                    data.extend(events);
+                    conn.send(MessageKind::Ordered, data);
+                }
 
+            }
         }
 
+        self.record_tick(events.remove(&REPLAY_CONNECTION_ID));
         self.events.flush();
 
+        if let Some(err) = self.replay_error.take() {
+            self.handler.replay_error(handle!(self, server), err);
+        }
+
     }
 
-    fn connection_lost(&mut self, server: &mut CobaltServer, conn: &mut Connection) {
+    pub fn on_connection_lost(&mut self, server: &mut ServerTransport, conn: &mut Connection) {
+        self.manager.reset_state(&conn.id());
+        self.clock.reset(&conn.id());
+        self.events.set_team(&conn.id(), None);
         self.handler.disconnect(handle!(self, server), conn);
     }
 
-    fn shutdown(&mut self, server: &mut CobaltServer) {
+    pub fn on_shutdown(&mut self, server: &mut ServerTransport) {
         self.handler.shutdown(handle!(self, server));
+        if let Some(mut replay) = self.replay.take() {
+            if let Err(err) = replay.flush() {
+                self.handler.replay_error(handle!(self, server), err);
+            }
+        }
+    }
+
+
+    // Internal ---------------------------------------------------------------
+    fn receive_message(&mut self, id: &ConnectionID, data: &[u8]) -> Result<(), DecodeError> {
+
+        let (message, data) = match network::Message::parse(data) {
+            Ok(message) => message,
+            Err(err) => return Err(err)
+        };
+
+        match message {
+
+            network::Message::ClientInput => {
+
+                // Route all unconfirmed inputs the client sent us to the
+                // entities they are tagged with
+                let inputs = match self.manager.receive_input(id, data) {
+                    Ok(inputs) => inputs,
+                    Err(err) => return Err(err)
+                };
+
+                // Track how far ahead of time the most recent input arrived
+                if let Some(&(_, ref input)) = inputs.last() {
+                    self.clock.input(id, input.tick(), self.manager.tick());
+                }
+
+                Ok(())
+
+            },
+
+            network::Message::ClientEvents => {
+                let result = self.events.receive_events(*id, data);
+                if result.is_ok() {
+                    self.write_replay(ReplayRecord::ClientEvents(*id, data.to_vec()));
+                }
+                result
+            },
+
+            network::Message::ClientAck => {
+                // Use the last received state as the new delta baseline and
+                // answer the attached sync request, if any
+                if data.is_empty() {
+                    Err(DecodeError::Truncated)
+
+                } else {
+                    self.manager.acknowledge_state(id, data[0]);
+                    if data.len() >= 5 {
+                        self.clock.request(id, clock::deserialize_u32(&data[1..5]));
+                    }
+                    Ok(())
+                }
+            },
+
+            message => Err(DecodeError::UnknownMessage(message as u8))
+
+        }
+
+    }
+
+    fn serialize_config(&self) -> Vec<u8> {
+        let mut config = self.header.serialize();
+        config.extend(self.manager.serialize_config());
+        config.extend(self.level.serialize());
+        config
+    }
+
+    fn write_replay(&mut self, record: ReplayRecord) {
+        let result = if let Some(ref mut replay) = self.replay {
+            replay.write(&record)
+
+        } else {
+            Ok(())
+        };
+
+        if let Err(err) = result {
+            self.stop_recording(err);
+        }
+    }
+
+    fn record_tick(&mut self, events: Option<Vec<u8>>) {
+
+        let result = if let Some(ref mut replay) = self.replay {
+
+            let entities = self.manager.entities().map(|e| {
+                (e.handle(), (e.type_id(), e.owner().cloned()))
+
+            }).collect();
+
+            let mut result = replay.write_entities(entities);
+            // Replays are viewed as a spectator which does not own any
+            // entities
+            if result.is_ok() {
+                result = replay.write(&ReplayRecord::State(
+                    self.manager.serialize_full_state(&REPLAY_CONNECTION_ID)
+                ));
+            }
+
+            if result.is_ok() {
+                if let Some(events) = events {
+                    result = replay.write(&ReplayRecord::Events(events));
+                }
+            }
+
+            // Flush once per second so interrupted servers still leave a
+            // usable replay behind
+            replay.next_frame();
+            if result.is_ok() && replay.frame() % self.manager.config().tick_rate as u32 == 0 {
+                result = replay.flush();
+            }
+
+            result
+
+        } else {
+            Ok(())
+        };
+
+        if let Err(err) = result {
+            self.stop_recording(err);
+        }
+
+    }
+
+    // Recording stops on the first failed write, the error is handed to the
+    // handler at the end of the tick
+    fn stop_recording(&mut self, err: Error) {
+        self.replay = None;
+        self.replay_error = Some(err);
+    }
+
+}
+
+impl<
+    H: Handler<R, G, L, E, S, I>, R: Renderer,
+    G: EntityRegistry<S, I, L, R>, L: BaseLevel<S>, E: Event, S: EntityState, I: EntityInput
+
+> CobaltHandler<CobaltServer> for Server<H, R, G, L, E, S, I> {
+
+    fn bind(&mut self, server: &mut CobaltServer) {
+        self.on_bind(server);
+    }
+
+    fn connection(&mut self, server: &mut CobaltServer, conn: &mut CobaltConnection) {
+        self.on_connection(server, conn);
+    }
+
+    fn tick_connections(
+        &mut self, server: &mut CobaltServer, connections: &mut ConnectionMap
+    ) {
+        self.on_tick(server, connections);
+    }
+
+    fn connection_lost(&mut self, server: &mut CobaltServer, conn: &mut CobaltConnection) {
+        self.on_connection_lost(server, conn);
+    }
+
+    fn shutdown(&mut self, server: &mut CobaltServer) {
+        self.on_shutdown(server);
     }
 
 }
@@ -211,45 +451,92 @@ impl<
 // Server Handle for Access from Handler ------------------------------------
 pub struct Handle<
     'a,
-    H: Handler<R, G, L, E, S> + 'a,
+    H: Handler<R, G, L, E, S, I> + 'a,
     R: Renderer + 'a,
-    G: EntityRegistry<S, L, R> + 'a,
+    G: EntityRegistry<S, I, L, R> + 'a,
     L: BaseLevel<S> + 'a,
     E: Event + 'a,
-    S: EntityState + 'a
+    S: EntityState + 'a,
+    I: EntityInput + 'a
 > {
     pub level: &'a mut Level<S, L>,
-    pub entities: &'a mut EntityManager<S, L, R, G>,
+    pub entities: &'a mut EntityManager<S, I, L, R, G>,
     pub events: &'a mut EventHandler<E>,
-    pub timer: &'a mut Timer<H, R, G, L, E, S>,
-    pub server: &'a mut CobaltServer
+    pub timer: &'a mut Timer<H, R, G, L, E, S, I>,
+    pub server: &'a mut ServerTransport
+}
+
+impl<
+    'a,
+    H: Handler<R, G, L, E, S, I> + 'a,
+    R: Renderer + 'a,
+    G: EntityRegistry<S, I, L, R> + 'a,
+    L: BaseLevel<S> + 'a,
+    E: Event + 'a,
+    S: EntityState + 'a,
+    I: EntityInput + 'a
+
+> Handle<'a, H, R, G, L, E, S, I> {
+
+    // Number of inputs consumed, dropped and missed by the entities the
+    // connection owns
+    pub fn input_stats(&self, conn: &Connection) -> InputStats {
+        self.entities.input_stats(&conn.id())
+    }
+
+    // Overrides the interest radius for the connection until it disconnects,
+    // None falls back to the radius set on the server
+    pub fn set_interest_radius(&mut self, conn: &Connection, radius: Option<f32>) {
+        self.entities.set_connection_interest_radius(&conn.id(), radius);
+    }
+
+    // Rewinds all entities to the states the connection was seeing when it
+    // generated its input for the given full tick, invokes the callback and
+    // restores the actual states afterwards
+    pub fn rewind<F, T>(&mut self, conn: &Connection, remote_tick: u32, f: F) -> T
+        where F: FnOnce(&Level<S, L>, &mut EntityManager<S, I, L, R, G>) -> T {
+
+        self.entities.rewind(&conn.id(), remote_tick);
+        let result = f(&*self.level, &mut *self.entities);
+        self.entities.forward();
+        result
+
+    }
+
 }
 
 
 // Server Handler -------------------------------------------------------------
 pub trait Handler<
     R: Renderer,
-    G: EntityRegistry<S, L, R>, L: BaseLevel<S>, E: Event, S: EntityState,
+    G: EntityRegistry<S, I, L, R>, L: BaseLevel<S>, E: Event, S: EntityState, I: EntityInput,
 > {
 
-    fn bind(&mut self, Handle<Self, R, G, L, E, S>) where Self: Sized;
-    fn connect(&mut self, Handle<Self, R, G, L, E, S>, &mut Connection) where Self: Sized;
-    fn disconnect(&mut self, Handle<Self, R, G, L, E, S>, &mut Connection) where Self: Sized;
+    fn bind(&mut self, Handle<Self, R, G, L, E, S, I>) where Self: Sized;
+    fn connect(&mut self, Handle<Self, R, G, L, E, S, I>, &mut Connection) where Self: Sized;
+    fn disconnect(&mut self, Handle<Self, R, G, L, E, S, I>, &mut Connection) where Self: Sized;
+
+    fn event(&mut self, Handle<Self, R, G, L, E, S, I>, &mut ConnectionSet, ConnectionID, E) where Self: Sized;
+
+    fn invalid_message(&mut self, Handle<Self, R, G, L, E, S, I>, &mut Connection, DecodeError) where Self: Sized;
+
+    // Invoked once recording a replay failed, no further ticks are recorded
+    fn replay_error(&mut self, Handle<Self, R, G, L, E, S, I>, Error) where Self: Sized;
 
-    fn event(&mut self, Handle<Self, R, G, L, E, S>, &mut ConnectionMap, ConnectionID, E) where Self: Sized;
+    fn tick_before(&mut self, Handle<Self, R, G, L, E, S, I>, &mut ConnectionSet) where Self: Sized;
 
-    fn tick_before(&mut self, Handle<Self, R, G, L, E, S>, &mut ConnectionMap) where Self: Sized;
+    fn tick_entity_before(&mut self, &Level<S, L>, &mut Entity<S, I, L, R>, u32, f32);
+    fn tick_entity_after(&mut self, &Level<S, L>, &mut Entity<S, I, L, R>, u32, f32);
 
-    fn tick_entity_before(&mut self, &Level<S, L>, &mut Entity<S, L, R>, u8, f32);
-    fn tick_entity_after(&mut self, &Level<S, L>, &mut Entity<S, L, R>, u8, f32);
+    fn collision(&mut self, Handle<Self, R, G, L, E, S, I>, u16, u16) where Self: Sized;
 
-    fn tick_after(&mut self, Handle<Self, R, G, L, E, S>, &mut ConnectionMap) where Self: Sized;
+    fn tick_after(&mut self, Handle<Self, R, G, L, E, S, I>, &mut ConnectionSet) where Self: Sized;
 
-    fn shutdown(&mut self, Handle<Self, R, G, L, E, S>) where Self: Sized;
+    fn shutdown(&mut self, Handle<Self, R, G, L, E, S, I>) where Self: Sized;
 
 }
 
 
 // Timer Implementation -------------------------------------------------------
-impl_timer!(Handler, Renderer, EntityRegistry, BaseLevel, Event, EntityState);
+impl_timer!(Handler, Renderer, EntityRegistry, BaseLevel, Event, EntityState, EntityInput);
 
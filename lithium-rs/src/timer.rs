@@ -2,36 +2,38 @@
 pub struct TimerId(pub u32);
 
 macro_rules! impl_timer {
-    ($h:ident, $r:ident, $g:ident, $l:ident, $e:ident, $s:ident) => {
+    ($h:ident, $r:ident, $g:ident, $l:ident, $e:ident, $s:ident, $i:ident) => {
 
         use timer::TimerId;
 
         // Timer Abstraction --------------------------------------------------
         pub struct Timer<
-            H: $h<R, G, L, E, S>,
+            H: $h<R, G, L, E, S, I>,
             R: $r,
-            G: $g<S, L, R>,
+            G: $g<S, I, L, R>,
             L: $l<S>,
             E: $e,
-            S: $s
+            S: $s,
+            I: $i
         > {
-            callbacks: BinaryHeap<TimerCallback<H, R, G, L, E, S>>,
+            callbacks: BinaryHeap<TimerCallback<H, R, G, L, E, S, I>>,
             canceled: HashSet<TimerId>,
             time: u64,
             id: u32
         }
 
         impl<
-            H: $h<R, G, L, E, S>,
+            H: $h<R, G, L, E, S, I>,
             R: $r,
-            G: $g<S, L, R>,
+            G: $g<S, I, L, R>,
             L: $l<S>,
             E: $e,
-            S: $s
+            S: $s,
+            I: $i
 
-        > Timer<H, R, G, L, E, S> {
+        > Timer<H, R, G, L, E, S, I> {
 
-            pub fn new() -> Timer<H, R, G, L, E, S> {
+            pub fn new() -> Timer<H, R, G, L, E, S, I> {
                 Timer {
                     callbacks: BinaryHeap::new(),
                     canceled: HashSet::new(),
@@ -44,7 +46,7 @@ macro_rules! impl_timer {
                 &mut self,
                 dt: u64
 
-            ) -> Vec<Box<FnMut(&mut H, Handle<H, R, G, L, E, S>)>> {
+            ) -> Vec<Box<FnMut(&mut H, Handle<H, R, G, L, E, S, I>)>> {
 
                 self.time += dt;
 
@@ -71,7 +73,7 @@ macro_rules! impl_timer {
 
             pub fn schedule(
                 &mut self,
-                f: Box<FnMut(&mut H, Handle<H, R, G, L, E, S>)>,
+                f: Box<FnMut(&mut H, Handle<H, R, G, L, E, S, I>)>,
                 time: u64
 
             ) -> TimerId {
@@ -94,69 +96,74 @@ macro_rules! impl_timer {
 
         // Timer Callback Wrapper ---------------------------------------------
         struct TimerCallback<
-            H: $h<R, G, L, E, S>,
+            H: $h<R, G, L, E, S, I>,
             R: $r,
-            G: $g<S, L, R>,
+            G: $g<S, I, L, R>,
             L: $l<S>,
             E: $e,
-            S: $s
+            S: $s,
+            I: $i
         > {
-            func: Box<FnMut(&mut H, Handle<H, R, G, L, E, S>)>,
+            func: Box<FnMut(&mut H, Handle<H, R, G, L, E, S, I>)>,
             time: u64,
             id: TimerId
         }
 
         impl<
-            H: $h<R, G, L, E, S>,
+            H: $h<R, G, L, E, S, I>,
             R: $r,
-            G: $g<S, L, R>,
+            G: $g<S, I, L, R>,
             L: $l<S>,
             E: $e,
-            S: $s
+            S: $s,
+            I: $i
 
-        > Eq for TimerCallback<H, R, G, L, E, S> {}
+        > Eq for TimerCallback<H, R, G, L, E, S, I> {}
 
         impl<
-            H: $h<R, G, L, E, S>,
+            H: $h<R, G, L, E, S, I>,
             R: $r,
-            G: $g<S, L, R>,
+            G: $g<S, I, L, R>,
             L: $l<S>,
             E: $e,
-            S: $s
+            S: $s,
+            I: $i
 
-        > PartialEq for TimerCallback<H, R, G, L, E, S> {
-            fn eq(&self, other: &TimerCallback<H, R, G, L, E, S>) -> bool {
+        > PartialEq for TimerCallback<H, R, G, L, E, S, I> {
+            fn eq(&self, other: &TimerCallback<H, R, G, L, E, S, I>) -> bool {
                 self.id == other.id
             }
         }
 
         impl<
-            H: $h<R, G, L, E, S>,
+            H: $h<R, G, L, E, S, I>,
             R: $r,
-            G: $g<S, L, R>,
+            G: $g<S, I, L, R>,
             L: $l<S>,
             E: $e,
-            S: $s
+            S: $s,
+            I: $i
 
-        > Ord for TimerCallback<H, R, G, L, E, S> {
+        > Ord for TimerCallback<H, R, G, L, E, S, I> {
             // Explicitly implement the trait so the queue becomes a min-heap
             // instead of a max-heap.
-            fn cmp(&self, other: &TimerCallback<H, R, G, L, E, S>) -> cmp::Ordering {
+            fn cmp(&self, other: &TimerCallback<H, R, G, L, E, S, I>) -> cmp::Ordering {
                 other.time.cmp(&self.time)
             }
         }
 
         impl<
-            H: $h<R, G, L, E, S>,
+            H: $h<R, G, L, E, S, I>,
             R: $r,
-            G: $g<S, L, R>,
+            G: $g<S, I, L, R>,
             L: $l<S>,
             E: $e,
-            S: $s
+            S: $s,
+            I: $i
 
-        > PartialOrd for TimerCallback<H, R, G, L, E, S> {
+        > PartialOrd for TimerCallback<H, R, G, L, E, S, I> {
             fn partial_cmp(
-                &self, other: &TimerCallback<H, R, G, L, E, S>
+                &self, other: &TimerCallback<H, R, G, L, E, S, I>
 
             ) -> Option<cmp::Ordering> {
                 Some(self.cmp(other))
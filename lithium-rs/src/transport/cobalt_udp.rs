@@ -0,0 +1,128 @@
+// External Dependencies ------------------------------------------------------
+use std::io::Error;
+use std::net::SocketAddr;
+use std::sync::mpsc::TryRecvError;
+use cobalt::{
+    Config,
+    Connection as CobaltConnection,
+    ConnectionID,
+    ConnectionMap,
+    ClientEvent,
+    ClientStream,
+    MessageKind,
+    Server as CobaltServer
+};
+
+
+// Internal Dependencies ------------------------------------------------------
+use super::{Connection, ConnectionSet, ServerTransport, ClientTransport};
+
+
+// UDP Server Transport -------------------------------------------------------
+impl Connection for CobaltConnection {
+
+    fn id(&self) -> ConnectionID {
+        CobaltConnection::id(self)
+    }
+
+    fn peer_addr(&self) -> SocketAddr {
+        CobaltConnection::peer_addr(self)
+    }
+
+    fn rtt(&self) -> u32 {
+        CobaltConnection::rtt(self)
+    }
+
+    fn send(&mut self, kind: MessageKind, data: Vec<u8>) {
+        CobaltConnection::send(self, kind, data);
+    }
+
+    fn received(&mut self) -> Vec<Vec<u8>> {
+        CobaltConnection::received(self).into_iter().collect()
+    }
+
+    fn close(&mut self) {
+        CobaltConnection::close(self);
+    }
+
+}
+
+impl ConnectionSet for ConnectionMap {
+
+    fn ids(&self) -> Vec<ConnectionID> {
+        self.keys().cloned().collect()
+    }
+
+    fn get_mut(&mut self, id: &ConnectionID) -> Option<&mut Connection> {
+        match ConnectionMap::get_mut(self, id) {
+            Some(conn) => Some(conn as &mut Connection),
+            None => None
+        }
+    }
+
+}
+
+impl ServerTransport for CobaltServer {
+    fn shutdown(&mut self) -> Result<(), Error> {
+        CobaltServer::shutdown(self)
+    }
+}
+
+
+// UDP Client Transport -------------------------------------------------------
+impl ClientTransport for ClientStream {
+
+    fn connect(&mut self, addr: SocketAddr) -> Result<(), Error> {
+        ClientStream::connect(self, addr)
+    }
+
+    fn receive(&mut self) -> Result<ClientEvent, TryRecvError> {
+        ClientStream::receive(self)
+    }
+
+    fn send(&mut self, kind: MessageKind, data: Vec<u8>) -> Result<(), Error> {
+        ClientStream::send(self, kind, data)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        ClientStream::flush(self)
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        ClientStream::reset(self)
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        ClientStream::close(self)
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        ClientStream::peer_addr(self)
+    }
+
+    fn config(&self) -> Config {
+        ClientStream::config(self)
+    }
+
+    fn set_config(&mut self, config: Config) {
+        ClientStream::set_config(self, config);
+    }
+
+    fn rtt(&self) -> u32 {
+        ClientStream::rtt(self)
+    }
+
+    fn packet_loss(&self) -> f32 {
+        ClientStream::packet_loss(self)
+    }
+
+    fn bytes_sent(&self) -> u32 {
+        ClientStream::bytes_sent(self)
+    }
+
+    fn bytes_received(&self) -> u32 {
+        ClientStream::bytes_received(self)
+    }
+
+}
+
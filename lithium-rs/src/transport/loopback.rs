@@ -0,0 +1,1119 @@
+// External Dependencies ------------------------------------------------------
+use std::cmp;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Error, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::mpsc::TryRecvError;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use cobalt::{Config, ConnectionID, ClientEvent, MessageKind};
+
+
+// Internal Dependencies ------------------------------------------------------
+use entity::{EntityState, EntityInput, EntityRegistry};
+use level::BaseLevel;
+use event::Event;
+use renderer::Renderer;
+use server::{Server, Handler};
+use super::{Connection, ConnectionSet, ServerTransport, ClientTransport};
+
+
+// Loopback Configuration -----------------------------------------------------
+#[derive(Debug, Copy, Clone)]
+pub struct LoopbackConfig {
+
+    // One way latency in milliseconds
+    pub latency: u32,
+
+    // Maximum additional random delay in milliseconds
+    pub jitter: u32,
+
+    // Probability of an instant message getting lost
+    pub loss: f32,
+
+    // Probability of an instant message getting delayed past its successors
+    pub reorder: f32,
+
+    pub seed: [u32; 4]
+
+}
+
+impl Default for LoopbackConfig {
+    fn default() -> LoopbackConfig {
+        LoopbackConfig {
+            latency: 0,
+            jitter: 0,
+            loss: 0.0,
+            reorder: 0.0,
+            seed: [0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb]
+        }
+    }
+}
+
+
+// Loopback Network -----------------------------------------------------------
+// Connects any number of clients with a single server in memory. Time only
+// passes when calling advance(), so for a given configuration and seed all
+// messages are delivered in the exact same order and at the exact same time.
+#[derive(Clone)]
+pub struct LoopbackNetwork {
+    state: Rc<RefCell<NetworkState>>
+}
+
+impl LoopbackNetwork {
+
+    pub fn new(addr: SocketAddr, config: LoopbackConfig) -> LoopbackNetwork {
+        LoopbackNetwork {
+            state: Rc::new(RefCell::new(NetworkState {
+                rng: XorShiftRng::from_seed(config.seed),
+                config: config,
+                addr: addr,
+                time: 0,
+                seq: 0,
+                id: 0,
+                peers: Vec::new()
+            }))
+        }
+    }
+
+    pub fn time(&self) -> u64 {
+        self.state.borrow().time
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.state.borrow_mut().time += ms;
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.state.borrow().addr
+    }
+
+    pub fn config(&self) -> LoopbackConfig {
+        self.state.borrow().config
+    }
+
+    pub fn set_config(&self, config: LoopbackConfig) {
+        self.state.borrow_mut().config = config;
+    }
+
+    pub fn client(&self, config: Config) -> LoopbackClient {
+        LoopbackClient {
+            network: self.clone(),
+            config: config,
+            peer: None,
+            connected: false,
+            failed: false,
+            incoming: VecDeque::new(),
+            last_tick: 0,
+            bytes_sent: 0,
+            bytes_received: 0
+        }
+    }
+
+    pub fn server(&self) -> LoopbackServer {
+        LoopbackServer {
+            network: self.clone(),
+            transport: LoopbackTransport {
+                running: true
+            },
+            connections: HashMap::new(),
+            bound: false,
+            last_tick: 0
+        }
+    }
+
+
+    // Internal ---------------------------------------------------------------
+    fn connect(&self) -> ConnectionID {
+        let mut state = self.state.borrow_mut();
+        let id = ConnectionID(state.id);
+        let time = state.time;
+        state.id = state.id.wrapping_add(1);
+        state.peers.push(Peer {
+            id: id,
+            status: PeerStatus::Connecting(time),
+            to_server: Link::new(),
+            to_client: Link::new(),
+            sent: 0,
+            lost: 0
+        });
+        id
+    }
+
+    fn accept(&self) -> Vec<LoopbackConnection> {
+
+        let mut state = self.state.borrow_mut();
+        let latency = state.config.latency as u64;
+        let time = state.time;
+
+        let mut accepted = Vec::new();
+        for peer in &mut state.peers {
+            if let PeerStatus::Connecting(t) = peer.status {
+                if t + latency <= time {
+                    peer.status = PeerStatus::Connected;
+                    accepted.push(LoopbackConnection {
+                        network: self.clone(),
+                        id: peer.id
+                    });
+                }
+            }
+        }
+
+        accepted
+
+    }
+
+    fn lost(&self) -> Vec<ConnectionID> {
+        let mut state = self.state.borrow_mut();
+        let lost = state.peers.iter().filter(|p| {
+            p.status == PeerStatus::ClosedByClient
+
+        }).map(|p| p.id).collect();
+
+        state.peers.retain(|p| p.status != PeerStatus::ClosedByClient);
+        lost
+    }
+
+    fn status(&self, id: &ConnectionID) -> Option<PeerStatus> {
+        self.state.borrow().peers.iter().find(|p| p.id == *id).map(|p| p.status)
+    }
+
+    fn close(&self, id: &ConnectionID, status: PeerStatus) {
+        let mut state = self.state.borrow_mut();
+        if let Some(peer) = state.peers.iter_mut().find(|p| p.id == *id) {
+            peer.status = status;
+        }
+    }
+
+    fn remove(&self, id: &ConnectionID) {
+        self.state.borrow_mut().peers.retain(|p| p.id != *id);
+    }
+
+    fn send(&self, id: &ConnectionID, to_server: bool, kind: MessageKind, data: Vec<u8>) {
+
+        let mut state = self.state.borrow_mut();
+        let NetworkState {
+            ref config, ref mut rng, ref mut peers, ref mut seq, time, ..
+        } = *state;
+
+        if let Some(peer) = peers.iter_mut().find(|p| p.id == *id) {
+
+            let mut delay = config.latency as u64;
+            if config.jitter > 0 {
+                delay += rng.gen_range(0, config.jitter as u64 + 1);
+            }
+
+            let ordered = match kind {
+                MessageKind::Instant => {
+
+                    peer.sent += 1;
+
+                    // Only instant messages are unreliable
+                    if rng.gen::<f32>() < config.loss {
+                        peer.lost += 1;
+                        return;
+                    }
+
+                    if rng.gen::<f32>() < config.reorder {
+                        delay += config.latency as u64 + config.jitter as u64 + 1;
+                    }
+
+                    false
+
+                },
+                _ => true
+            };
+
+            let link = if to_server {
+                &mut peer.to_server
+
+            } else {
+                &mut peer.to_client
+            };
+
+            link.push(time + delay, *seq, ordered, data);
+            *seq += 1;
+
+        }
+
+    }
+
+    fn receive(&self, id: &ConnectionID, to_server: bool) -> Vec<Vec<u8>> {
+        let mut state = self.state.borrow_mut();
+        let time = state.time;
+        match state.peers.iter_mut().find(|p| p.id == *id) {
+            Some(peer) => if to_server {
+                peer.to_server.receive(time)
+
+            } else {
+                peer.to_client.receive(time)
+            },
+            None => Vec::new()
+        }
+    }
+
+    fn packet_loss(&self, id: &ConnectionID) -> f32 {
+        match self.state.borrow().peers.iter().find(|p| p.id == *id) {
+            Some(peer) if peer.sent > 0 => peer.lost as f32 / peer.sent as f32,
+            _ => 0.0
+        }
+    }
+
+    // Both directions are delayed by half of the maximum jitter on average
+    fn rtt(&self) -> u32 {
+        let config = self.state.borrow().config;
+        config.latency * 2 + config.jitter
+    }
+
+}
+
+
+// Loopback Client Transport --------------------------------------------------
+pub struct LoopbackClient {
+    network: LoopbackNetwork,
+    config: Config,
+    peer: Option<ConnectionID>,
+    connected: bool,
+    failed: bool,
+    incoming: VecDeque<Vec<u8>>,
+    last_tick: u64,
+    bytes_sent: u32,
+    bytes_received: u32
+}
+
+impl LoopbackClient {
+
+    fn detach(&mut self) {
+        self.peer = None;
+        self.connected = false;
+        self.incoming.clear();
+    }
+
+    fn tick_interval(&self) -> u64 {
+        1000 / cmp::max(self.config.send_rate as u64, 1)
+    }
+
+}
+
+impl ClientTransport for LoopbackClient {
+
+    fn connect(&mut self, addr: SocketAddr) -> Result<(), Error> {
+        if self.peer.is_some() {
+            Err(Error::new(ErrorKind::AlreadyExists, "Client already connected."))
+
+        } else if addr != self.network.addr() {
+            self.failed = true;
+            Ok(())
+
+        } else {
+            self.peer = Some(self.network.connect());
+            Ok(())
+        }
+    }
+
+    fn receive(&mut self) -> Result<ClientEvent, TryRecvError> {
+
+        if self.failed {
+            self.failed = false;
+            return Ok(ClientEvent::ConnectionFailed);
+        }
+
+        let id = match self.peer {
+            Some(id) => id,
+            None => return Err(TryRecvError::Empty)
+        };
+
+        match self.network.status(&id) {
+            Some(PeerStatus::Connected) => {
+                if !self.connected {
+                    self.connected = true;
+                    self.last_tick = self.network.time();
+                    return Ok(ClientEvent::Connection);
+                }
+            },
+            Some(PeerStatus::ClosedByServer) => {
+                self.network.remove(&id);
+                self.detach();
+                return Ok(ClientEvent::ConnectionClosed(true));
+            },
+            Some(_) => return Err(TryRecvError::Empty),
+            None => {
+                self.detach();
+                return Ok(ClientEvent::ConnectionLost);
+            }
+        }
+
+        if self.incoming.is_empty() {
+            self.incoming.extend(self.network.receive(&id, false));
+        }
+
+        if let Some(data) = self.incoming.pop_front() {
+            self.bytes_received += data.len() as u32;
+            return Ok(ClientEvent::Message(data));
+        }
+
+        let interval = self.tick_interval();
+        if self.last_tick + interval <= self.network.time() {
+            self.last_tick += interval;
+            Ok(ClientEvent::Tick)
+
+        } else {
+            Err(TryRecvError::Empty)
+        }
+
+    }
+
+    fn send(&mut self, kind: MessageKind, data: Vec<u8>) -> Result<(), Error> {
+        if let (Some(id), true) = (self.peer, self.connected) {
+            self.bytes_sent += data.len() as u32;
+            self.network.send(&id, true, kind, data);
+            Ok(())
+
+        } else {
+            Err(Error::new(ErrorKind::NotConnected, "Client is not connected."))
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.close().ok();
+        self.bytes_sent = 0;
+        self.bytes_received = 0;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        if let Some(id) = self.peer {
+            self.network.close(&id, PeerStatus::ClosedByClient);
+            self.detach();
+            Ok(())
+
+        } else {
+            Err(Error::new(ErrorKind::NotConnected, "Client is not connected."))
+        }
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        if self.peer.is_some() {
+            Ok(self.network.addr())
+
+        } else {
+            Err(Error::new(ErrorKind::NotConnected, "Client is not connected."))
+        }
+    }
+
+    fn config(&self) -> Config {
+        self.config
+    }
+
+    fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    fn rtt(&self) -> u32 {
+        self.network.rtt()
+    }
+
+    fn packet_loss(&self) -> f32 {
+        match self.peer {
+            Some(id) => self.network.packet_loss(&id),
+            None => 0.0
+        }
+    }
+
+    fn bytes_sent(&self) -> u32 {
+        self.bytes_sent
+    }
+
+    fn bytes_received(&self) -> u32 {
+        self.bytes_received
+    }
+
+}
+
+
+// Loopback Server Transport --------------------------------------------------
+pub struct LoopbackServer {
+    network: LoopbackNetwork,
+    transport: LoopbackTransport,
+    connections: HashMap<ConnectionID, LoopbackConnection>,
+    bound: bool,
+    last_tick: u64
+}
+
+impl LoopbackServer {
+
+    pub fn running(&self) -> bool {
+        self.transport.running
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.len()
+    }
+
+    // Accepts and drops connections and runs all server ticks which are due
+    // at the network's current time
+    pub fn step<
+        H: Handler<R, G, L, E, S, I>, R: Renderer,
+        G: EntityRegistry<S, I, L, R>, L: BaseLevel<S>, E: Event, S: EntityState, I: EntityInput
+
+    >(&mut self, server: &mut Server<H, R, G, L, E, S, I>) -> bool {
+
+        if !self.transport.running {
+            return false;
+        }
+
+        if !self.bound {
+            self.bound = true;
+            self.last_tick = self.network.time();
+            server.on_bind(&mut self.transport);
+        }
+
+        for conn in self.network.accept() {
+            let id = conn.id;
+            self.connections.insert(id, conn);
+            if let Some(conn) = self.connections.get_mut(&id) {
+                server.on_connection(&mut self.transport, conn);
+            }
+        }
+
+        for id in self.network.lost() {
+            if let Some(mut conn) = self.connections.remove(&id) {
+                server.on_connection_lost(&mut self.transport, &mut conn);
+            }
+        }
+
+        let interval = 1000 / cmp::max(server.config().tick_rate as u64, 1);
+        while self.transport.running && self.last_tick + interval <= self.network.time() {
+
+            self.last_tick += interval;
+            server.on_tick(&mut self.transport, &mut self.connections);
+
+            // Drop connections which were closed by the server
+            let network = &self.network;
+            let closed: Vec<ConnectionID> = self.connections.keys().filter(|id| {
+                network.status(id) != Some(PeerStatus::Connected)
+
+            }).cloned().collect();
+
+            for id in closed {
+                self.connections.remove(&id);
+            }
+
+        }
+
+        if !self.transport.running {
+            server.on_shutdown(&mut self.transport);
+            for conn in self.connections.values_mut() {
+                conn.close();
+            }
+            self.connections.clear();
+        }
+
+        self.transport.running
+
+    }
+
+}
+
+struct LoopbackTransport {
+    running: bool
+}
+
+impl ServerTransport for LoopbackTransport {
+    fn shutdown(&mut self) -> Result<(), Error> {
+        if self.running {
+            self.running = false;
+            Ok(())
+
+        } else {
+            Err(Error::new(ErrorKind::NotConnected, "Server is not running."))
+        }
+    }
+}
+
+
+// Loopback Server Connection -------------------------------------------------
+pub struct LoopbackConnection {
+    network: LoopbackNetwork,
+    id: ConnectionID
+}
+
+impl Connection for LoopbackConnection {
+
+    fn id(&self) -> ConnectionID {
+        self.id
+    }
+
+    fn peer_addr(&self) -> SocketAddr {
+        SocketAddr::V4(SocketAddrV4::new(
+            Ipv4Addr::new(127, 0, 0, 1),
+            (1024 + self.id.0 % 64511) as u16
+        ))
+    }
+
+    fn rtt(&self) -> u32 {
+        self.network.rtt()
+    }
+
+    fn send(&mut self, kind: MessageKind, data: Vec<u8>) {
+        self.network.send(&self.id, false, kind, data);
+    }
+
+    fn received(&mut self) -> Vec<Vec<u8>> {
+        self.network.receive(&self.id, true)
+    }
+
+    fn close(&mut self) {
+        self.network.close(&self.id, PeerStatus::ClosedByServer);
+    }
+
+}
+
+impl ConnectionSet for HashMap<ConnectionID, LoopbackConnection> {
+
+    fn ids(&self) -> Vec<ConnectionID> {
+        // Iterate in a stable order to keep things deterministic
+        let mut ids: Vec<ConnectionID> = self.keys().cloned().collect();
+        ids.sort_by(|a, b| a.0.cmp(&b.0));
+        ids
+    }
+
+    fn get_mut(&mut self, id: &ConnectionID) -> Option<&mut Connection> {
+        match HashMap::get_mut(self, id) {
+            Some(conn) => Some(conn as &mut Connection),
+            None => None
+        }
+    }
+
+}
+
+
+// Internal State -------------------------------------------------------------
+struct NetworkState {
+    config: LoopbackConfig,
+    rng: XorShiftRng,
+    addr: SocketAddr,
+    time: u64,
+    seq: u64,
+    id: u32,
+    peers: Vec<Peer>
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum PeerStatus {
+    Connecting(u64),
+    Connected,
+    ClosedByClient,
+    ClosedByServer
+}
+
+struct Peer {
+    id: ConnectionID,
+    status: PeerStatus,
+    to_server: Link,
+    to_client: Link,
+    sent: u32,
+    lost: u32
+}
+
+struct Packet {
+    time: u64,
+    seq: u64,
+    data: Vec<u8>
+}
+
+struct Link {
+    packets: Vec<Packet>,
+    last_ordered: u64
+}
+
+impl Link {
+
+    fn new() -> Link {
+        Link {
+            packets: Vec::new(),
+            last_ordered: 0
+        }
+    }
+
+    fn push(&mut self, time: u64, seq: u64, ordered: bool, data: Vec<u8>) {
+
+        // Reliable messages never overtake each other
+        let time = if ordered {
+            self.last_ordered = cmp::max(time, self.last_ordered);
+            self.last_ordered
+
+        } else {
+            time
+        };
+
+        self.packets.push(Packet {
+            time: time,
+            seq: seq,
+            data: data
+        });
+
+    }
+
+    fn receive(&mut self, time: u64) -> Vec<Vec<u8>> {
+
+        let mut due = Vec::new();
+        let mut pending = Vec::new();
+        for packet in self.packets.drain(..) {
+            if packet.time <= time {
+                due.push(packet);
+
+            } else {
+                pending.push(packet);
+            }
+        }
+
+        self.packets = pending;
+
+        due.sort_by(|a, b| (a.time, a.seq).cmp(&(b.time, b.seq)));
+        due.into_iter().map(|p| p.data).collect()
+
+    }
+
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use std::{env, fs};
+    use std::io::Error;
+    use std::path::Path;
+    use std::collections::HashSet;
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+    use cobalt::{Config, ConnectionID};
+
+    use mock;
+    use mock::{MockEvent, MockInput, MockLevel, MockRegistry, MockState};
+    use client;
+    use server;
+    use client::Client;
+    use server::Server;
+    use entity::{Entity, EntityInput, EntityState};
+    use event::EventTarget;
+    use level::Level;
+    use network::{DecodeError, Incompatibility};
+    use renderer::DefaultRenderer;
+    use replay::{Replay, ReplayRecord};
+    use transport::{Connection, ConnectionSet};
+    use super::{LoopbackConfig, LoopbackNetwork, LoopbackServer};
+
+    type TestServer = Server<
+        ServerHandler, DefaultRenderer, MockRegistry, MockLevel, MockEvent, MockState, MockInput
+    >;
+
+    type TestClient = Client<
+        ClientHandler, DefaultRenderer, MockRegistry, MockLevel, MockEvent, MockState, MockInput
+    >;
+
+    type ServerHandle<'a> = server::Handle<
+        'a, ServerHandler, DefaultRenderer, MockRegistry, MockLevel, MockEvent, MockState, MockInput
+    >;
+
+    type ClientHandle<'a> = client::Handle<
+        'a, ClientHandler, DefaultRenderer, MockRegistry, MockLevel, MockEvent, MockState, MockInput
+    >;
+
+    type TestEntity = Entity<MockState, MockInput, MockLevel, DefaultRenderer>;
+
+    const TICK_RATE: u8 = 30;
+
+
+    // Observations shared between the test and the handlers
+    #[derive(Default)]
+    struct Observed {
+        server_x: Option<f32>,
+        client_x: Option<f32>,
+        moving_ticks: u32,
+        sent_moves: u32,
+        predicted_ahead: bool,
+        server_push: f32,
+        max_misprediction: f32,
+        pending_events: Vec<MockEvent>,
+        server_events: Vec<MockEvent>,
+        client_events: Vec<MockEvent>,
+        invalid_messages: u32,
+        replay_errors: u32
+    }
+
+    struct ServerHandler {
+        observed: Rc<RefCell<Observed>>
+    }
+
+    impl server::Handler<
+        DefaultRenderer, MockRegistry, MockLevel, MockEvent, MockState, MockInput
+
+    > for ServerHandler {
+
+        fn bind(&mut self, _: ServerHandle) {}
+
+        fn connect(&mut self, handle: ServerHandle, conn: &mut Connection) {
+            handle.entities.create(0, Some(MockState::new(0.0, 0.0)), Some(&conn.id()));
+        }
+
+        fn disconnect(&mut self, _: ServerHandle, _: &mut Connection) {}
+
+        // Echo all events back to their sender
+        fn event(
+            &mut self, handle: ServerHandle, _: &mut ConnectionSet, owner: ConnectionID, event: MockEvent
+        ) {
+            self.observed.borrow_mut().server_events.push(event.clone());
+            handle.events.send_to(EventTarget::Connection(owner), event);
+        }
+
+        fn invalid_message(&mut self, _: ServerHandle, _: &mut Connection, _: DecodeError) {
+            self.observed.borrow_mut().invalid_messages += 1;
+        }
+
+        fn replay_error(&mut self, _: ServerHandle, _: Error) {
+            self.observed.borrow_mut().replay_errors += 1;
+        }
+
+        fn tick_before(&mut self, _: ServerHandle, _: &mut ConnectionSet) {}
+
+        fn tick_entity_before(
+            &mut self, _: &Level<MockState, MockLevel>, _: &mut TestEntity, _: u32, _: f32
+        ) {}
+
+        fn tick_entity_after(
+            &mut self, _: &Level<MockState, MockLevel>, entity: &mut TestEntity, _: u32, _: f32
+        ) {
+
+            // Moves the entity without the client knowing about it
+            let mut observed = self.observed.borrow_mut();
+            if observed.server_push != 0.0 {
+                let mut state = entity.state().clone();
+                state.x += observed.server_push;
+                entity.set_state(state);
+                observed.server_push = 0.0;
+            }
+
+            observed.server_x = Some(entity.state().x);
+
+        }
+
+        fn collision(&mut self, _: ServerHandle, _: u16, _: u16) {}
+
+        fn tick_after(&mut self, _: ServerHandle, _: &mut ConnectionSet) {}
+
+        fn shutdown(&mut self, _: ServerHandle) {}
+
+    }
+
+    struct ClientHandler {
+        addr: SocketAddr,
+        observed: Rc<RefCell<Observed>>
+    }
+
+    impl client::Handler<
+        DefaultRenderer, MockRegistry, MockLevel, MockEvent, MockState, MockInput
+
+    > for ClientHandler {
+
+        fn init(&mut self, handle: ClientHandle) {
+            handle.client.connect(self.addr).unwrap();
+        }
+
+        fn connect(&mut self, _: ClientHandle) {}
+
+        fn disconnect(&mut self, _: ClientHandle, _: bool, _: bool) {}
+
+        fn config(&mut self, _: ClientHandle, _: &[u8]) {}
+
+        fn incompatible(&mut self, _: ClientHandle, _: Incompatibility) {}
+
+        fn invalid_message(&mut self, _: ClientHandle, _: DecodeError) {
+            self.observed.borrow_mut().invalid_messages += 1;
+        }
+
+        fn event(&mut self, _: ClientHandle, _: ConnectionID, event: MockEvent) {
+            self.observed.borrow_mut().client_events.push(event);
+        }
+
+        fn tick_before(&mut self, handle: ClientHandle) {
+            for event in self.observed.borrow_mut().pending_events.drain(..) {
+                handle.events.send(event);
+            }
+        }
+
+        // Moves the local entity by one unit per tick while the test wants
+        // it to, afterwards it keeps sending inputs which do not move it
+        fn tick_entity_before(
+            &mut self,
+            _: &mut DefaultRenderer,
+            _: &Level<MockState, MockLevel>,
+            entity: &mut TestEntity,
+            tick: u32,
+            _: f32
+        ) {
+            if entity.local() {
+                let mut observed = self.observed.borrow_mut();
+                let dx = if observed.moving_ticks > 0 {
+                    observed.moving_ticks -= 1;
+                    observed.sent_moves += 1;
+                    1
+
+                } else {
+                    0
+                };
+
+                entity.local_input(MockInput {
+                    tick: tick as u8,
+                    dx: dx
+                });
+            }
+        }
+
+        fn tick_entity_after(
+            &mut self,
+            _: &mut DefaultRenderer,
+            _: &Level<MockState, MockLevel>,
+            entity: &mut TestEntity,
+            _: u32,
+            _: f32
+        ) {
+            if entity.local() {
+                let mut observed = self.observed.borrow_mut();
+                let x = entity.state().x;
+                if observed.server_x.map_or(false, |server_x| x > server_x) {
+                    observed.predicted_ahead = true;
+                }
+                observed.client_x = Some(x);
+                observed.max_misprediction = observed.max_misprediction.max(
+                    entity.misprediction()
+                );
+            }
+        }
+
+        fn tick_after(&mut self, _: ClientHandle) {}
+
+        fn draw(&mut self, _: ClientHandle) {}
+
+        fn destroy(&mut self, _: ClientHandle) {}
+
+    }
+
+
+    // Harness ----------------------------------------------------------------
+    struct Harness {
+        network: LoopbackNetwork,
+        transport: LoopbackServer,
+        server: TestServer,
+        client: TestClient,
+        renderer: DefaultRenderer,
+        observed: Rc<RefCell<Observed>>
+    }
+
+    impl Harness {
+
+        fn new(config: LoopbackConfig) -> Harness {
+
+            let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 7156));
+            let network = LoopbackNetwork::new(addr, config);
+            let observed = Rc::new(RefCell::new(Observed::default()));
+
+            let server = Server::new(
+                TICK_RATE as u32, 1000, 75,
+                mock::level(),
+                MockRegistry,
+                ServerHandler {
+                    observed: observed.clone()
+                }
+            );
+
+            let mut client = Client::new(
+                TICK_RATE,
+                mock::level(),
+                MockRegistry,
+                ClientHandler {
+                    addr: addr,
+                    observed: observed.clone()
+                }
+            );
+
+            client.set_transport(Box::new(network.client(Config::default())));
+
+            let mut renderer = DefaultRenderer;
+            client.init(&mut renderer);
+
+            Harness {
+                transport: network.server(),
+                network: network,
+                server: server,
+                client: client,
+                renderer: renderer,
+                observed: observed
+            }
+
+        }
+
+        fn run(&mut self, ms: u64) {
+            for _ in 0..ms {
+                self.network.advance(1);
+                self.transport.step(&mut self.server);
+                self.client.tick(&mut self.renderer);
+            }
+        }
+
+        // Moves the entity for the given number of ticks, pushing it on the
+        // server halfway through, and then waits for the client and server to
+        // settle
+        fn move_and_settle(&mut self, ticks: u32, push: f32) -> (f32, f32) {
+            let duration = ticks as u64 * 1000 / TICK_RATE as u64;
+            self.run(2000);
+            self.observed.borrow_mut().moving_ticks = ticks;
+            self.run(duration / 2);
+            self.observed.borrow_mut().server_push = push;
+            self.run(duration / 2 + 5000);
+            let observed = self.observed.borrow();
+            (observed.client_x.unwrap(), observed.server_x.unwrap())
+        }
+
+    }
+
+    fn lossy() -> LoopbackConfig {
+        LoopbackConfig {
+            latency: 40,
+            jitter: 20,
+            loss: 0.1,
+            reorder: 0.1,
+            .. LoopbackConfig::default()
+        }
+    }
+
+
+    // Tests ------------------------------------------------------------------
+    #[test]
+    fn test_prediction() {
+
+        let mut harness = Harness::new(LoopbackConfig {
+            latency: 50,
+            .. LoopbackConfig::default()
+        });
+
+        let (client_x, server_x) = harness.move_and_settle(90, 0.0);
+
+        // Without any loss the server applies every single input, while the
+        // client is ahead of the server during the movement
+        let observed = harness.observed.borrow();
+        assert_eq!(observed.sent_moves, 90);
+        assert_eq!(server_x, 90.0);
+        assert_eq!(client_x, 90.0);
+        assert!(observed.predicted_ahead);
+        assert_eq!(observed.invalid_messages, 0);
+
+    }
+
+    #[test]
+    fn test_reconciliation() {
+
+        let mut harness = Harness::new(lossy());
+        let (client_x, server_x) = harness.move_and_settle(300, 50.0);
+
+        // The client mispredicts the push and must end up at the server's
+        // state once all inputs were confirmed
+        let observed = harness.observed.borrow();
+        assert_eq!(server_x, 350.0);
+        assert_eq!(client_x, server_x);
+        assert!(observed.max_misprediction >= 50.0);
+        assert_eq!(observed.invalid_messages, 0);
+
+    }
+
+    #[test]
+    fn test_deterministic() {
+
+        let mut a = Harness::new(lossy());
+        let mut b = Harness::new(lossy());
+        assert_eq!(a.move_and_settle(300, 50.0), b.move_and_settle(300, 50.0));
+        assert_eq!(a.network.time(), b.network.time());
+
+    }
+
+    #[test]
+    fn test_event_delivery() {
+
+        let mut harness = Harness::new(lossy());
+        harness.run(2000);
+
+        for i in 0..10 {
+            harness.observed.borrow_mut().pending_events.push(MockEvent {
+                name: "ping".to_string(),
+                value: i
+            });
+        }
+
+        harness.run(2000);
+
+        // Events are delivered exactly once and in order in both directions
+        let observed = harness.observed.borrow();
+        let values: Vec<u32> = (0..10).collect();
+        assert_eq!(observed.server_events.iter().map(|e| e.value).collect::<Vec<u32>>(), values);
+        assert_eq!(observed.client_events.iter().map(|e| e.value).collect::<Vec<u32>>(), values);
+
+    }
+
+    // Writes to /dev/full always fail once the buffered replay gets flushed
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_replay_error() {
+
+        let mut harness = Harness::new(LoopbackConfig::default());
+        harness.server.record(Path::new("/dev/full")).unwrap();
+        harness.run(3000);
+
+        // Recording stops after the first error
+        assert_eq!(harness.observed.borrow().replay_errors, 1);
+
+    }
+
+    // Clients re-send their unconfirmed inputs with every message, but each
+    // of them is only applied and recorded once
+    #[test]
+    fn test_replay_inputs() {
+
+        let path = env::temp_dir().join("lithium_test_replay_inputs.lrp");
+        let mut harness = Harness::new(LoopbackConfig::default());
+        harness.server.record(&path).unwrap();
+        harness.observed.borrow_mut().moving_ticks = 60;
+        harness.run(3000);
+
+        let replay = Replay::open(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        let mut recorded = HashSet::new();
+        for record in replay.frames().iter().flat_map(|frame| frame.iter()) {
+            if let ReplayRecord::Input(owner, entity_id, ref data) = *record {
+                let input = MockInput::from_serialized(data).unwrap();
+                assert!(recorded.insert((owner, entity_id, input.tick)));
+            }
+        }
+
+        assert!(recorded.len() > 30);
+        assert_eq!(harness.observed.borrow().replay_errors, 0);
+
+    }
+
+    #[test]
+    fn test_rtt_includes_jitter() {
+        let network = LoopbackNetwork::new(
+            SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 7156)),
+            lossy()
+        );
+        assert_eq!(network.rtt(), 100);
+    }
+
+}
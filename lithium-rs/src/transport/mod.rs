@@ -0,0 +1,80 @@
+// External Dependencies ------------------------------------------------------
+use std::io::Error;
+use std::net::SocketAddr;
+use std::sync::mpsc::TryRecvError;
+use cobalt::{Config, ConnectionID, ClientEvent, MessageKind};
+
+
+// Internal Dependencies ------------------------------------------------------
+mod cobalt_udp;
+pub mod loopback;
+pub mod replay;
+
+
+// Server Side Connection -----------------------------------------------------
+pub trait Connection {
+
+    fn id(&self) -> ConnectionID;
+
+    fn peer_addr(&self) -> SocketAddr;
+
+    // Round trip time in milliseconds
+    fn rtt(&self) -> u32;
+
+    fn send(&mut self, kind: MessageKind, data: Vec<u8>);
+
+    // Returns all messages received since the last call
+    fn received(&mut self) -> Vec<Vec<u8>>;
+
+    fn close(&mut self);
+
+}
+
+
+// Server Side Connection Set -------------------------------------------------
+pub trait ConnectionSet {
+
+    fn ids(&self) -> Vec<ConnectionID>;
+
+    fn get_mut(&mut self, id: &ConnectionID) -> Option<&mut Connection>;
+
+}
+
+
+// Server Transport -----------------------------------------------------------
+pub trait ServerTransport {
+    fn shutdown(&mut self) -> Result<(), Error>;
+}
+
+
+// Client Transport -----------------------------------------------------------
+pub trait ClientTransport {
+
+    fn connect(&mut self, addr: SocketAddr) -> Result<(), Error>;
+
+    fn receive(&mut self) -> Result<ClientEvent, TryRecvError>;
+
+    fn send(&mut self, kind: MessageKind, data: Vec<u8>) -> Result<(), Error>;
+
+    fn flush(&mut self) -> Result<(), Error>;
+
+    fn reset(&mut self) -> Result<(), Error>;
+
+    fn close(&mut self) -> Result<(), Error>;
+
+    fn peer_addr(&self) -> Result<SocketAddr, Error>;
+
+    fn config(&self) -> Config;
+
+    fn set_config(&mut self, config: Config);
+
+    fn rtt(&self) -> u32;
+
+    fn packet_loss(&self) -> f32;
+
+    fn bytes_sent(&self) -> u32;
+
+    fn bytes_received(&self) -> u32;
+
+}
+
@@ -0,0 +1,397 @@
+// External Dependencies ------------------------------------------------------
+use std::cmp;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::mpsc::TryRecvError;
+use cobalt::{Config, ClientEvent, MessageKind};
+
+
+// Internal Dependencies ------------------------------------------------------
+use network;
+use clock::SyncHeader;
+use entity::EntityHandle;
+use replay::{Replay, ReplayRecord};
+use super::ClientTransport;
+
+
+// Replay Player --------------------------------------------------------------
+// Plays back a recorded replay by feeding the recorded states and events
+// into a client as if they were received from a server. Playback only
+// advances when calling advance(), which allows for pausing and changing the
+// speed.
+#[derive(Clone)]
+pub struct ReplayPlayer {
+    state: Rc<RefCell<PlaybackState>>
+}
+
+impl ReplayPlayer {
+
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        let tick_rate = replay.tick_rate().unwrap_or(30);
+        ReplayPlayer {
+            state: Rc::new(RefCell::new(PlaybackState {
+                replay: replay,
+                tick_rate: cmp::max(tick_rate, 1),
+                frame: 0,
+                time: 0.0,
+                speed: 1.0,
+                paused: false,
+                connecting: false,
+                connected: false,
+                spawned: HashMap::new(),
+                incoming: VecDeque::new()
+            }))
+        }
+    }
+
+    pub fn transport(&self) -> ReplayTransport {
+        ReplayTransport {
+            player: self.clone(),
+            config: Config::default(),
+            bytes_received: 0
+        }
+    }
+
+    pub fn tick_rate(&self) -> u8 {
+        self.state.borrow().tick_rate
+    }
+
+    pub fn frame(&self) -> usize {
+        self.state.borrow().frame
+    }
+
+    pub fn frames(&self) -> usize {
+        self.state.borrow().replay.frames().len()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.frame() >= self.frames()
+    }
+
+    pub fn paused(&self) -> bool {
+        self.state.borrow().paused
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.state.borrow_mut().paused = paused;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.state.borrow().speed
+    }
+
+    pub fn set_speed(&self, speed: f32) {
+        self.state.borrow_mut().speed = speed.max(0.0);
+    }
+
+    // Advances the playback time by the given number of milliseconds, scaled
+    // by the current speed
+    pub fn advance(&self, ms: f32) {
+        let mut state = self.state.borrow_mut();
+        if !state.paused {
+            state.time += (ms * state.speed) as f64;
+        }
+    }
+
+    // Jumps to the given frame, events from skipped frames are still
+    // delivered so the receiving side can rebuild its state from them. When
+    // seeking backwards, all events since the start of the replay are
+    // delivered again.
+    pub fn seek(&self, frame: usize) {
+
+        let mut state = self.state.borrow_mut();
+        let frame = cmp::min(frame, state.replay.frames().len());
+        let from = if frame < state.frame {
+            0
+
+        } else {
+            state.frame
+        };
+
+        let mut skipped = Vec::new();
+        for records in &state.replay.frames()[from..frame] {
+            for record in records {
+                if let ReplayRecord::Events(ref data) = *record {
+                    skipped.push(message(network::Message::ServerEvents, data));
+                }
+            }
+        }
+
+        state.frame = frame;
+        state.time = 0.0;
+
+        // Entities are re-synchronized before the skipped events so they can
+        // refer to them
+        if state.connected {
+            if let Some(entities) = state.sync_entities() {
+                state.incoming.push_back(ClientEvent::Message(entities));
+            }
+            state.incoming.extend(skipped.into_iter().map(ClientEvent::Message));
+        }
+
+    }
+
+}
+
+
+// Replay Client Transport ----------------------------------------------------
+pub struct ReplayTransport {
+    player: ReplayPlayer,
+    config: Config,
+    bytes_received: u32
+}
+
+impl ClientTransport for ReplayTransport {
+
+    fn connect(&mut self, _: SocketAddr) -> Result<(), Error> {
+        let mut state = self.player.state.borrow_mut();
+        if state.connecting || state.connected {
+            Err(Error::new(ErrorKind::AlreadyExists, "Replay is already playing."))
+
+        } else {
+            state.connecting = true;
+            Ok(())
+        }
+    }
+
+    fn receive(&mut self) -> Result<ClientEvent, TryRecvError> {
+
+        let mut state = self.player.state.borrow_mut();
+
+        // The recorded config is delivered right after connecting
+        if state.connecting {
+            let config = message(network::Message::ServerConfig, state.replay.config());
+            state.connecting = false;
+            state.connected = true;
+            state.time = 0.0;
+            state.spawned.clear();
+            state.incoming.push_back(ClientEvent::Message(config));
+            if let Some(entities) = state.sync_entities() {
+                state.incoming.push_back(ClientEvent::Message(entities));
+            }
+            return Ok(ClientEvent::Connection);
+        }
+
+        if let Some(event) = state.incoming.pop_front() {
+            if let ClientEvent::Message(ref data) = event {
+                self.bytes_received += data.len() as u32;
+            }
+            return Ok(event);
+        }
+
+        // Each recorded frame is delivered as a single tick
+        let interval = 1000.0 / state.tick_rate as f64;
+        if !state.connected || state.frame >= state.replay.frames().len() || state.time < interval {
+            return Err(TryRecvError::Empty);
+        }
+
+        let mut incoming = Vec::new();
+        let frame = state.frame;
+        if let Some(entities) = state.frame_entities(frame) {
+            incoming.push(entities);
+        }
+
+        for record in &state.replay.frames()[state.frame] {
+            match *record {
+                // Recorded states never carry clock sync information
+                ReplayRecord::State(ref data) => {
+                    let mut payload = SyncHeader::serialize(None);
+                    payload.extend_from_slice(data);
+                    incoming.push(message(network::Message::ServerState, &payload));
+                },
+                ReplayRecord::Events(ref data) => {
+                    incoming.push(message(network::Message::ServerEvents, data));
+                },
+                _ => {}
+            }
+        }
+
+        state.incoming.extend(incoming.into_iter().map(ClientEvent::Message));
+        state.incoming.push_back(ClientEvent::Tick);
+        state.frame += 1;
+        state.time -= interval;
+
+        match state.incoming.pop_front() {
+            Some(ClientEvent::Message(data)) => {
+                self.bytes_received += data.len() as u32;
+                Ok(ClientEvent::Message(data))
+            },
+            Some(event) => Ok(event),
+            None => Err(TryRecvError::Empty)
+        }
+
+    }
+
+    fn send(&mut self, _: MessageKind, _: Vec<u8>) -> Result<(), Error> {
+        // Nothing is ever sent back during playback
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.close()
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        let mut state = self.player.state.borrow_mut();
+        state.connecting = false;
+        state.connected = false;
+        state.spawned.clear();
+        state.incoming.clear();
+        Ok(())
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        Err(Error::new(ErrorKind::NotConnected, "Replays have no peer address."))
+    }
+
+    fn config(&self) -> Config {
+        self.config
+    }
+
+    fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    fn rtt(&self) -> u32 {
+        0
+    }
+
+    fn packet_loss(&self) -> f32 {
+        0.0
+    }
+
+    fn bytes_sent(&self) -> u32 {
+        0
+    }
+
+    fn bytes_received(&self) -> u32 {
+        self.bytes_received
+    }
+
+}
+
+
+// Internal State -------------------------------------------------------------
+struct PlaybackState {
+    replay: Replay,
+    tick_rate: u8,
+    frame: usize,
+    time: f64,
+    speed: f32,
+    paused: bool,
+    connecting: bool,
+    connected: bool,
+    // Type and creation frame of all entities spawned on the client
+    spawned: HashMap<EntityHandle, (u8, usize)>,
+    incoming: VecDeque<ClientEvent>
+}
+
+impl PlaybackState {
+
+    // Spawns and despawns the entities recorded as created or destroyed
+    // during the given frame
+    fn frame_entities(&mut self, frame: usize) -> Option<Vec<u8>> {
+
+        let mut records = Vec::new();
+        for record in &self.replay.frames()[frame] {
+            match *record {
+                ReplayRecord::Created(handle, type_id, _) => {
+                    self.spawned.insert(handle, (type_id, frame));
+                    records.extend(spawn_record(handle, type_id));
+                },
+                ReplayRecord::Destroyed(handle) => {
+                    if self.spawned.remove(&handle).is_some() {
+                        records.extend(despawn_record(handle));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        if records.is_empty() {
+            None
+
+        } else {
+            Some(message(network::Message::ServerEntities, &records))
+        }
+
+    }
+
+    // Brings the spawned entities in line with the ones which existed at the
+    // current frame, e.g. after seeking
+    fn sync_entities(&mut self) -> Option<Vec<u8>> {
+
+        let mut entities = HashMap::new();
+        for (frame, records) in self.replay.frames()[..self.frame].iter().enumerate() {
+            for record in records {
+                match *record {
+                    ReplayRecord::Created(handle, type_id, _) => {
+                        entities.insert(handle, (type_id, frame));
+                    },
+                    ReplayRecord::Destroyed(handle) => {
+                        entities.remove(&handle);
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        let mut records = Vec::new();
+        for (handle, entity) in &self.spawned {
+            if entities.get(handle) != Some(entity) {
+                records.extend(despawn_record(*handle));
+            }
+        }
+
+        for (handle, entity) in &entities {
+            if self.spawned.get(handle) != Some(entity) {
+                records.extend(spawn_record(*handle, entity.0));
+            }
+        }
+
+        self.spawned = entities;
+
+        if records.is_empty() {
+            None
+
+        } else {
+            Some(message(network::Message::ServerEntities, &records))
+        }
+
+    }
+
+}
+
+// Entities are spawned without a state, they become visible with the first
+// state received for them
+fn spawn_record(handle: EntityHandle, type_id: u8) -> Vec<u8> {
+    vec![
+        network::LIFECYCLE_SPAWN,
+        (handle.id >> 8) as u8, handle.id as u8,
+        handle.generation,
+        type_id,
+        0, 0
+    ]
+}
+
+fn despawn_record(handle: EntityHandle) -> Vec<u8> {
+    vec![
+        network::LIFECYCLE_DESPAWN,
+        (handle.id >> 8) as u8, handle.id as u8,
+        handle.generation
+    ]
+}
+
+fn message(typ: network::Message, data: &[u8]) -> Vec<u8> {
+    let mut msg = [typ as u8].to_vec();
+    msg.extend_from_slice(data);
+    msg
+}
+
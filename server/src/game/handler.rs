@@ -1,18 +1,24 @@
 // External Dependencies ------------------------------------------------------
-use shared::Lithium::Cobalt::{Connection, ConnectionID, ConnectionMap};
-use shared::Lithium::{DefaultRenderer, ServerHandler};
+use std::io::Error;
+use shared::Lithium::Cobalt::ConnectionID;
+use shared::Lithium::{
+    CollisionShape, Connection, ConnectionSet, DecodeError, DefaultRenderer, EntityState,
+    EventTarget, ServerHandler
+};
 
 
 // Internal Dependencies ------------------------------------------------------
-use game::{Game, ServerHandle, ServerLevel, ServerEntity};
-use shared::{Color, SharedEvent, SharedCommand, SharedLevel, SharedState, SharedRegistry};
+use game::{Game, ServerHandle, ServerLevel, ServerEntity, ServerEntities};
+use shared::{SharedEvent, SharedCommand, SharedInput, SharedLevel, SharedState, SharedRegistry};
+use shared::entities::{Bullet, Ship};
 
 
 // Handler Implementation -----------------------------------------------------
-impl ServerHandler<DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, SharedState> for Game {
+impl ServerHandler<DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, SharedState, SharedInput> for Game {
 
-    fn bind(&mut self, handle: ServerHandle) {
+    fn bind(&mut self, mut handle: ServerHandle) {
         println!("[Server] Started");
+        self.start_round(&mut handle);
         self.count(handle);
     }
 
@@ -25,44 +31,30 @@ impl ServerHandler<DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, Sh
         self.disconnect_client(handle, conn);
     }
 
+    fn invalid_message(&mut self, handle: ServerHandle, conn: &mut Connection, err: DecodeError) {
+        println!("[Server] [Client {}] Sent invalid message: {:?}", conn.peer_addr(), err);
+        self.disconnect_client(handle, conn);
+        conn.close();
+    }
+
+    fn replay_error(&mut self, _: ServerHandle, err: Error) {
+        println!("[Server] Stopped recording replay: {:?}", err);
+    }
+
     fn event(
-        &mut self, handle: ServerHandle, connections: &mut ConnectionMap,
+        &mut self, handle: ServerHandle, connections: &mut ConnectionSet,
         owner: ConnectionID, event: SharedEvent
     ) {
 
         println!("[Server] [Client {:?}] Event: {:?}", owner, event);
 
         match event {
-            SharedEvent::JoinGame => {
-
-                if let Some(_) = handle.entities.get_entity_for_owner(&owner) {
-                    println!("[Server] [Client {:?}] Already has a entity.", owner);
-
-                } else {
-
-                    // Create a ship entity from one of the available colors
-                    if let Some(color) = self.available_colors.pop() {
-
-                        let (x, y) = handle.level.center();
-                        let state = SharedState {
-                            x: x as f32,
-                            y: y as f32,
-                            flags: color.to_flags(),
-                            .. Default::default() // TODO implement default trait
-                        };
-
-                        handle.entities.create(
-                            0,
-                            Some(state),
-                            Some(&owner)
-                        );
-
-                        handle.events.send_to(Some(owner), SharedEvent::GameJoined);
-                        handle.events.send(SharedEvent::PlayerJoined);
-
-                    }
+            SharedEvent::JoinGame { name, color } => {
+                self.join(handle, owner, name, color);
+            },
 
-                }
+            SharedEvent::Spectate => {
+                self.spectate(handle, owner);
             },
 
             SharedEvent::LeaveGame => {
@@ -80,31 +72,167 @@ impl ServerHandler<DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, Sh
 
     }
 
-    fn tick_before(&mut self, _: ServerHandle, _: &mut ConnectionMap) {
+    fn tick_before(&mut self, _: ServerHandle, _: &mut ConnectionSet) {
 
-        // TODO bullets are handled by pre-creating a local object and then
-        // syncing it with the remote one, we submit a local ID and the server
-        // return this ID along with the remote object ID when updating
+        // Bullets are handled by pre-creating a local object on the client
+        // and then syncing it with the remote one, the client submits the
+        // input tick as a local ID and the server returns this ID along with
+        // the remote object ID once the bullet was created
 
-        // TODO server side collision is checked on each server tick
-        // positions are warped to the last known local tick of the player
-        // BUT there is a maximum tick difference to prevent cheating
+        // Server side hit detection warps all ship positions back to the
+        // tick the shooter was seeing via handle.rewind() in tick_after(),
+        // the maximum tick difference is limited by Server::set_max_rewind
+        // in order to prevent cheating
 
     }
 
     fn tick_entity_before(
-        &mut self, _: &ServerLevel, _: &mut ServerEntity, _: u8, _: f32
+        &mut self, _: &ServerLevel, entity: &mut ServerEntity, tick: u32, dt: f32
     ) {
 
+        // Collect bullets fired by ships
+        if entity.type_id() == 0 {
+            if let Some(owner) = entity.owner().map(|o| *o) {
+                for input in entity.inputs() {
+                    if input.fire() {
+
+                        // Enforce the fire interval of the ships, the tick of
+                        // the input is set by the client and only serves as
+                        // the local id of the predicted bullet
+                        let can_fire = self.last_fire_ticks.get(&owner).map_or(true, |last_tick| {
+                            tick.saturating_sub(*last_tick) >= Bullet::fire_interval() as u32
+                        });
+
+                        if can_fire {
+                            self.last_fire_ticks.insert(owner, tick);
+                            self.fired_bullets.push((
+                                owner,
+                                input.tick,
+                                Bullet::fire(entity.state(), dt)
+                            ));
+                        }
+
+                    }
+                }
+            }
+        }
+
     }
 
     fn tick_entity_after(
-        &mut self, _: &ServerLevel, _: &mut ServerEntity, _: u8, _: f32
+        &mut self, _: &ServerLevel, _: &mut ServerEntity, _: u32, _: f32
     ) {
 
     }
 
-    fn tick_after(&mut self, _: ServerHandle, _: &mut ConnectionMap) {
+    fn collision(&mut self, handle: ServerHandle, a: u16, b: u16) {
+
+        let types = (
+            handle.entities.get_entity(a).map(|e| e.type_id()),
+            handle.entities.get_entity(b).map(|e| e.type_id())
+        );
+
+        match types {
+
+            // Ships bouncing off of each other
+            (Some(0), Some(0)) => {
+
+                let states = (
+                    handle.entities.get_entity(a).map(|e| e.state().clone()),
+                    handle.entities.get_entity(b).map(|e| e.state().clone())
+                );
+
+                if let (Some(mut state_a), Some(mut state_b)) = states {
+
+                    let (mx, my) = (state_a.mx, state_a.my);
+                    state_a.mx = state_b.mx;
+                    state_a.my = state_b.my;
+                    state_b.mx = mx;
+                    state_b.my = my;
+
+                    if let Some(entity) = handle.entities.get_entity_mut(a) {
+                        entity.set_state(state_a);
+                    }
+
+                    if let Some(entity) = handle.entities.get_entity_mut(b) {
+                        entity.set_state(state_b);
+                    }
+
+                }
+
+            },
+
+            _ => {}
+
+        }
+
+    }
+
+    fn tick_after(&mut self, mut handle: ServerHandle, connections: &mut ConnectionSet) {
+
+        // Create fired bullets and let their owners know about the remote IDs
+        for (owner, local_id, state) in self.fired_bullets.drain(..) {
+            if let Some(entity) = handle.entities.create(1, Some(state), None) {
+                self.bullets.insert(entity.id(), (owner, Bullet::lifetime()));
+                handle.events.send_to(EventTarget::Connection(owner), SharedEvent::BulletConfirmed {
+                    local_id: local_id,
+                    entity: entity.handle()
+                });
+            }
+        }
+
+        // Bullets hit ships at the positions their shooter was seeing when
+        // sending the inputs for this tick
+        let tick = handle.entities.tick().saturating_sub(1);
+        let bullets: Vec<(u16, ConnectionID)> = self.bullets.iter().map(|(id, &(owner, _))| {
+            (*id, owner)
+
+        }).collect();
+
+        for (bullet_id, owner) in bullets {
+
+            let bullet = handle.entities.get_entity(bullet_id).and_then(|e| {
+                e.collision_shape().map(|shape| (e.state().clone(), shape))
+            });
+
+            let conn = match connections.get_mut(&owner) {
+                Some(conn) => conn,
+                None => continue
+            };
+
+            if let Some((state, shape)) = bullet {
+
+                let ships = handle.rewind(conn, tick, |level, entities| {
+                    Game::ships_hit(level, entities, &owner, &state, &shape)
+                });
+
+                // Hits on team mates or between rounds are ignored and the
+                // bullet keeps flying
+                for ship_id in ships {
+                    if self.bullets.contains_key(&bullet_id) {
+                        self.bullet_hit(&mut handle, bullet_id, ship_id);
+                    }
+                }
+
+            }
+
+        }
+
+        // Destroy expired bullets
+        let mut expired = Vec::new();
+        for (id, &mut (_, ref mut ticks)) in &mut self.bullets {
+            if *ticks == 0 {
+                expired.push(*id);
+
+            } else {
+                *ticks -= 1;
+            }
+        }
+
+        for id in expired {
+            self.bullets.remove(&id);
+            handle.entities.destroy(id);
+        }
 
     }
 
@@ -116,14 +244,90 @@ impl ServerHandler<DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, Sh
 
 impl Game {
 
-    pub fn disconnect_client(&mut self, handle: ServerHandle, conn: &mut Connection) {
-        while let Some(id) = handle.entities.get_entity_id_for_owner(&conn.id()) {
-            if let Some(entity) = handle.entities.destroy(id) {
-                let color = Color::from_flags(entity.state().flags);
-                println!("[Server] [Client {}] Destroyed entity ({:?})", conn.peer_addr(), color);
-                self.available_colors.push(color);
+    fn ships_hit(
+        level: &ServerLevel, entities: &ServerEntities, owner: &ConnectionID,
+        state: &SharedState, shape: &CollisionShape
+
+    ) -> Vec<u16> {
+        entities.entities().filter(|e| {
+            e.type_id() == 0 && !e.owned_by(owner)
+
+        }).filter_map(|ship| {
+            ship.collision_shape().and_then(|ship_shape| {
+                let offset = level.entity_state_distance(state, ship.state());
+                if shape.overlaps(&ship_shape, offset) {
+                    Some(ship.id())
+
+                } else {
+                    None
+                }
+            })
+
+        }).collect()
+    }
+
+    fn bullet_hit(&mut self, handle: &mut ServerHandle, bullet_id: u16, ship_id: u16) {
+
+        let shooter = self.bullets.get(&bullet_id).map(|b| b.0);
+        let target = handle.entities.get_entity(ship_id).and_then(|e| e.owner().map(|o| *o));
+
+        // Ships cannot hit themselves or members of their own team
+        let shooter_color = shooter.and_then(|s| self.players.get(&s)).map(|p| p.color.to_u8());
+        let target_color = target.and_then(|t| self.players.get(&t)).map(|p| p.color.to_u8());
+        if shooter.is_none() || shooter == target {
+            return;
+
+        } else if self.rules.teams && shooter_color == target_color {
+            return;
+
+        // No damage is dealt between rounds
+        } else if !self.round_active {
+            return;
+        }
+
+        if let (Some(shooter), Some(target)) = (shooter, target) {
+            println!(
+                "[Server] \"{}\" hit \"{}\"",
+                self.player_name(&shooter), self.player_name(&target)
+            );
+        }
+        self.bullets.remove(&bullet_id);
+        handle.entities.destroy(bullet_id);
+
+        // Apply damage unless the ship recently spawned
+        let destroyed = match handle.entities.get_entity_mut(ship_id) {
+            Some(entity) => if entity.state().flags & 0x04 == 0 {
+                let mut state = entity.state().clone();
+                let destroyed = Ship::damage(&mut state, Bullet::damage());
+                entity.set_state(state);
+                destroyed
+
+            } else {
+                false
+            },
+            None => false
+        };
+
+        if destroyed {
+            handle.entities.destroy(ship_id);
+            if let (Some(killer), Some(victim)) = (shooter, target) {
+                println!(
+                    "[Server] \"{}\" killed \"{}\"",
+                    self.player_name(&killer), self.player_name(&victim)
+                );
+                self.player_killed(handle, killer, victim);
             }
         }
+
+    }
+
+    pub fn disconnect_client(&mut self, mut handle: ServerHandle, conn: &mut Connection) {
+        self.last_fire_ticks.remove(&conn.id());
+        let ids: Vec<u16> = handle.entities.get_entities_for_owner(&conn.id()).map(|e| e.id()).collect();
+        for id in ids {
+            handle.entities.destroy(id);
+        }
+        self.leave(&mut handle, &conn.id());
     }
 
 }
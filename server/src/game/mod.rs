@@ -1,55 +1,441 @@
 // External Dependencies ------------------------------------------------------
-use shared::Lithium::{Level, Server, DefaultRenderer, Entity, ServerHandle as Handle};
+use std::collections::{HashMap, HashSet};
+use shared::Lithium::Cobalt::ConnectionID;
+use shared::Lithium::{
+    Level, Server, DefaultRenderer, Entity, EntityManager, EntityState, EventTarget,
+    ServerHandle as Handle
+};
 
 
 // Internal Dependencies ------------------------------------------------------
-use shared::{Color, SharedEvent, SharedLevel, SharedState, SharedRegistry};
+use shared::{GAME_VERSION, Color, ColorName, JoinError, PlayerScore, SharedEvent, SharedInput, SharedLevel, SharedState, SharedRegistry};
+use shared::entities::Ship;
 mod handler;
+mod player;
+mod rules;
+use self::player::Player;
+pub use self::rules::MatchRules;
 
 
 // Type Aliases ---------------------------------------------------------------
-pub type ServerHandle<'a> = Handle<'a, Game, DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, SharedState>;
+pub type ServerHandle<'a> = Handle<'a, Game, DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, SharedState, SharedInput>;
 pub type ServerLevel = Level<SharedState, SharedLevel>;
-pub type ServerEntity = Entity<SharedState, SharedLevel, DefaultRenderer>;
+pub type ServerEntity = Entity<SharedState, SharedInput, SharedLevel, DefaultRenderer>;
+pub type ServerEntities = EntityManager<SharedState, SharedInput, SharedLevel, DefaultRenderer, SharedRegistry>;
 
 
 // Server Side Game Logic -----------------------------------------------------
 pub struct Game {
     available_colors: Vec<Color>,
     loopback_mode: bool,
-    counter: u32
+    counter: u32,
+    rules: MatchRules,
+    round: u32,
+    round_active: bool,
+    players: HashMap<ConnectionID, Player>,
+    spectators: HashSet<ConnectionID>,
+    scores: HashMap<ConnectionID, PlayerScore>,
+    bullets: HashMap<u16, (ConnectionID, u8)>,
+    fired_bullets: Vec<(ConnectionID, u8, SharedState)>,
+    last_fire_ticks: HashMap<ConnectionID, u32>
 }
 
 impl Game {
 
-    pub fn new(loopback_mode: bool) -> Game {
+    pub fn new(loopback_mode: bool, rules: MatchRules) -> Game {
         Game {
             available_colors: Color::all_colored().into_iter().rev().collect(),
             loopback_mode: loopback_mode,
-            counter: 1
+            counter: 1,
+            rules: rules,
+            round: 0,
+            round_active: false,
+            players: HashMap::new(),
+            spectators: HashSet::new(),
+            scores: HashMap::new(),
+            bullets: HashMap::new(),
+            fired_bullets: Vec::new(),
+            last_fire_ticks: HashMap::new()
         }
     }
 
     pub fn server(
         tick_rate: u32,
-        loopback_mode: bool
+        loopback_mode: bool,
+        rules: MatchRules
 
     ) -> Server<
         Game, DefaultRenderer,
-        SharedRegistry, SharedLevel, SharedEvent, SharedState
+        SharedRegistry, SharedLevel, SharedEvent, SharedState, SharedInput
     > {
-        Server::new(
+        let (view_radius, state_budget) = (rules.view_radius, rules.state_budget);
+        let mut server = Server::new(
             tick_rate, 1000, 75,
             Game::default_level(),
             SharedRegistry,
-            Game::new(loopback_mode)
-        )
+            Game::new(loopback_mode, rules)
+        );
+        server.set_max_rewind(200);
+        server.set_interest_radius(view_radius);
+        server.set_state_budget(state_budget);
+        server.set_game_version(GAME_VERSION);
+        server
     }
 
     pub fn default_level() -> Level<SharedState, SharedLevel> {
         SharedLevel::create(384, 384, 16)
     }
 
+    pub fn respawn_delay() -> u64 {
+        3000
+    }
+
+    pub fn invulnerability_duration() -> u64 {
+        2000
+    }
+
+    fn spawn_ship(&mut self, handle: &mut ServerHandle, owner: ConnectionID) {
+
+        let flags = match self.players.get(&owner) {
+            Some(player) => player.color.to_flags(),
+            None => return
+        };
+
+        // Ships are invulnerable for a short time after spawning
+        let (x, y) = handle.level.center();
+        let state = SharedState {
+            x: x as f32,
+            y: y as f32,
+            flags: flags | 0x04,
+            hp: Ship::max_hp(),
+            shield: Ship::max_shield(),
+            .. Default::default()
+        };
+
+        let ship = match handle.entities.create(0, Some(state), Some(&owner)) {
+            Some(entity) => entity.handle(),
+            None => return
+        };
+
+        // The timer only applies to this exact ship, in case it was destroyed
+        // in the meantime a new ship of the same player keeps its own timer
+        handle.timer.schedule(Box::new(move |_, handle| {
+            if let Some(entity) = handle.entities.get_entity_by_handle_mut(ship) {
+                let mut state = entity.state().clone();
+                state.flags &= !0x04;
+                entity.set_state(state);
+            }
+
+        }), Game::invulnerability_duration());
+
+    }
+
+    fn schedule_respawn(&mut self, handle: &mut ServerHandle, owner: ConnectionID) {
+        handle.timer.schedule(Box::new(move |game, mut handle| {
+
+            // Players might have left the game in the meantime
+            if game.round_active && handle.entities.get_entities_for_owner(&owner).next().is_none() {
+                game.spawn_ship(&mut handle, owner);
+            }
+
+        }), Game::respawn_delay());
+    }
+
+    fn join_color(&mut self, preferred: Option<ColorName>) -> Result<Color, JoinError> {
+
+        if self.rules.teams {
+
+            let team_colors = MatchRules::team_colors();
+            match preferred {
+
+                // Players may pick any of the teams
+                Some(name) => team_colors.into_iter().find(|color| {
+                    color.to_u8() == name.to_u8()
+
+                }).ok_or(JoinError::ColorUnavailable),
+
+                // Otherwise assign them to the team with the fewest members
+                None => team_colors.into_iter().min_by_key(|color| {
+                    self.players.values().filter(|p| {
+                        p.color.to_u8() == color.to_u8()
+
+                    }).count()
+
+                }).ok_or(JoinError::GameFull)
+
+            }
+
+        } else {
+            match preferred {
+                Some(name) => {
+                    let index = self.available_colors.iter().position(|color| {
+                        color.to_u8() == name.to_u8()
+                    });
+                    match index {
+                        Some(index) => Ok(self.available_colors.remove(index)),
+                        None => Err(JoinError::ColorUnavailable)
+                    }
+                },
+                None => self.available_colors.pop().ok_or(JoinError::GameFull)
+            }
+        }
+
+    }
+
+    fn join(
+        &mut self,
+        mut handle: ServerHandle,
+        owner: ConnectionID,
+        name: String,
+        color: Option<ColorName>
+    ) {
+
+        if self.players.contains_key(&owner) || self.spectators.contains(&owner) {
+            println!("[Server] [Client {:?}] Already joined the game.", owner);
+            return;
+        }
+
+        // Validate the player name
+        let name = name.trim().to_string();
+        let result = if name.is_empty() || name.chars().count() > Player::max_name_length() {
+            Err(JoinError::InvalidName)
+
+        } else if self.rules.max_players.map_or(false, |max| self.players.len() >= max) {
+            Err(JoinError::GameFull)
+
+        } else if self.players.values().any(|p| p.name.to_lowercase() == name.to_lowercase()) {
+            Err(JoinError::DuplicateName)
+
+        } else {
+            self.join_color(color)
+        };
+
+        let color = match result {
+            Ok(color) => color,
+            Err(err) => {
+                println!("[Server] [Client {:?}] Join rejected: {:?}", owner, err);
+                handle.events.send_to(EventTarget::Connection(owner), SharedEvent::JoinRejected(err));
+                return;
+            }
+        };
+
+        println!("[Server] [Client {:?}] Joined as \"{}\" ({:?})", owner, name, color.to_name());
+        handle.events.send_to(EventTarget::Connection(owner), SharedEvent::GameJoined {
+            id: owner.0,
+            color: color.to_name()
+        });
+
+        self.send_roster(&mut handle, owner);
+
+        handle.events.send(SharedEvent::PlayerJoined {
+            id: owner.0,
+            name: name.clone(),
+            color: color.to_name()
+        });
+
+        // Allow events to be sent to all members of a team
+        if self.rules.teams {
+            handle.events.set_team(&owner, Some(color.to_u8()));
+        }
+
+        self.scores.insert(owner, PlayerScore::new(owner.0, color.to_u8()));
+        self.players.insert(owner, Player::new(name, color));
+        if self.round_active {
+            self.spawn_ship(&mut handle, owner);
+        }
+        self.send_scores(&mut handle);
+
+    }
+
+    fn spectate(&mut self, mut handle: ServerHandle, owner: ConnectionID) {
+
+        if self.players.contains_key(&owner) || self.spectators.contains(&owner) {
+            println!("[Server] [Client {:?}] Already joined the game.", owner);
+            return;
+        }
+
+        if self.spectators.len() >= self.rules.max_spectators {
+            println!("[Server] [Client {:?}] Spectate rejected: {:?}", owner, JoinError::SpectatorsFull);
+            handle.events.send_to(
+                EventTarget::Connection(owner),
+                SharedEvent::JoinRejected(JoinError::SpectatorsFull)
+            );
+            return;
+        }
+
+        println!("[Server] [Client {:?}] Joined as a spectator", owner);
+        handle.events.send_to(EventTarget::Connection(owner), SharedEvent::SpectateAccepted);
+        handle.entities.set_state_delay(&owner, self.rules.spectator_delay);
+
+        self.spectators.insert(owner);
+        self.send_roster(&mut handle, owner);
+        self.send_scores(&mut handle);
+
+    }
+
+    // Lets a newly joined client know about everyone who is already playing
+    fn send_roster(&self, handle: &mut ServerHandle, owner: ConnectionID) {
+        for (id, player) in &self.players {
+            handle.events.send_to(EventTarget::Connection(owner), SharedEvent::PlayerJoined {
+                id: id.0,
+                name: player.name.clone(),
+                color: player.color.to_name()
+            });
+        }
+    }
+
+    fn leave(&mut self, handle: &mut ServerHandle, owner: &ConnectionID) {
+
+        if self.spectators.remove(owner) {
+            println!("[Server] [Client {:?}] Spectator left", owner);
+        }
+
+        if let Some(player) = self.players.remove(owner) {
+
+            println!("[Server] [Client {:?}] \"{}\" left the game", owner, player.name);
+            handle.events.set_team(owner, None);
+            handle.events.send(SharedEvent::PlayerLeft {
+                id: owner.0,
+                name: player.name
+            });
+
+            // Team colors are shared and never run out
+            if !self.rules.teams {
+                self.available_colors.push(player.color);
+            }
+
+            self.scores.remove(owner);
+            self.send_scores(handle);
+
+        }
+    }
+
+    fn player_name(&self, owner: &ConnectionID) -> &str {
+        self.players.get(owner).map_or("Unknown", |p| &p.name[..])
+    }
+
+    fn player_killed(&mut self, handle: &mut ServerHandle, killer: ConnectionID, victim: ConnectionID) {
+
+        if let Some(score) = self.scores.get_mut(&killer) {
+            score.kills += 1;
+            score.score += 1;
+        }
+
+        if let Some(score) = self.scores.get_mut(&victim) {
+            score.deaths += 1;
+        }
+
+        handle.events.send(SharedEvent::PlayerKilled {
+            killer: killer.0,
+            victim: victim.0
+        });
+
+        self.send_scores(handle);
+
+        let limit_reached = match (self.rules.score_limit, self.leader()) {
+            (Some(limit), Some((_, score))) => score >= limit,
+            _ => false
+        };
+
+        if limit_reached {
+            self.end_round(handle);
+
+        } else {
+            self.schedule_respawn(handle, victim);
+        }
+
+    }
+
+    // Scores are summed up per color, which results in per team scores when
+    // team play is enabled, equal top scores result in a draw
+    fn leader(&self) -> Option<(Option<u8>, i16)> {
+
+        let mut totals: HashMap<u8, i16> = HashMap::new();
+        for score in self.scores.values() {
+            *totals.entry(score.color).or_insert(0) += score.score;
+        }
+
+        let mut leader = None;
+        for (color, score) in totals {
+            leader = match leader {
+                Some((_, best)) if best > score => leader,
+                Some((_, best)) if best == score => Some((None, score)),
+                _ => Some((Some(color), score))
+            };
+        }
+
+        leader
+
+    }
+
+    fn send_scores(&self, handle: &mut ServerHandle) {
+        let mut scores: Vec<PlayerScore> = self.scores.values().cloned().collect();
+        scores.sort_by(|a, b| b.score.cmp(&a.score));
+        handle.events.send(SharedEvent::ScoreUpdate(scores));
+    }
+
+    fn start_round(&mut self, handle: &mut ServerHandle) {
+
+        self.round += 1;
+        self.round_active = true;
+
+        // Remove all left over ships and bullets from the previous round
+        for (id, _) in self.bullets.drain() {
+            handle.entities.destroy(id);
+        }
+        self.fired_bullets.clear();
+
+        let owners: Vec<ConnectionID> = self.players.keys().cloned().collect();
+        for owner in owners {
+            let ids: Vec<u16> = handle.entities.get_entities_for_owner(&owner).map(|e| e.id()).collect();
+            for id in ids {
+                handle.entities.destroy(id);
+            }
+            self.spawn_ship(handle, owner);
+        }
+
+        for score in self.scores.values_mut() {
+            score.reset();
+        }
+
+        handle.events.send(SharedEvent::RoundStarted);
+        self.send_scores(handle);
+
+        if let Some(time_limit) = self.rules.time_limit {
+            let round = self.round;
+            handle.timer.schedule(Box::new(move |game, mut handle| {
+                if game.round == round && game.round_active {
+                    game.end_round(&mut handle);
+                }
+
+            }), time_limit);
+        }
+
+        println!("[Server] Round {} started", self.round);
+
+    }
+
+    fn end_round(&mut self, handle: &mut ServerHandle) {
+
+        self.round_active = false;
+
+        let winner = self.leader().and_then(|(color, _)| color);
+
+        println!("[Server] Round {} ended, winner: {:?}", self.round, winner);
+        handle.events.send(SharedEvent::RoundEnded {
+            winner: winner
+        });
+
+        let round = self.round;
+        handle.timer.schedule(Box::new(move |game, mut handle| {
+            if game.round == round {
+                game.start_round(&mut handle);
+            }
+
+        }), self.rules.restart_delay);
+
+    }
+
     fn count(&mut self, handle: ServerHandle) {
 
         // TODO pass in TimerId?
@@ -68,3 +454,247 @@ impl Game {
 
 }
 
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::io::Error;
+    use shared::Lithium::Cobalt::ConnectionID;
+    use shared::Lithium::{
+        DefaultRenderer, EntityManager, EventHandler, ServerTimer, ServerTransport
+    };
+    use shared::{Color, ColorName, SharedEvent, SharedInput, SharedLevel, SharedRegistry, SharedState};
+    use super::{Game, MatchRules, ServerEntities, ServerHandle, ServerLevel};
+
+    struct MockTransport;
+    impl ServerTransport for MockTransport {
+        fn shutdown(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    // Everything a server handle borrows from the server
+    struct World {
+        level: ServerLevel,
+        entities: ServerEntities,
+        events: EventHandler<SharedEvent>,
+        timer: ServerTimer<Game, DefaultRenderer, SharedRegistry, SharedLevel, SharedEvent, SharedState, SharedInput>,
+        transport: MockTransport
+    }
+
+    impl World {
+
+        fn new() -> World {
+            World {
+                level: Game::default_level(),
+                entities: EntityManager::new(30, 1000, 75, true, SharedRegistry),
+                events: EventHandler::new(),
+                timer: ServerTimer::new(),
+                transport: MockTransport
+            }
+        }
+
+        fn handle(&mut self) -> ServerHandle {
+            ServerHandle {
+                level: &mut self.level,
+                entities: &mut self.entities,
+                events: &mut self.events,
+                timer: &mut self.timer,
+                server: &mut self.transport
+            }
+        }
+
+        fn run_timers(&mut self, game: &mut Game, ms: u64) {
+            for mut f in self.timer.update(ms) {
+                f(game, self.handle());
+            }
+        }
+
+    }
+
+    // Debug representations of the events sent to the connection since the
+    // last call
+    fn events_for(world: &mut World, owner: ConnectionID) -> Vec<String> {
+
+        let outgoing = world.events.serialize_targeted(&[owner], |_, _| false);
+        world.events.flush();
+
+        let mut receiver = EventHandler::<SharedEvent>::new();
+        if let Some(data) = outgoing.get(&owner) {
+            receiver.receive_events(owner, data).unwrap();
+        }
+
+        receiver.received().map_or(Vec::new(), |events| {
+            events.into_iter().map(|(_, e)| format!("{:?}", e)).collect()
+        })
+
+    }
+
+    fn game(rules: MatchRules) -> (Game, World) {
+        let mut game = Game::new(false, rules);
+        let mut world = World::new();
+        game.start_round(&mut world.handle());
+        (game, world)
+    }
+
+    #[test]
+    fn test_invulnerability() {
+
+        let owner = ConnectionID(1);
+        let (mut game, mut world) = game(MatchRules::default());
+        game.join(world.handle(), owner, "Alice".to_string(), None);
+
+        let invulnerable = |world: &World| {
+            world.entities.get_entities_for_owner(&owner).next().map(|e| {
+                e.state().flags & 0x04 != 0
+            })
+        };
+        assert_eq!(invulnerable(&world), Some(true));
+
+        // A ship spawned while the timer of a previous ship is still running
+        // keeps its full invulnerability
+        world.run_timers(&mut game, 1000);
+        let id = world.entities.get_entities_for_owner(&owner).next().unwrap().id();
+        world.entities.destroy(id);
+        game.spawn_ship(&mut world.handle(), owner);
+
+        world.run_timers(&mut game, 1000);
+        assert_eq!(invulnerable(&world), Some(true));
+
+        world.run_timers(&mut game, 1000);
+        assert_eq!(invulnerable(&world), Some(false));
+
+    }
+
+    #[test]
+    fn test_round_ends_at_score_limit() {
+
+        let (a, b) = (ConnectionID(1), ConnectionID(2));
+        let (mut game, mut world) = game(MatchRules {
+            score_limit: Some(2),
+            .. MatchRules::default()
+        });
+        game.join(world.handle(), a, "Alice".to_string(), None);
+        game.join(world.handle(), b, "Bob".to_string(), None);
+
+        game.player_killed(&mut world.handle(), a, b);
+        assert!(game.round_active);
+        assert_eq!(game.leader().map(|l| l.1), Some(1));
+
+        game.player_killed(&mut world.handle(), a, b);
+        assert!(!game.round_active);
+        assert_eq!(game.scores[&a].kills, 2);
+        assert_eq!(game.scores[&a].score, 2);
+        assert_eq!(game.scores[&b].deaths, 2);
+        assert_eq!(game.leader(), Some((Some(game.players[&a].color.to_u8()), 2)));
+
+        // The next round starts after the restart delay with reset scores
+        let delay = game.rules.restart_delay;
+        world.run_timers(&mut game, delay);
+        assert!(game.round_active);
+        assert_eq!(game.round, 2);
+        assert_eq!(game.scores[&a].score, 0);
+        assert_eq!(world.entities.get_entities_for_owner(&b).count(), 1);
+
+    }
+
+    #[test]
+    fn test_team_scores() {
+
+        let (a, b, c) = (ConnectionID(1), ConnectionID(2), ConnectionID(3));
+        let (mut game, mut world) = game(MatchRules {
+            teams: true,
+            .. MatchRules::default()
+        });
+        game.join(world.handle(), a, "Alice".to_string(), Some(ColorName::Red));
+        game.join(world.handle(), b, "Bob".to_string(), None);
+        game.join(world.handle(), c, "Carol".to_string(), None);
+
+        // Players without a preference fill up the smaller team
+        let (red, blue) = (
+            Color::from_name(ColorName::Red).to_u8(),
+            Color::from_name(ColorName::Blue).to_u8()
+        );
+        assert_eq!(game.players[&b].color.to_u8(), blue);
+        assert_eq!(game.players[&c].color.to_u8(), red);
+
+        // Team scores are summed up, equal scores are a draw
+        game.player_killed(&mut world.handle(), a, b);
+        game.player_killed(&mut world.handle(), b, a);
+        assert_eq!(game.leader(), Some((None, 1)));
+
+        game.player_killed(&mut world.handle(), c, b);
+        assert_eq!(game.leader(), Some((Some(red), 2)));
+
+    }
+
+    #[test]
+    fn test_join_validation() {
+
+        let (a, b, c) = (ConnectionID(1), ConnectionID(2), ConnectionID(3));
+        let (mut game, mut world) = game(MatchRules {
+            max_players: Some(2),
+            .. MatchRules::default()
+        });
+
+        game.join(world.handle(), a, "  Alice ".to_string(), Some(ColorName::Red));
+        assert_eq!(game.players[&a].name, "Alice");
+        assert!(events_for(&mut world, a).iter().any(|e| e.starts_with("GameJoined")));
+
+        let rejected = vec![
+            ("", None, "InvalidName"),
+            ("A name that is far too long", None, "InvalidName"),
+            ("ALICE", None, "DuplicateName"),
+            ("Bob", Some(ColorName::Red), "ColorUnavailable")
+        ];
+
+        for (name, color, reason) in rejected {
+            game.join(world.handle(), b, name.to_string(), color);
+            assert!(game.players.get(&b).is_none());
+            assert_eq!(events_for(&mut world, b), vec![format!("JoinRejected({})", reason)]);
+        }
+
+        // Joining players receive the roster of everyone already playing
+        game.join(world.handle(), b, "Bob".to_string(), None);
+        assert!(events_for(&mut world, b).iter().any(|e| e.contains("\"Alice\"")));
+
+        game.join(world.handle(), c, "Carol".to_string(), None);
+        assert_eq!(events_for(&mut world, c), vec!["JoinRejected(GameFull)".to_string()]);
+
+        // Colors become available again once their player left
+        game.leave(&mut world.handle(), &a);
+        game.join(world.handle(), c, "Carol".to_string(), Some(ColorName::Red));
+        assert_eq!(game.players[&c].color.to_u8(), Color::from_name(ColorName::Red).to_u8());
+
+    }
+
+    #[test]
+    fn test_spectators() {
+
+        let (a, b) = (ConnectionID(1), ConnectionID(2));
+        let (mut game, mut world) = game(MatchRules {
+            max_spectators: 1,
+            .. MatchRules::default()
+        });
+        world.events.flush();
+
+        game.spectate(world.handle(), a);
+        assert_eq!(events_for(&mut world, a)[0], "SpectateAccepted");
+
+        game.spectate(world.handle(), b);
+        assert_eq!(events_for(&mut world, b), vec!["JoinRejected(SpectatorsFull)".to_string()]);
+
+        // Spectators neither become players nor own a ship
+        game.join(world.handle(), a, "Alice".to_string(), None);
+        assert!(game.players.get(&a).is_none());
+        assert_eq!(world.entities.get_entities_for_owner(&a).count(), 0);
+
+        game.leave(&mut world.handle(), &a);
+        game.spectate(world.handle(), b);
+        assert_eq!(events_for(&mut world, b)[0], "SpectateAccepted");
+
+    }
+
+}
@@ -0,0 +1,26 @@
+// Internal Dependencies ------------------------------------------------------
+use shared::Color;
+
+
+// Player ---------------------------------------------------------------------
+#[derive(Debug)]
+pub struct Player {
+    pub name: String,
+    pub color: Color
+}
+
+impl Player {
+
+    pub fn new(name: String, color: Color) -> Player {
+        Player {
+            name: name,
+            color: color
+        }
+    }
+
+    pub fn max_name_length() -> usize {
+        16
+    }
+
+}
+
@@ -0,0 +1,63 @@
+// Internal Dependencies ------------------------------------------------------
+use shared::{Color, ColorName};
+
+
+// Match Rules ----------------------------------------------------------------
+#[derive(Debug)]
+pub struct MatchRules {
+
+    // Score at which a player or team wins the round
+    pub score_limit: Option<i16>,
+
+    // Round duration in milliseconds
+    pub time_limit: Option<u64>,
+
+    // Delay in milliseconds before the next round starts
+    pub restart_delay: u64,
+
+    // Assign players to one of the team colors instead of unique colors
+    pub teams: bool,
+
+    // Maximum number of players, otherwise limited by the available colors
+    pub max_players: Option<usize>,
+
+    pub max_spectators: usize,
+
+    // Delay in milliseconds of the state sent to spectators
+    pub spectator_delay: u32,
+
+    // Radius around a player's ship outside of which entities are not sent
+    pub view_radius: Option<f32>,
+
+    // Maximum number of bytes per state update sent to a client
+    pub state_budget: Option<usize>
+
+}
+
+impl MatchRules {
+
+    pub fn team_colors() -> Vec<Color> {
+        vec![
+            Color::from_name(ColorName::Red),
+            Color::from_name(ColorName::Blue)
+        ]
+    }
+
+}
+
+impl Default for MatchRules {
+    fn default() -> MatchRules {
+        MatchRules {
+            score_limit: Some(10),
+            time_limit: None,
+            restart_delay: 5000,
+            teams: false,
+            max_players: None,
+            max_spectators: 8,
+            spectator_delay: 0,
+            view_radius: None,
+            state_budget: None
+        }
+    }
+}
+
@@ -5,6 +5,7 @@ extern crate shared;
 
 // External Dependencies ------------------------------------------------------
 use std::str::FromStr;
+use std::path::Path;
 use std::net::SocketAddr;
 use shared::Lithium::Server;
 
@@ -24,6 +25,50 @@ fn main() {
             .help("Local server address to bind to.")
             .index(1)
 
+        ).arg(clap::Arg::with_name("score-limit")
+            .help("Score at which a round ends, 0 disables the limit.")
+            .long("score-limit")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("time-limit")
+            .help("Round duration in seconds.")
+            .long("time-limit")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("teams")
+            .help("Enables team play.")
+            .long("teams")
+
+        ).arg(clap::Arg::with_name("max-players")
+            .help("Maximum number of players.")
+            .long("max-players")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("max-spectators")
+            .help("Maximum number of spectators.")
+            .long("max-spectators")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("spectator-delay")
+            .help("Delay of the state sent to spectators in milliseconds.")
+            .long("spectator-delay")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("view-radius")
+            .help("Radius around each ship outside of which entities are not sent.")
+            .long("view-radius")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("state-budget")
+            .help("Maximum size of each state update in bytes.")
+            .long("state-budget")
+            .takes_value(true)
+
+        ).arg(clap::Arg::with_name("record")
+            .help("Records the session into the given replay file.")
+            .long("record")
+            .takes_value(true)
+
         ).get_matches();
 
 
@@ -33,9 +78,49 @@ fn main() {
 
     ).unwrap_or(SocketAddr::from_str("127.0.0.1:31475").unwrap());
 
+    let mut rules = game::MatchRules::default();
+    if let Ok(limit) = value_t!(args.value_of("score-limit"), i16) {
+        rules.score_limit = if limit > 0 { Some(limit) } else { None };
+    }
+
+    if let Ok(seconds) = value_t!(args.value_of("time-limit"), u64) {
+        rules.time_limit = Some(seconds * 1000);
+    }
+
+    rules.teams = args.is_present("teams");
+
+    if let Ok(max) = value_t!(args.value_of("max-players"), usize) {
+        rules.max_players = Some(max);
+    }
+
+    if let Ok(max) = value_t!(args.value_of("max-spectators"), usize) {
+        rules.max_spectators = max;
+    }
+
+    if let Ok(delay) = value_t!(args.value_of("spectator-delay"), u32) {
+        rules.spectator_delay = delay;
+    }
+
+    if let Ok(radius) = value_t!(args.value_of("view-radius"), f32) {
+        rules.view_radius = Some(radius);
+    }
+
+    if let Ok(bytes) = value_t!(args.value_of("state-budget"), usize) {
+        rules.state_budget = Some(bytes);
+    }
+
 
     // Server Setup -----------------------------------------------------------
-    if let Err(err) = Server::run(server_addr, game::Game::server(30, false)) {
+    let mut server = game::Game::server(30, false, rules);
+    if let Some(path) = args.value_of("record") {
+        if let Err(err) = server.record(Path::new(path)) {
+            println!("[Server] [Fatal] Failed to create replay file: {:?}", err);
+            return;
+        }
+        println!("[Server] Recording replay to {}", path);
+    }
+
+    if let Err(err) = Server::run(server_addr, server) {
         println!("[Server] [Fatal] {:?}", err);
     }
 
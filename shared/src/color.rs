@@ -1,5 +1,5 @@
 // Color Name Mapping ---------------------------------------------------------
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, RustcEncodable, RustcDecodable)]
 pub enum ColorName {
     Grey,
     Red,
@@ -31,6 +31,20 @@ impl ColorName {
         }
     }
 
+    pub fn parse(name: &str) -> Option<ColorName> {
+        match &name.to_lowercase()[..] {
+            "red" => Some(ColorName::Red),
+            "orange" => Some(ColorName::Orange),
+            "yellow" => Some(ColorName::Yellow),
+            "green" => Some(ColorName::Green),
+            "teal" => Some(ColorName::Teal),
+            "blue" => Some(ColorName::Blue),
+            "purple" => Some(ColorName::Purple),
+            "pink" => Some(ColorName::Pink),
+            _ => None
+        }
+    }
+
     pub fn from_u8(value: u8) -> ColorName {
         match value {
             0 => ColorName::Grey,
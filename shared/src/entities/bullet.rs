@@ -0,0 +1,92 @@
+// External Dependencies ------------------------------------------------------
+use lithium::{
+    Entity,
+    BaseEntity,
+    CollisionShape,
+    Level,
+    DefaultRenderer
+};
+
+
+// Internal Dependencies ------------------------------------------------------
+use super::DefaultDrawable;
+use input::SharedInput;
+use state::SharedState;
+use level::SharedLevel;
+
+
+// Bullet Logic Implementation ------------------------------------------------
+pub struct Bullet;
+
+impl Bullet {
+
+    pub fn create_entity() -> Entity<SharedState, SharedInput, SharedLevel, DefaultRenderer> {
+        Entity::new(
+            Box::new(Bullet),
+            Box::new(DefaultDrawable)
+        )
+    }
+
+    // Minimum number of ticks between two fired bullets
+    pub fn fire_interval() -> u8 {
+        6
+    }
+
+    // Number of ticks before a bullet is destroyed
+    pub fn lifetime() -> u8 {
+        45
+    }
+
+    pub fn damage() -> u8 {
+        20
+    }
+
+    // Initial bullet state when fired from a ship with the given state
+    pub fn fire(ship: &SharedState, dt: f32) -> SharedState {
+        let speed = 150.0 * dt;
+        SharedState {
+            x: ship.x + ship.r.cos() * 12.0,
+            y: ship.y + ship.r.sin() * 12.0,
+            r: ship.r,
+            mx: ship.mx + ship.r.cos() * speed,
+            my: ship.my + ship.r.sin() * speed,
+            flags: ship.flags & 0b1111_0000,
+            hp: 0,
+            shield: 0
+        }
+    }
+
+}
+
+
+// Trait Implementations ------------------------------------------------------
+impl BaseEntity<SharedState, SharedInput, SharedLevel> for Bullet {
+
+    fn type_id(&self) -> u8 {
+        1
+    }
+
+    fn collision_shape(&self, _: &SharedState) -> Option<CollisionShape> {
+        Some(CollisionShape::Circle(2.0))
+    }
+
+    fn apply_input(
+        &mut self,
+        _: &Level<SharedState, SharedLevel>,
+        _: &mut SharedState, _: &SharedInput,
+        _: f32
+    ) {
+    }
+
+    fn update(
+        &mut self,
+        level: &Level<SharedState, SharedLevel>,
+        state: &mut SharedState,
+        _: f32
+    ) {
+        state.x += state.mx;
+        state.y += state.my;
+        level.limit_state(state);
+    }
+
+}
@@ -3,20 +3,24 @@ use lithium::{Entity, EntityRegistry, DrawableEntity, DefaultRenderer};
 
 
 // Internal Dependencies ------------------------------------------------------
+use input::SharedInput;
 use state::SharedState;
 use level::SharedLevel;
 
+mod bullet;
 mod ship;
+pub use entities::bullet::Bullet;
 pub use entities::ship::Ship;
 
 
 // Entity Registry ------------------------------------------------------------
 pub struct Registry;
-impl EntityRegistry<SharedState, SharedLevel, DefaultRenderer> for Registry {
-    fn entity_from_type_id(&self, type_id: u8) -> Entity<SharedState, SharedLevel, DefaultRenderer> {
+impl EntityRegistry<SharedState, SharedInput, SharedLevel, DefaultRenderer> for Registry {
+    fn entity_from_type_id(&self, type_id: u8) -> Option<Entity<SharedState, SharedInput, SharedLevel, DefaultRenderer>> {
         match type_id {
-            0 => ship::Ship::create_entity(1.0),
-            _ => unreachable!()
+            0 => Some(ship::Ship::create_entity(1.0)),
+            1 => Some(bullet::Bullet::create_entity()),
+            _ => None
         }
     }
 }
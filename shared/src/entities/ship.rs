@@ -1,9 +1,9 @@
 // External Dependencies ------------------------------------------------------
-use std::f32;
+use std::{cmp, f32};
 use lithium::{
     Entity,
-    EntityInput,
     BaseEntity,
+    CollisionShape,
     Level,
     DefaultRenderer
 };
@@ -11,6 +11,7 @@ use lithium::{
 
 // Internal Dependencies ------------------------------------------------------
 use super::DefaultDrawable;
+use input::SharedInput;
 use state::SharedState;
 use level::SharedLevel;
 
@@ -19,23 +20,42 @@ use level::SharedLevel;
 pub struct Ship {
     max_speed: f32,
     acceleration: f32,
-    rotation: f32
+    rotation: f32,
+    radius: f32
 }
 
 impl Ship {
 
-    pub fn create_entity(scale: f32) -> Entity<SharedState, SharedLevel, DefaultRenderer> {
+    pub fn create_entity(scale: f32) -> Entity<SharedState, SharedInput, SharedLevel, DefaultRenderer> {
         Entity::new(
             Box::new(Ship::new(scale)),
             Box::new(DefaultDrawable)
         )
     }
 
+    pub fn max_hp() -> u8 {
+        100
+    }
+
+    pub fn max_shield() -> u8 {
+        50
+    }
+
+    // Applies damage to the shield first and then to the hull, returns
+    // whether the ship was destroyed
+    pub fn damage(state: &mut SharedState, damage: u8) -> bool {
+        let absorbed = cmp::min(state.shield, damage);
+        state.shield -= absorbed;
+        state.hp = state.hp.saturating_sub(damage - absorbed);
+        state.hp == 0
+    }
+
     pub fn new(scale: f32) -> Ship {
         Ship {
             max_speed: 90.0 * scale,
             acceleration: 2.0 * scale,
-            rotation: 120.0
+            rotation: 120.0,
+            radius: 8.0 * scale
         }
     }
 
@@ -43,35 +63,51 @@ impl Ship {
 
 
 // Trait Implementations ------------------------------------------------------
-impl BaseEntity<SharedState, SharedLevel> for Ship {
+impl BaseEntity<SharedState, SharedInput, SharedLevel> for Ship {
 
     fn type_id(&self) -> u8 {
         0
     }
 
+    fn collision_shape(&self, _: &SharedState) -> Option<CollisionShape> {
+        Some(CollisionShape::Circle(self.radius))
+    }
+
+    // Ships are updated more often than bullets at the same distance
+    fn priority(&self, _: &SharedState) -> f32 {
+        2.0
+    }
+
     fn apply_input(
         &mut self,
         level: &Level<SharedState, SharedLevel>,
-        state: &mut SharedState, input: &EntityInput,
+        state: &mut SharedState, input: &SharedInput,
         dt: f32
     ) {
 
-        let mut steer = 0.0;
-        if input.fields & 0x01 == 0x01 {
-            steer -= 1.0;
-        }
+        let max_turn = f32::consts::PI / 180.0 * self.rotation * dt;
+        let steer = match input.aim() {
 
-        if input.fields & 0x02 == 0x02 {
-            steer += 1.0;
-        }
+            // Turn towards the aimed at direction without exceeding the
+            // ship's turn rate
+            Some(aim) => {
+                let diff = aim - state.r;
+                let diff = diff.sin().atan2(diff.cos());
+                (diff / max_turn).max(-1.0).min(1.0)
+            },
+
+            None => input.steer()
+
+        };
 
-        state.r += f32::consts::PI / 180.0 * self.rotation * (steer / (1.0 / dt));
+        state.r += max_turn * steer;
 
-        if input.fields & 0x04 == 0x04 {
+        let thrust = input.thrust();
+        if thrust > 0.0 {
             // Constant time acceleration
             let m = 60.0 / (1.0 / dt);
-            state.mx += state.r.cos() * self.acceleration * dt * m;
-            state.my += state.r.sin() * self.acceleration * dt * m;
+            state.mx += state.r.cos() * self.acceleration * dt * m * thrust;
+            state.my += state.r.sin() * self.acceleration * dt * m * thrust;
             state.flags |= 0x02;
 
         } else {
@@ -1,24 +1,64 @@
 // External Dependencies ------------------------------------------------------
-use lithium::Event;
+use lithium::{EntityHandle, Event};
 
 
 // Internal Dependencies ------------------------------------------------------
+use color::ColorName;
 use command::SharedCommand;
+use score::PlayerScore;
 
 // Game Events ----------------------------------------------------------------
 #[derive(Debug, RustcEncodable, RustcDecodable)]
 pub enum SharedEvent {
-    JoinGame,
-    GameJoined,
+    JoinGame {
+        name: String,
+        color: Option<ColorName>
+    },
+    JoinRejected(JoinError),
+    GameJoined {
+        id: u32,
+        color: ColorName
+    },
     LeaveGame,
-    PlayerJoined,
-    PlayerLeft,
+    Spectate,
+    SpectateAccepted,
+    PlayerJoined {
+        id: u32,
+        name: String,
+        color: ColorName
+    },
+    PlayerLeft {
+        id: u32,
+        name: String
+    },
+    PlayerKilled {
+        killer: u32,
+        victim: u32
+    },
+    ScoreUpdate(Vec<PlayerScore>),
+    RoundEnded {
+        winner: Option<u8>
+    },
+    RoundStarted,
+    BulletConfirmed {
+        local_id: u8,
+        entity: EntityHandle
+    },
     Command(SharedCommand),
     Unknown
 }
 
 impl Event for SharedEvent {}
 
+#[derive(Debug, RustcEncodable, RustcDecodable)]
+pub enum JoinError {
+    InvalidName,
+    DuplicateName,
+    ColorUnavailable,
+    GameFull,
+    SpectatorsFull
+}
+
 impl Default for SharedEvent {
     fn default() -> SharedEvent {
         SharedEvent::Unknown
@@ -0,0 +1,267 @@
+// External Dependencies ------------------------------------------------------
+use lithium::{DecodeError, EntityInput};
+use bincode::SizeLimit;
+use bincode::rustc_serialize::{encode, decode};
+
+
+// Input Buttons --------------------------------------------------------------
+pub const INPUT_FIRE: u8 = 0b0000_0001;
+pub const INPUT_AIM: u8 = 0b0000_0010;
+
+
+// Delta Encoding -------------------------------------------------------------
+const DELTA_STEER: u8 = 0b0000_0001;
+const DELTA_THRUST: u8 = 0b0000_0010;
+const DELTA_AIM: u8 = 0b0000_0100;
+const DELTA_BUTTONS: u8 = 0b0000_1000;
+
+
+// Entity Input ---------------------------------------------------------------
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct SharedInput {
+    pub tick: u8,
+    pub buttons: u8,
+
+    // Analog axes, steering ranges from -1.0 (left) to 1.0 (right) and thrust
+    // from 0.0 to 1.0
+    pub steer: f32,
+    pub thrust: f32,
+
+    // Absolute angle the ship should turn towards while INPUT_AIM is set
+    pub aim: f32
+}
+
+impl SharedInput {
+
+    // Inputs are received from clients so all axes are sanitized before use
+    pub fn steer(&self) -> f32 {
+        SharedInput::axis(self.steer, -1.0, 1.0)
+    }
+
+    pub fn thrust(&self) -> f32 {
+        SharedInput::axis(self.thrust, 0.0, 1.0)
+    }
+
+    pub fn aim(&self) -> Option<f32> {
+        if self.buttons & INPUT_AIM == INPUT_AIM && self.aim.is_finite() {
+            Some(self.aim)
+
+        } else {
+            None
+        }
+    }
+
+    pub fn fire(&self) -> bool {
+        self.buttons & INPUT_FIRE == INPUT_FIRE
+    }
+
+    fn axis(value: f32, min: f32, max: f32) -> f32 {
+        if value.is_finite() {
+            value.max(min).min(max)
+
+        } else {
+            0.0
+        }
+    }
+
+}
+
+impl EntityInput for SharedInput {
+
+    fn encoded_size() -> usize {
+        14
+    }
+
+    fn from_serialized(data: &[u8]) -> Result<SharedInput, DecodeError> {
+        if data.len() >= SharedInput::encoded_size() {
+            decode::<SharedInput>(data).map_err(DecodeError::from)
+
+        } else {
+            Err(DecodeError::Truncated)
+        }
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        encode(&self, SizeLimit::Infinite).unwrap()
+    }
+
+    fn tick(&self) -> u8 {
+        self.tick
+    }
+
+    fn set_tick(&mut self, tick: u8) {
+        self.tick = tick;
+    }
+
+    // Missing inputs never fire in order to prevent phantom shots
+    fn extrapolate(&self) -> SharedInput {
+        SharedInput {
+            buttons: self.buttons & !INPUT_FIRE,
+            .. self.clone()
+        }
+    }
+
+    // The tick is not part of the delta since it is sent along with each run
+    fn serialize_delta(&self, base: &SharedInput) -> Vec<u8> {
+
+        let mut mask = 0;
+        let mut data = vec![0];
+        for (i, &(value, base_value)) in [
+            (self.steer, base.steer),
+            (self.thrust, base.thrust),
+            (self.aim, base.aim)
+
+        ].iter().enumerate() {
+            if value != base_value {
+                mask |= 1 << i;
+                data.extend(encode(&value, SizeLimit::Infinite).unwrap());
+            }
+        }
+
+        if self.buttons != base.buttons {
+            mask |= DELTA_BUTTONS;
+            data.push(self.buttons);
+        }
+
+        data[0] = mask;
+        data
+
+    }
+
+    fn from_serialized_delta(
+        base: &SharedInput, data: &[u8]
+
+    ) -> Result<(SharedInput, usize), DecodeError> {
+
+        if data.is_empty() {
+            return Err(DecodeError::Truncated);
+        }
+
+        // Make sure that all fields listed in the mask are present
+        let mask = data[0];
+        let size = 1 + (mask & (DELTA_STEER | DELTA_THRUST | DELTA_AIM)).count_ones() as usize * 4
+                     + (mask & DELTA_BUTTONS).count_ones() as usize;
+
+        if data.len() < size {
+            return Err(DecodeError::Truncated);
+        }
+
+        let mut input = base.clone();
+        let mut i = 1;
+
+        for (bit, value) in [
+            &mut input.steer,
+            &mut input.thrust,
+            &mut input.aim
+
+        ].iter_mut().enumerate() {
+            if mask & (1 << bit) != 0 {
+                **value = match decode::<f32>(&data[i..]) {
+                    Ok(value) => value,
+                    Err(err) => return Err(DecodeError::from(err))
+                };
+                i += 4;
+            }
+        }
+
+        if mask & DELTA_BUTTONS != 0 {
+            input.buttons = data[i];
+            i += 1;
+        }
+
+        Ok((input, i))
+
+    }
+
+}
+
+impl Default for SharedInput {
+    fn default() -> SharedInput {
+        SharedInput {
+            tick: 0,
+            buttons: 0,
+            steer: 0.0,
+            thrust: 0.0,
+            aim: 0.0
+        }
+    }
+}
+
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::f32;
+    use lithium::EntityInput;
+    use super::{SharedInput, INPUT_AIM, INPUT_FIRE};
+
+    fn input() -> SharedInput {
+        SharedInput {
+            tick: 4,
+            buttons: INPUT_FIRE | INPUT_AIM,
+            steer: -0.5,
+            thrust: 0.75,
+            aim: 1.25
+        }
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+
+        let base = SharedInput::default();
+        let data = input().serialize_delta(&base);
+        assert_eq!(data.len(), 1 + 3 * 4 + 1);
+
+        let (decoded, read) = SharedInput::from_serialized_delta(&base, &data).unwrap();
+        assert_eq!(read, data.len());
+        assert_eq!(decoded.buttons, INPUT_FIRE | INPUT_AIM);
+        assert_eq!((decoded.steer, decoded.thrust, decoded.aim), (-0.5, 0.75, 1.25));
+
+        for len in 0..data.len() {
+            assert!(SharedInput::from_serialized_delta(&base, &data[..len]).is_err());
+        }
+
+        // Only changed fields are encoded
+        let mut changed = input();
+        changed.thrust = 0.0;
+        let data = changed.serialize_delta(&input());
+        assert_eq!(data.len(), 1 + 4);
+
+        let (decoded, read) = SharedInput::from_serialized_delta(&input(), &data).unwrap();
+        assert_eq!(read, 5);
+        assert_eq!((decoded.steer, decoded.thrust, decoded.aim), (-0.5, 0.0, 1.25));
+
+        assert_eq!(input().serialize_delta(&input()), vec![0]);
+
+    }
+
+    #[test]
+    fn test_sanitized_axes() {
+
+        let mut input = input();
+        assert_eq!(input.aim(), Some(1.25));
+
+        input.steer = f32::NAN;
+        input.thrust = 5.0;
+        input.aim = f32::INFINITY;
+        assert_eq!(input.steer(), 0.0);
+        assert_eq!(input.thrust(), 1.0);
+        assert_eq!(input.aim(), None);
+
+        input.aim = 0.5;
+        input.buttons = INPUT_FIRE;
+        assert_eq!(input.aim(), None);
+
+    }
+
+    #[test]
+    fn test_extrapolate_never_fires() {
+        let extrapolated = input().extrapolate();
+        assert!(!extrapolated.fire());
+        assert_eq!(extrapolated.aim(), Some(1.25));
+        assert_eq!(extrapolated.thrust(), 0.75);
+    }
+
+}
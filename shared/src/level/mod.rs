@@ -1,5 +1,5 @@
 // External Dependencies ------------------------------------------------------
-use lithium::{Level, DrawableLevel};
+use lithium::{BaseLevel, DecodeError, Level, DrawableLevel};
 
 
 // Internal Dependencies ------------------------------------------------------
@@ -39,6 +39,15 @@ impl SharedLevel {
         )
     }
 
+    pub fn create_from_serialized(
+        data: &[u8]
+
+    ) -> Result<Level<SharedState, SharedLevel>, DecodeError> {
+        SharedLevel::from_serialized(data).map(|level| {
+            Level::new(level, Box::new(NoneDrawable))
+        })
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
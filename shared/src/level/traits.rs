@@ -2,7 +2,8 @@
 use std::f32;
 use bincode::SizeLimit;
 use bincode::rustc_serialize::{encode, decode};
-use lithium::{BaseLevel, Renderer};
+use lithium::{BaseLevel, DecodeError, EntityState, Renderer};
+use lithium::collision;
 
 
 // Internal Dependencies ------------------------------------------------------
@@ -15,7 +16,7 @@ impl BaseLevel<SharedState> for SharedLevel {
 
     fn limit_state(&self, state: &mut SharedState) {
 
-        let width = (self.height + self.border * 2) as f32;
+        let width = (self.width + self.border * 2) as f32;
         if state.x < 0.0 {
             state.x += width;
 
@@ -33,6 +34,13 @@ impl BaseLevel<SharedState> for SharedLevel {
 
     }
 
+    fn wrap_bounds(&self) -> Option<(f32, f32)> {
+        Some((
+            (self.width + self.border * 2) as f32,
+            (self.height + self.border * 2) as f32
+        ))
+    }
+
     fn interpolate_entity_state(
         &self,
         renderer: &mut Renderer,
@@ -65,17 +73,74 @@ impl BaseLevel<SharedState> for SharedLevel {
             y: y - self.border as f32,
             mx: last.mx,
             my: last.my,
-            flags: current.flags
+            flags: current.flags,
+            hp: current.hp,
+            shield: current.shield
         }
 
     }
 
+    fn blend_entity_state(
+        &self,
+        from: &SharedState, to: &SharedState,
+        u: f32
+
+    ) -> SharedState {
+
+        // Blend along the shortest path across the level boundaries
+        let (dx, dy) = collision::delta((from.x, from.y), (to.x, to.y), self.wrap_bounds());
+
+        let mr = to.r - from.r;
+        let mut state = SharedState {
+            r: (from.r + mr.sin().atan2(mr.cos()) * u) % (f32::consts::PI * 2.0),
+            x: from.x + dx * u,
+            y: from.y + dy * u,
+            mx: from.mx + (to.mx - from.mx) * u,
+            my: from.my + (to.my - from.my) * u,
+            flags: to.flags,
+            hp: to.hp,
+            shield: to.shield
+        };
+
+        self.limit_state(&mut state);
+        state
+
+    }
+
+    // Entities keep moving with their last known velocity
+    fn extrapolate_entity_state(&self, state: &SharedState, ticks: f32) -> SharedState {
+
+        let mut state = SharedState {
+            x: state.x + state.mx * ticks,
+            y: state.y + state.my * ticks,
+            .. state.clone()
+        };
+
+        self.limit_state(&mut state);
+        state
+
+    }
+
+    fn entity_state_distance(&self, from: &SharedState, to: &SharedState) -> (f32, f32) {
+        collision::delta((from.x, from.y), (to.x, to.y), self.wrap_bounds())
+    }
+
+    fn offset_entity_state(&self, state: &mut SharedState, offset: (f32, f32)) {
+        state.x += offset.0;
+        state.y += offset.1;
+    }
+
     fn encoded_size() -> usize {
         12
     }
 
-    fn from_serialized(data: &[u8]) -> Self {
-        decode::<Self>(data).unwrap()
+    fn from_serialized(data: &[u8]) -> Result<Self, DecodeError> {
+        if data.len() >= SharedLevel::encoded_size() {
+            decode::<Self>(data).map_err(DecodeError::from)
+
+        } else {
+            Err(DecodeError::Truncated)
+        }
     }
 
     fn serialize(&self) -> Vec<u8> {
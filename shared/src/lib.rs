@@ -9,7 +9,9 @@ mod color;
 mod command;
 pub mod entities;
 mod event;
+mod input;
 mod level;
+mod score;
 mod state;
 
 
@@ -17,9 +19,15 @@ mod state;
 pub use lithium as Lithium;
 pub use color::Color;
 pub use color::ColorName;
-pub use event::SharedEvent;
+pub use event::{JoinError, SharedEvent};
 pub use command::SharedCommand;
+pub use input::{SharedInput, INPUT_AIM, INPUT_FIRE};
 pub use level::SharedLevel;
+pub use score::PlayerScore;
 pub use state::SharedState;
 pub use entities::Registry as SharedRegistry;
 
+
+// Versioning -----------------------------------------------------------------
+pub const GAME_VERSION: u16 = 5;
+
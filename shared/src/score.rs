@@ -0,0 +1,30 @@
+// Player Scores --------------------------------------------------------------
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct PlayerScore {
+    pub id: u32,
+    pub color: u8,
+    pub kills: u16,
+    pub deaths: u16,
+    pub score: i16
+}
+
+impl PlayerScore {
+
+    pub fn new(id: u32, color: u8) -> PlayerScore {
+        PlayerScore {
+            id: id,
+            color: color,
+            kills: 0,
+            deaths: 0,
+            score: 0
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.kills = 0;
+        self.deaths = 0;
+        self.score = 0;
+    }
+
+}
+
@@ -1,9 +1,15 @@
 // External Dependencies ------------------------------------------------------
-use lithium::EntityState;
+use lithium::{DecodeError, EntityState};
 use bincode::SizeLimit;
 use bincode::rustc_serialize::{encode, decode};
 
 
+// Delta Encoding -------------------------------------------------------------
+const DELTA_FLAGS: u8 = 0b0010_0000;
+const DELTA_HP: u8 = 0b0100_0000;
+const DELTA_SHIELD: u8 = 0b1000_0000;
+
+
 // Entity State ---------------------------------------------------------------
 #[derive(Debug, RustcEncodable, RustcDecodable)]
 pub struct SharedState {
@@ -12,23 +18,130 @@ pub struct SharedState {
     pub r: f32,
     pub mx: f32,
     pub my: f32,
-    pub flags: u8
+    pub flags: u8,
+    pub hp: u8,
+    pub shield: u8
 }
 
 impl EntityState for SharedState {
 
     fn encoded_size() -> usize {
-        21
+        23
     }
 
-    fn from_serialized(data: &[u8]) -> SharedState {
-        decode::<SharedState>(data).unwrap()
+    fn from_serialized(data: &[u8]) -> Result<SharedState, DecodeError> {
+        if data.len() >= SharedState::encoded_size() {
+            decode::<SharedState>(data).map_err(DecodeError::from)
+
+        } else {
+            Err(DecodeError::Truncated)
+        }
     }
 
     fn serialize(&self) -> Vec<u8> {
         encode(&self, SizeLimit::Infinite).unwrap()
     }
 
+    fn serialize_delta(&self, base: &SharedState) -> Option<Vec<u8>> {
+
+        let mut mask = 0;
+        let mut data = vec![0];
+        for (i, &(value, base_value)) in [
+            (self.x, base.x),
+            (self.y, base.y),
+            (self.r, base.r),
+            (self.mx, base.mx),
+            (self.my, base.my)
+
+        ].iter().enumerate() {
+            if value != base_value {
+                mask |= 1 << i;
+                data.extend(encode(&value, SizeLimit::Infinite).unwrap());
+            }
+        }
+
+        if self.flags != base.flags {
+            mask |= DELTA_FLAGS;
+            data.push(self.flags);
+        }
+
+        if self.hp != base.hp {
+            mask |= DELTA_HP;
+            data.push(self.hp);
+        }
+
+        if self.shield != base.shield {
+            mask |= DELTA_SHIELD;
+            data.push(self.shield);
+        }
+
+        if mask != 0 {
+            data[0] = mask;
+            Some(data)
+
+        } else {
+            None
+        }
+
+    }
+
+    fn from_serialized_delta(
+        base: &SharedState, data: &[u8]
+
+    ) -> Result<(SharedState, usize), DecodeError> {
+
+        if data.is_empty() {
+            return Err(DecodeError::Truncated);
+        }
+
+        // Make sure that all fields listed in the mask are present
+        let mask = data[0];
+        let size = 1 + (mask & 0b0001_1111).count_ones() as usize * 4
+                     + (mask & 0b1110_0000).count_ones() as usize;
+
+        if data.len() < size {
+            return Err(DecodeError::Truncated);
+        }
+
+        let mut state = base.clone();
+        let mut i = 1;
+
+        for (bit, value) in [
+            &mut state.x,
+            &mut state.y,
+            &mut state.r,
+            &mut state.mx,
+            &mut state.my
+
+        ].iter_mut().enumerate() {
+            if mask & (1 << bit) != 0 {
+                **value = match decode::<f32>(&data[i..]) {
+                    Ok(value) => value,
+                    Err(err) => return Err(DecodeError::from(err))
+                };
+                i += 4;
+            }
+        }
+
+        if mask & DELTA_FLAGS != 0 {
+            state.flags = data[i];
+            i += 1;
+        }
+
+        if mask & DELTA_HP != 0 {
+            state.hp = data[i];
+            i += 1;
+        }
+
+        if mask & DELTA_SHIELD != 0 {
+            state.shield = data[i];
+            i += 1;
+        }
+
+        Ok((state, i))
+
+    }
+
     fn set_to(&mut self, state: &Self) {
         self.x = state.x;
         self.y = state.y;
@@ -36,6 +149,8 @@ impl EntityState for SharedState {
         self.mx = state.mx;
         self.my = state.my;
         self.flags = state.flags;
+        self.hp = state.hp;
+        self.shield = state.shield;
     }
 
     fn clone(&self) -> Self {
@@ -45,7 +160,9 @@ impl EntityState for SharedState {
             r: self.r,
             mx: self.mx,
             my: self.my,
-            flags: self.flags
+            flags: self.flags,
+            hp: self.hp,
+            shield: self.shield
         }
     }
 
@@ -57,6 +174,10 @@ impl EntityState for SharedState {
         self.flags = flags;
     }
 
+    fn position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
 }
 
 impl Default for SharedState {
@@ -68,7 +189,9 @@ impl Default for SharedState {
             r: 0.0,
             mx: 0.0,
             my: 0.0,
-            flags: 0
+            flags: 0,
+            hp: 0,
+            shield: 0
         }
     }
 